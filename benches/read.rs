@@ -0,0 +1,63 @@
+//! Stream a large file by the tokio file or the blocking pool and by io_uring, with and
+//! without the reads ahead, e.g. `cargo bench --features io-uring`.
+use std::time::{Duration, Instant};
+
+use blockless_hyper_file::FileService;
+use hyper::{body::HttpBody, service::Service, Request};
+
+const FILE_SIZE: usize = 256 * 1024 * 1024;
+const ROUNDS: u32 = 8;
+
+async fn stream(service: &mut FileService) -> usize {
+    let request = Request::get("/large.bin").body(()).unwrap();
+    let mut body = service.call(request).await.unwrap().into_body();
+    let mut len = 0;
+    while let Some(chunk) = body.data().await {
+        len += chunk.unwrap().len();
+    }
+    len
+}
+
+async fn bench(name: &str, mut service: FileService) {
+    // warm up the page cache, the disk is not measured.
+    assert_eq!(stream(&mut service).await, FILE_SIZE);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        assert_eq!(stream(&mut service).await, FILE_SIZE);
+    }
+    let elapsed = start.elapsed();
+    let throughput = (FILE_SIZE as f64 * ROUNDS as f64) / elapsed.as_secs_f64() / 1e6;
+    let average = elapsed / ROUNDS;
+    println!("{name:<24} {average:>10.1?} per file {throughput:>8.0} MB/s");
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join("blockless-bench-read");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("large.bin");
+    if std::fs::metadata(&path).map(|m| m.len()).ok() != Some(FILE_SIZE as u64) {
+        let content: Vec<u8> = (0..FILE_SIZE).map(|n| n as u8).collect();
+        std::fs::write(&path, content).unwrap();
+    }
+    let root = dir.to_str().unwrap();
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        for read_ahead in [0, 4] {
+            let service = || FileService::new(root).read_ahead(read_ahead);
+            // the chunks read ahead are read by the blocking pool instead of the tokio file.
+            let path = if read_ahead == 0 {
+                "tokio::fs"
+            } else {
+                "blocking"
+            };
+            let name = format!("{path} read_ahead={read_ahead}");
+            bench(&name, service()).await;
+            let name = format!("io_uring read_ahead={read_ahead}");
+            bench(&name, service().io_uring(true)).await;
+        }
+    });
+    runtime.shutdown_timeout(Duration::from_secs(1));
+}
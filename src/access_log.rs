@@ -0,0 +1,322 @@
+use std::{
+    fmt,
+    io::Write,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime},
+};
+
+use hyper::{header, header::HeaderName, HeaderMap, Request, Response, StatusCode};
+
+use crate::{
+    auth,
+    body::{Body, ObservedBytesStream},
+};
+
+/// The format of the access log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// the Common Log Format, e.g.
+    /// `127.0.0.1 - frank [10/Oct/2000:13:55:36 +0000] "GET /a.gif HTTP/1.0" 200 2326`.
+    Common,
+    /// the Common Log Format with the quoted `Referer` and `User-Agent`.
+    Combined,
+    /// the JSON object of the fields per line, e.g. `{"method":"GET","path":"/a.gif",..}`,
+    /// the missing field is `null`.
+    Json,
+}
+
+/// the header of the request id of the JSON log, unless it is set by
+/// `FileService::request_id`.
+const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+type Sink = dyn Fn(&str) + Send + Sync;
+
+/// The access log emits one line of every response after the body is finished, the size
+/// is the bytes of the body actually sent, the aborted body is logged with the bytes sent.
+#[derive(Clone)]
+pub struct AccessLog {
+    format: LogFormat,
+    sink: Arc<Sink>,
+}
+
+impl fmt::Debug for AccessLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessLog")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl AccessLog {
+    /// write the lines to the writer, e.g. the file opened to append. the writer is not
+    /// flushed, the write error is ignored.
+    pub fn writer<W: Write + Send + 'static>(format: LogFormat, writer: W) -> Self {
+        let writer = Mutex::new(writer);
+        Self::callback(format, move |line| {
+            let mut writer = writer.lock().unwrap();
+            let _ = writer.write_all(format!("{line}\n").as_bytes());
+        })
+    }
+
+    /// call the callback with every line without the newline, e.g. to the log crate.
+    pub fn callback<F>(format: LogFormat, callback: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        Self {
+            format,
+            sink: Arc::new(callback),
+        }
+    }
+
+    /// the fields of the request before it is rewritten.
+    pub(crate) fn record<B>(
+        &self,
+        request: &Request<B>,
+        ip: Option<IpAddr>,
+        request_id: Option<&HeaderName>,
+    ) -> LogRecord {
+        let headers = request.headers();
+        let target = request
+            .uri()
+            .path_and_query()
+            .map_or("/", |p| p.as_str())
+            .to_string();
+        LogRecord {
+            remote: ip,
+            user: auth::basic_credentials(headers).map(|(name, _)| name),
+            time: SystemTime::now(),
+            start: Instant::now(),
+            method: request.method().to_string(),
+            target,
+            version: format!("{:?}", request.version()),
+            range: header_value(headers, header::RANGE),
+            referer: header_value(headers, header::REFERER),
+            user_agent: header_value(headers, header::USER_AGENT),
+            request_id: header_value(headers, request_id.cloned().unwrap_or(REQUEST_ID)),
+        }
+    }
+
+    /// log the line of the response when the body is finished.
+    pub(crate) fn observe(&self, record: LogRecord, resp: &mut Response<Body>) {
+        let log = self.clone();
+        let status = resp.status();
+        let on_finish = move |sent: u64, _| (log.sink)(&log.line(&record, status, sent));
+        let body = std::mem::replace(resp.body_mut(), Body::Empty);
+        *resp.body_mut() = Body::Observed(ObservedBytesStream::new(body, Box::new(on_finish)));
+    }
+
+    fn line(&self, record: &LogRecord, status: StatusCode, sent: u64) -> String {
+        if self.format == LogFormat::Json {
+            return json_line(record, status, sent);
+        }
+        let remote = record
+            .remote
+            .map_or_else(|| "-".into(), |ip| ip.to_string());
+        let request_line = format!("{} {} {}", record.method, record.target, record.version);
+        let user = record.user.as_deref().map_or_else(|| "-".into(), escape);
+        let size = if sent == 0 {
+            "-".to_string()
+        } else {
+            sent.to_string()
+        };
+        let mut line = format!(
+            "{} - {} [{}] \"{}\" {} {}",
+            remote,
+            user,
+            clf_time(record.time),
+            escape(&request_line),
+            status.as_u16(),
+            size,
+        );
+        if self.format == LogFormat::Combined {
+            let quoted = |v: &Option<String>| v.as_deref().map_or_else(|| "-".into(), escape);
+            line.push_str(&format!(
+                " \"{}\" \"{}\"",
+                quoted(&record.referer),
+                quoted(&record.user_agent)
+            ));
+        }
+        line
+    }
+}
+
+/// The fields of the request logged after the response.
+pub(crate) struct LogRecord {
+    remote: Option<IpAddr>,
+    user: Option<String>,
+    time: SystemTime,
+    start: Instant,
+    method: String,
+    // the path and the query of the request.
+    target: String,
+    version: String,
+    range: Option<String>,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+}
+
+fn header_value(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+}
+
+/// escape the quote, the backslash and the control characters of the client, which
+/// could forge the lines.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// the JSON string of the value, `null` if it is missing.
+fn json_string(value: Option<&str>) -> String {
+    let value = match value {
+        Some(value) => value,
+        None => return "null".to_string(),
+    };
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn json_line(record: &LogRecord, status: StatusCode, sent: u64) -> String {
+    let (path, query) = match record.target.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (record.target.as_str(), None),
+    };
+    let remote = record.remote.map(|ip| ip.to_string());
+    let fields = [
+        ("time", json_string(Some(&iso_time(record.time)))),
+        ("remote", json_string(remote.as_deref())),
+        ("user", json_string(record.user.as_deref())),
+        ("method", json_string(Some(&record.method))),
+        ("path", json_string(Some(path))),
+        ("query", json_string(query)),
+        ("protocol", json_string(Some(&record.version))),
+        ("status", status.as_u16().to_string()),
+        ("size", sent.to_string()),
+        (
+            "duration_ms",
+            record.start.elapsed().as_millis().to_string(),
+        ),
+        ("range", json_string(record.range.as_deref())),
+        ("referer", json_string(record.referer.as_deref())),
+        ("user_agent", json_string(record.user_agent.as_deref())),
+        ("request_id", json_string(record.request_id.as_deref())),
+    ];
+    let members: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("\"{name}\":{value}"))
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
+
+/// the fields of the HTTP date, e.g. `["Tue,", "10", "Oct", "2000", "13:55:36", "GMT"]`.
+fn date_fields(time: SystemTime) -> Option<[String; 4]> {
+    let date = httpdate::fmt_http_date(time);
+    match date.split(' ').collect::<Vec<_>>()[..] {
+        [_, day, month, year, time, _] => Some([day, month, year, time].map(String::from)),
+        _ => None,
+    }
+}
+
+/// the time of the JSON log in UTC, e.g. `2000-10-10T13:55:36Z`.
+fn iso_time(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    match date_fields(time) {
+        Some([day, month, year, time]) => {
+            let month = MONTHS.iter().position(|m| *m == month).unwrap_or(0) + 1;
+            format!("{year}-{month:02}-{day}T{time}Z")
+        }
+        None => httpdate::fmt_http_date(time),
+    }
+}
+
+/// the time of the log in UTC, e.g. `10/Oct/2000:13:55:36 +0000`.
+fn clf_time(time: SystemTime) -> String {
+    match date_fields(time) {
+        Some([day, month, year, time]) => format!("{day}/{month}/{year}:{time} +0000"),
+        None => httpdate::fmt_http_date(time),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_line() {
+        let log = AccessLog::callback(LogFormat::Combined, |_| {});
+        let request = Request::get("/a.gif?v=1")
+            .header(header::AUTHORIZATION, "Basic ZnJhbms6cHc=")
+            .header(header::USER_AGENT, "bad\"agent\t")
+            .body(())
+            .unwrap();
+        let mut record = log.record(&request, "127.0.0.1".parse().ok(), None);
+        record.time = SystemTime::UNIX_EPOCH + Duration::from_secs(971186136);
+        let line = log.line(&record, StatusCode::OK, 2326);
+        let expected = "127.0.0.1 - frank [10/Oct/2000:13:55:36 +0000] \
+            \"GET /a.gif?v=1 HTTP/1.1\" 200 2326 \"-\" \"bad\\\"agent\\x09\"";
+        assert_eq!(line, expected);
+        let log = AccessLog::callback(LogFormat::Common, |_| {});
+        let record = log.record(&Request::head("/").body(()).unwrap(), None, None);
+        let line = log.line(&record, StatusCode::NOT_MODIFIED, 0);
+        assert!(line.starts_with("- - - ["));
+        assert!(line.ends_with("] \"HEAD / HTTP/1.1\" 304 -"));
+    }
+
+    #[test]
+    fn test_json_line() {
+        let log = AccessLog::callback(LogFormat::Json, |_| {});
+        let request = Request::get("/a%20b.mp4?t=1")
+            .header(header::RANGE, "bytes=0-99")
+            .header(header::USER_AGENT, "say \"hi\"")
+            .header("x-request-id", "r-1")
+            .body(())
+            .unwrap();
+        let mut record = log.record(&request, None, None);
+        record.time = SystemTime::UNIX_EPOCH + Duration::from_secs(971186136);
+        let line = log.line(&record, StatusCode::PARTIAL_CONTENT, 100);
+        let (head, tail) = line.split_once(",\"duration_ms\":").unwrap();
+        assert_eq!(
+            head,
+            "{\"time\":\"2000-10-10T13:55:36Z\",\"remote\":null,\"user\":null,\
+             \"method\":\"GET\",\"path\":\"/a%20b.mp4\",\"query\":\"t=1\",\
+             \"protocol\":\"HTTP/1.1\",\"status\":206,\"size\":100"
+        );
+        let tail = tail.split_once(',').unwrap().1;
+        assert_eq!(
+            tail,
+            "\"range\":\"bytes=0-99\",\"referer\":null,\
+             \"user_agent\":\"say \\\"hi\\\"\",\"request_id\":\"r-1\"}"
+        );
+    }
+}
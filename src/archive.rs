@@ -0,0 +1,473 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    future::Future,
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use flate2::{Decompress, FlushDecompress, Status};
+use futures_util::ready;
+
+use crate::{
+    file::{self, BlockingFuture, FileMeta, SymlinkPolicy},
+    request_resolve::{self, ResolveOptions, Resolved},
+    tar,
+    vfs::Vfs,
+    zip,
+};
+
+/// How the member is stored in the archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Method {
+    Stored,
+    Deflated,
+}
+
+/// The member of the archive, the data is at the offset of the archive.
+#[derive(Debug, Clone)]
+pub(crate) struct Member {
+    pub method: Method,
+    pub offset: u64,
+    // the size of the data in the archive.
+    pub compressed_size: u64,
+    // the size of the content.
+    pub size: u64,
+    // the inflated bytes before the content, e.g. the member of the `.tar.gz`.
+    pub skip: u64,
+}
+
+/// The source of the file read instead of the file at the path.
+#[derive(Debug, Clone)]
+pub(crate) enum Source {
+    /// the member of the archive.
+    Archive { archive: PathBuf, member: Member },
+    /// the content in memory, e.g. the embedded asset, the content type overrides
+    /// the one by the extension.
+    Memory {
+        content: Bytes,
+        content_type: Option<String>,
+    },
+    /// the file of the virtual filesystem at the relative path.
+    Vfs { vfs: Vfs, path: String },
+}
+
+/// open the archive at the data of the member.
+pub(crate) fn open(archive: &Path, member: &Member) -> Result<File> {
+    let mut file = File::open(archive)?;
+    file.seek(SeekFrom::Start(member.offset))?;
+    Ok(file)
+}
+
+impl Source {
+    /// open the reader of the content in blocking, e.g. to hash or cache it.
+    pub fn open_std(&self) -> Result<Box<dyn Read + Send>> {
+        let (archive, member) = match self {
+            Source::Archive { archive, member } => (archive, member),
+            Source::Memory { content, .. } => {
+                return Ok(Box::new(std::io::Cursor::new(content.clone())))
+            }
+            Source::Vfs { .. } => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "the file of the vfs is read asynchronously",
+                ))
+            }
+        };
+        let data = open(archive, member)?.take(member.compressed_size);
+        let mut decoder = match member.method {
+            Method::Stored => return Ok(Box::new(data)),
+            Method::Deflated => flate2::read::DeflateDecoder::new(data),
+        };
+        std::io::copy(&mut (&mut decoder).take(member.skip), &mut std::io::sink())?;
+        Ok(Box::new(decoder.take(member.size)))
+    }
+}
+
+/// The index of the members by the normalized path, the directories are implied
+/// by the paths of the members.
+#[derive(Debug, Default)]
+pub(crate) struct ArchiveIndex {
+    members: HashMap<String, Member>,
+    dirs: HashSet<String>,
+}
+
+impl ArchiveIndex {
+    /// add the member, the invalid path is skipped, e.g. the path has `..`.
+    pub fn insert(&mut self, name: &str, member: Option<Member>) {
+        let path = match request_resolve::normalize_path(name) {
+            Some(path) if !path.is_empty() => path,
+            _ => return,
+        };
+        let mut dir = path.as_str();
+        while let Some((parent, _)) = dir.rsplit_once('/') {
+            self.dirs.insert(parent.to_string());
+            dir = parent;
+        }
+        match member {
+            Some(member) => {
+                self.members.insert(path, member);
+            }
+            None => {
+                self.dirs.insert(path);
+            }
+        }
+    }
+
+    pub fn member(&self, path: &str) -> Option<&Member> {
+        self.members.get(path)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        path.is_empty() || self.dirs.contains(path)
+    }
+}
+
+/// The archive mounted as the directory of the request path.
+pub(crate) struct Mounted {
+    pub archive: FileMeta,
+    pub index: Arc<ArchiveIndex>,
+    // the directory mounted, e.g. `/root/bundle` of `/root/bundle.zip`.
+    pub dir: PathBuf,
+    // the request path of the directory, e.g. `bundle`.
+    pub prefix: String,
+    // the path of the member, e.g. `app.js` of `bundle/app.js`.
+    pub member: String,
+}
+
+impl Mounted {
+    fn found(&self, path: &str) -> Option<FileMeta> {
+        let member = self.index.member(path)?;
+        Some(FileMeta {
+            path: self.dir.join(path),
+            size: member.size,
+            inode: self.archive.inode,
+            is_dir: false,
+            modified: self.archive.modified,
+            permisions: self.archive.permisions.clone(),
+            source: Some(Source::Archive {
+                archive: self.archive.path.clone(),
+                member: member.clone(),
+            }),
+        })
+    }
+
+    /// resolve the member as the file under the root.
+    pub fn resolve<B>(&self, request: &hyper::Request<B>, options: &ResolveOptions) -> Resolved {
+        let tree = Tree {
+            prefix: &self.prefix,
+            is_dir: |path: &str| self.index.is_dir(path),
+            find: |path: &str| self.found(path),
+        };
+        tree.resolve(&self.member, request, options)
+    }
+}
+
+/// The tree of the files without the filesystem access, e.g. the archive or the assets
+/// in memory, which is resolved as the directory under the root.
+pub(crate) struct Tree<'a, D, F> {
+    // the request path of the root of the tree.
+    pub prefix: &'a str,
+    pub is_dir: D,
+    pub find: F,
+}
+
+impl<D, F> Tree<'_, D, F>
+where
+    D: Fn(&str) -> bool,
+    F: Fn(&str) -> Option<FileMeta>,
+{
+    /// resolve the path of the tree, the directory is served by the index file,
+    /// it is forbidden without it.
+    pub fn resolve<B>(
+        &self,
+        path: &str,
+        request: &hyper::Request<B>,
+        options: &ResolveOptions,
+    ) -> Resolved {
+        let is_options = request.method() == hyper::Method::OPTIONS;
+        if let Some(meta) = (self.find)(path) {
+            return if is_options {
+                Resolved::Options
+            } else {
+                Resolved::Found(meta)
+            };
+        }
+        if !(self.is_dir)(path) {
+            return Resolved::NotFound;
+        }
+        let raw_path = request.uri().path();
+        if !raw_path.ends_with('/') && options.redirect_directory {
+            let location = request_resolve::location(request, &format!("{raw_path}/"));
+            return Resolved::Redirect(location);
+        }
+        let join = |dir: &str, name: &str| match dir {
+            "" => name.to_string(),
+            dir => format!("{dir}/{name}"),
+        };
+        let index = options
+            .index_files
+            .iter()
+            .map(|index| join(path, index))
+            .filter(|index| options.reject(&join(self.prefix, index)).is_none())
+            .find_map(|index| (self.find)(&index));
+        match index {
+            Some(_) if is_options => Resolved::Options,
+            Some(meta) => Resolved::Found(meta),
+            None => Resolved::PermissionDenied,
+        }
+    }
+}
+
+/// the kind of the archive by the file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ArchiveKind::Zip => &["zip"],
+            ArchiveKind::Tar => &["tar"],
+            ArchiveKind::TarGz => &["tar.gz", "tgz"],
+        }
+    }
+
+    fn index(&self, file: &mut File) -> Result<ArchiveIndex> {
+        match self {
+            ArchiveKind::Zip => zip::index(file),
+            ArchiveKind::Tar => tar::index(file),
+            ArchiveKind::TarGz => tar::index_gzip(file),
+        }
+    }
+}
+
+struct CachedIndex {
+    inode: u64,
+    size: u64,
+    modified: Option<SystemTime>,
+    index: Arc<ArchiveIndex>,
+}
+
+/// The archives mounted as the directories, e.g. `bundle.zip` or `bundle.tar` is `bundle/`,
+/// the index is built on the first access and rebuilt if the archive is changed.
+#[derive(Default)]
+pub(crate) struct Archives {
+    indexes: Mutex<HashMap<PathBuf, CachedIndex>>,
+}
+
+impl Archives {
+    fn index(&self, meta: &FileMeta, kind: ArchiveKind) -> Result<Arc<ArchiveIndex>> {
+        let mut indexes = self.indexes.lock().unwrap();
+        if let Some(cached) = indexes.get(&meta.path) {
+            if cached.inode == meta.inode
+                && cached.size == meta.size
+                && cached.modified == meta.modified
+            {
+                return Ok(cached.index.clone());
+            }
+        }
+        drop(indexes);
+        let index = Arc::new(kind.index(&mut File::open(&meta.path)?)?);
+        indexes = self.indexes.lock().unwrap();
+        let cached = CachedIndex {
+            inode: meta.inode,
+            size: meta.size,
+            modified: meta.modified,
+            index: index.clone(),
+        };
+        indexes.insert(meta.path.clone(), cached);
+        Ok(index)
+    }
+
+    /// find the archive of the kinds for the normalized path under the root in blocking,
+    /// the nearest archive is preferred.
+    pub fn find(
+        &self,
+        kinds: &[ArchiveKind],
+        root: &Path,
+        path: &str,
+        policy: SymlinkPolicy,
+    ) -> Result<Option<Mounted>> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        for i in 1..=segments.len() {
+            let dir = root.join(segments[..i].join("/"));
+            let candidates = kinds
+                .iter()
+                .flat_map(|kind| kind.extensions().iter().map(move |ext| (kind, ext)));
+            for (kind, extension) in candidates {
+                let mut archive = dir.clone().into_os_string();
+                archive.push(".");
+                archive.push(extension);
+                let archive = PathBuf::from(archive);
+                if policy.check(root, &archive).is_err() {
+                    continue;
+                }
+                let meta = match std::fs::metadata(&archive) {
+                    Ok(meta) if meta.is_file() => FileMeta::new(archive, &meta),
+                    _ => continue,
+                };
+                let index = self.index(&meta, *kind)?;
+                return Ok(Some(Mounted {
+                    archive: meta,
+                    index,
+                    dir,
+                    prefix: segments[..i].join("/"),
+                    member: segments[i..].join("/"),
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The reader of the member in the archive, the deflated data is inflated while reading,
+/// seeking backward restarts from the beginning of the data.
+pub(crate) struct MemberReader {
+    file: Arc<File>,
+    member: Member,
+    // the compressed data read from the file, not inflated yet.
+    input: Bytes,
+    // the bytes of the data read from the file.
+    read: u64,
+    // the read of the data in flight, dropped by the seek.
+    reading: Option<BlockingFuture<Bytes>>,
+    inflater: Decompress,
+    // the position of the inflated data, the content starts at `skip` of the member.
+    position: u64,
+    // the position to seek, the inflated bytes before it are skipped.
+    target: u64,
+}
+
+impl MemberReader {
+    pub fn new(file: File, member: Member) -> Self {
+        Self {
+            file: Arc::new(file),
+            input: Bytes::new(),
+            read: 0,
+            reading: None,
+            inflater: Decompress::new(false),
+            position: 0,
+            target: member.skip,
+            member,
+        }
+    }
+
+    /// read the data of the member at the offset by the blocking executor.
+    fn poll_read_data(&mut self, cx: &mut Context<'_>, len: u64) -> Poll<Result<Bytes>> {
+        let len = len.min(self.member.compressed_size - self.read) as usize;
+        if len == 0 {
+            return Poll::Ready(Ok(Bytes::new()));
+        }
+        let reading = self.reading.get_or_insert_with(|| {
+            let (file, offset) = (self.file.clone(), self.member.offset + self.read);
+            BlockingFuture::spawn(move || {
+                let mut buf = vec![0u8; len];
+                let n = file::pread(&file, &mut buf, offset)?;
+                buf.truncate(n);
+                Ok(Bytes::from(buf))
+            })
+        });
+        let data = ready!(Pin::new(reading).poll(cx));
+        self.reading = None;
+        let data = data?;
+        if data.is_empty() {
+            return Poll::Ready(Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "the archive is truncated",
+            )));
+        }
+        self.read += data.len() as u64;
+        Poll::Ready(Ok(data))
+    }
+
+    pub fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        readn: u64,
+        chunk_size: usize,
+    ) -> Poll<Result<Bytes>> {
+        let end = self.member.skip + self.member.size;
+        let readn = readn
+            .min(chunk_size as u64)
+            .min(end.saturating_sub(self.position.max(self.target)));
+        if readn == 0 {
+            return Poll::Ready(Ok(Bytes::new()));
+        }
+        if self.member.method == Method::Stored {
+            let data = ready!(self.poll_read_data(cx, readn))?;
+            self.position += data.len() as u64;
+            return Poll::Ready(Ok(data));
+        }
+        loop {
+            if self.input.is_empty() {
+                self.input = ready!(self.poll_read_data(cx, chunk_size as u64))?;
+            }
+            let skip = self.target.saturating_sub(self.position);
+            let len = match skip {
+                0 => readn,
+                skip => skip.min(chunk_size as u64),
+            };
+            let mut output = Vec::with_capacity(len as usize);
+            let total_in = self.inflater.total_in();
+            let status = self
+                .inflater
+                .decompress_vec(&self.input, &mut output, FlushDecompress::None)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+            let consumed = (self.inflater.total_in() - total_in) as usize;
+            self.input = self.input.slice(consumed..);
+            self.position += output.len() as u64;
+            if output.len() as u64 > skip {
+                return Poll::Ready(Ok(Bytes::from(output).slice(skip as usize..)));
+            }
+            let is_stuck = output.is_empty() && consumed == 0 && self.input.is_empty();
+            if status == Status::StreamEnd || is_stuck {
+                return Poll::Ready(Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "the deflated member is truncated",
+                )));
+            }
+        }
+    }
+
+    pub fn start_seek(&mut self, position: SeekFrom) -> Result<()> {
+        let current = self.position.max(self.target) - self.member.skip;
+        let position = match position {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.member.size.checked_add_signed(n),
+            SeekFrom::Current(n) => current.checked_add_signed(n),
+        };
+        let position = position
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek position"))?
+            + self.member.skip;
+        let restart = match self.member.method {
+            Method::Stored => true,
+            Method::Deflated => position < self.position,
+        };
+        if restart {
+            let offset = match self.member.method {
+                Method::Stored => position.min(self.member.size),
+                Method::Deflated => 0,
+            };
+            self.reading = None;
+            self.read = offset;
+            self.position = offset;
+            self.input = Bytes::new();
+            self.inflater.reset(false);
+        }
+        self.target = position;
+        Ok(())
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position.max(self.target) - self.member.skip
+    }
+}
@@ -0,0 +1,151 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use hyper::{
+    header::{self, HeaderValue},
+    HeaderMap, StatusCode,
+};
+
+#[cfg(feature = "bearer")]
+use crate::bearer::BearerAuth;
+use crate::sign::{constant_time_eq, decode_base64};
+
+/// The future of the credential verifier.
+pub type VerifyFuture = Pin<Box<dyn Future<Output = bool> + Send>>;
+
+/// The verifier of the user name and the password, e.g. by the user database.
+pub type BasicVerifier = dyn Fn(&str, &str) -> VerifyFuture + Send + Sync;
+
+#[derive(Clone)]
+enum Credentials {
+    Users(HashMap<String, String>),
+    Verifier(Arc<BasicVerifier>),
+}
+
+/// The HTTP Basic Authentication of the protected paths, the request without the valid
+/// `Authorization` header is responded as `401` with `WWW-Authenticate: Basic`.
+#[derive(Clone)]
+pub struct BasicAuth {
+    realm: String,
+    credentials: Credentials,
+}
+
+impl BasicAuth {
+    /// the realm of the static users added by `BasicAuth::user`.
+    pub fn new(realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            credentials: Credentials::Users(HashMap::new()),
+        }
+    }
+
+    /// the realm of the users verified by the callback.
+    pub fn verifier<F>(realm: impl Into<String>, verifier: F) -> Self
+    where
+        F: Fn(&str, &str) -> VerifyFuture + Send + Sync + 'static,
+    {
+        Self {
+            realm: realm.into(),
+            credentials: Credentials::Verifier(Arc::new(verifier)),
+        }
+    }
+
+    /// add the static user, the verifier is replaced.
+    pub fn user(mut self, name: impl Into<String>, password: impl Into<String>) -> Self {
+        if let Credentials::Verifier(_) = self.credentials {
+            self.credentials = Credentials::Users(HashMap::new());
+        }
+        if let Credentials::Users(ref mut users) = self.credentials {
+            users.insert(name.into(), password.into());
+        }
+        self
+    }
+
+    /// the challenge of the `WWW-Authenticate` header.
+    pub(crate) fn challenge(&self) -> HeaderValue {
+        let realm = self.realm.replace(['\\', '"'], "");
+        let challenge = format!("Basic realm=\"{realm}\", charset=\"UTF-8\"");
+        HeaderValue::from_str(&challenge).unwrap_or(HeaderValue::from_static("Basic"))
+    }
+
+    /// verify the credentials of the `Authorization` header.
+    pub(crate) async fn verify(&self, headers: &HeaderMap) -> bool {
+        let (name, password) = match basic_credentials(headers) {
+            Some(credentials) => credentials,
+            None => return false,
+        };
+        match self.credentials {
+            Credentials::Users(ref users) => match users.get(&name) {
+                Some(expected) => constant_time_eq(expected.as_bytes(), password.as_bytes()),
+                None => {
+                    // the same work as the existing user.
+                    std::hint::black_box(constant_time_eq(
+                        password.as_bytes(),
+                        password.as_bytes(),
+                    ));
+                    false
+                }
+            },
+            Credentials::Verifier(ref verifier) => verifier(&name, &password).await,
+        }
+    }
+}
+
+/// The authentication of the protected paths.
+#[derive(Clone)]
+pub(crate) enum Authentication {
+    Basic(BasicAuth),
+    #[cfg(feature = "bearer")]
+    Bearer(BearerAuth),
+}
+
+impl Authentication {
+    /// the status and the `WWW-Authenticate` challenge of the request denied.
+    pub(crate) async fn check(&self, headers: &HeaderMap) -> Option<(StatusCode, HeaderValue)> {
+        match self {
+            Self::Basic(auth) if auth.verify(headers).await => None,
+            Self::Basic(auth) => Some((StatusCode::UNAUTHORIZED, auth.challenge())),
+            #[cfg(feature = "bearer")]
+            Self::Bearer(auth) => auth.check(headers).await,
+        }
+    }
+}
+
+/// the user name and the password of `Authorization: Basic <base64>`.
+pub(crate) fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(decode_base64(encoded.trim())?).ok()?;
+    let (name, password) = decoded.split_once(':')?;
+    Some((name.to_string(), password.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let value = HeaderValue::from_str(authorization).unwrap();
+        headers.insert(header::AUTHORIZATION, value);
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let auth = BasicAuth::new("files").user("user", "pass");
+        assert!(auth.verify(&headers("Basic dXNlcjpwYXNz")).await);
+        assert!(!auth.verify(&headers("Basic dXNlcjpwYXNT")).await);
+        assert!(!auth.verify(&headers("Bearer dXNlcjpwYXNz")).await);
+        assert!(!auth.verify(&HeaderMap::new()).await);
+
+        let auth = BasicAuth::verifier("files", |name, _| {
+            let is_user = name == "user";
+            Box::pin(async move { is_user })
+        });
+        assert!(auth.verify(&headers("basic dXNlcjpwYXNz")).await);
+        assert_eq!(auth.challenge(), "Basic realm=\"files\", charset=\"UTF-8\"");
+    }
+}
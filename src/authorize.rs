@@ -0,0 +1,25 @@
+use std::{future::Future, pin::Pin};
+
+use hyper::{Request, Response};
+
+use crate::{body::Body, file::FileMeta};
+
+/// The decision of the authorizer on the resolved file.
+pub enum Decision {
+    /// the file is served.
+    Allow,
+    /// responded as `403 Forbidden`, by the error page or the handler.
+    Forbidden,
+    /// responded as `404 Not Found`, hiding the file is existing.
+    NotFound,
+    /// the response sent instead of the file, e.g. `401` with `WWW-Authenticate`
+    /// or the redirect to the login page.
+    Respond(Box<Response<Body>>),
+}
+
+/// The future of the authorizer, the request parts needed are cloned into it.
+pub type AuthorizeFuture = Pin<Box<dyn Future<Output = Decision> + Send>>;
+
+/// The authorizer of the request to the resolved file or directory, called after
+/// the request is resolved and before the file is opened, e.g. the per-directory acls.
+pub type Authorizer = dyn Fn(&Request<()>, &FileMeta) -> AuthorizeFuture + Send + Sync;
@@ -0,0 +1,308 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{
+    header::{self, HeaderValue},
+    HeaderMap, StatusCode,
+};
+
+use crate::sign::{constant_time_eq, decode_base64, hmac};
+
+/// The status of the bearer token checked by the validator.
+pub enum TokenStatus {
+    /// the file is served.
+    Valid,
+    /// the token is expired or malformed, responded as `401`.
+    Invalid,
+    /// the token is valid but not granted to the path, responded as `403`.
+    Forbidden,
+}
+
+/// The future of the token validator.
+pub type TokenFuture = Pin<Box<dyn Future<Output = TokenStatus> + Send>>;
+
+/// The validator of the bearer token, e.g. by the token store of the ci.
+pub type TokenValidator = dyn Fn(&str) -> TokenFuture + Send + Sync;
+
+/// The key of the JWT signed by `HS256`, the `exp` and the `nbf` claims are checked,
+/// the `iss` and the `aud` claims are checked if they are configured.
+#[derive(Clone)]
+pub struct JwtKey {
+    secret: Vec<u8>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    leeway: Duration,
+}
+
+impl JwtKey {
+    pub fn hs256(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            issuer: None,
+            audience: None,
+            leeway: Duration::ZERO,
+        }
+    }
+
+    /// the `iss` claim required.
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// the `aud` claim required, the string or one of the array.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    /// the clock skew allowed for the `exp` and the `nbf` claims.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// verify the signature and the claims of the token.
+    fn verify(&self, token: &str) -> bool {
+        let mut parts = token.split('.');
+        let (header, claims, signature) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(h), Some(c), Some(s)) if parts.next().is_none() => (h, c, s),
+            _ => return false,
+        };
+        let signed = &token[..header.len() + 1 + claims.len()];
+        let signature = decode_base64(signature).unwrap_or_default();
+        if !constant_time_eq(&hmac(&self.secret, signed.as_bytes()), &signature) {
+            return false;
+        }
+        let decode = |s| decode_base64(s).and_then(|json| String::from_utf8(json).ok());
+        let (header, claims) = match (decode(header), decode(claims)) {
+            (Some(header), Some(claims)) => (header, claims),
+            _ => return false,
+        };
+        // the `alg` is checked, e.g. `none` is never accepted.
+        if json_member(&header, "alg").map(json_string) != Some("HS256") {
+            return false;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let time = |name| json_member(&claims, name).and_then(|v| v.parse::<f64>().ok());
+        let leeway = self.leeway.as_secs_f64();
+        if time("exp").is_some_and(|exp| exp + leeway <= now.as_secs_f64()) {
+            return false;
+        }
+        if time("nbf").is_some_and(|nbf| nbf - leeway > now.as_secs_f64()) {
+            return false;
+        }
+        if let Some(ref issuer) = self.issuer {
+            if json_member(&claims, "iss").map(json_string) != Some(issuer) {
+                return false;
+            }
+        }
+        if let Some(ref audience) = self.audience {
+            let aud = json_member(&claims, "aud").unwrap_or_default();
+            let mut audiences = aud.trim_matches(['[', ']']).split(',');
+            if !audiences.any(|aud| json_string(aud.trim()) == audience) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// the raw value of the top-level member of the json object, e.g. `123` or `"HS256"`.
+fn json_member<'a>(json: &'a str, name: &str) -> Option<&'a str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut is_escaped = false;
+    let mut string_start = 0;
+    let mut last_string = "";
+    let mut value_start = None;
+    for (i, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if is_escaped => is_escaped = false,
+                '\\' => is_escaped = true,
+                '"' => {
+                    in_string = false;
+                    last_string = &json[string_start..i];
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                string_start = i + 1;
+            }
+            '{' | '[' => depth += 1,
+            '}' | ']' | ',' if depth == 1 && value_start.is_some() => {
+                return value_start.map(|start| json[start..i].trim());
+            }
+            '}' | ']' => depth = depth.checked_sub(1)?,
+            ':' if depth == 1 && last_string == name => value_start = Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// the string value without the quotes.
+fn json_string(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// the challenge of the realm, the invalid realm is omitted.
+fn challenge_value(challenge: String) -> HeaderValue {
+    HeaderValue::try_from(challenge).unwrap_or(HeaderValue::from_static("Bearer"))
+}
+
+#[derive(Clone)]
+enum Validator {
+    Custom(Arc<TokenValidator>),
+    Jwt(JwtKey),
+}
+
+/// The bearer token authentication of the protected paths, responded as `401` with
+/// `WWW-Authenticate: Bearer` if the token is missing or invalid, or `403` if the
+/// token is not granted.
+#[derive(Clone)]
+pub struct BearerAuth {
+    realm: String,
+    validator: Validator,
+}
+
+impl BearerAuth {
+    /// the tokens checked by the validator.
+    pub fn validator<F>(realm: impl Into<String>, validator: F) -> Self
+    where
+        F: Fn(&str) -> TokenFuture + Send + Sync + 'static,
+    {
+        Self {
+            realm: realm.into(),
+            validator: Validator::Custom(Arc::new(validator)),
+        }
+    }
+
+    /// the JWTs verified by the key.
+    pub fn jwt(realm: impl Into<String>, key: JwtKey) -> Self {
+        Self {
+            realm: realm.into(),
+            validator: Validator::Jwt(key),
+        }
+    }
+
+    /// the status and the challenge of the request denied, by RFC 6750.
+    pub(crate) async fn check(&self, headers: &HeaderMap) -> Option<(StatusCode, HeaderValue)> {
+        let realm = self.realm.replace(['\\', '"'], "");
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim());
+        let status = match (token, &self.validator) {
+            (None, _) => {
+                let challenge = format!("Bearer realm=\"{realm}\"");
+                return Some((StatusCode::UNAUTHORIZED, challenge_value(challenge)));
+            }
+            (Some(token), Validator::Custom(validator)) => validator(token).await,
+            (Some(token), Validator::Jwt(key)) if key.verify(token) => TokenStatus::Valid,
+            (Some(_), Validator::Jwt(_)) => TokenStatus::Invalid,
+        };
+        let (status, error) = match status {
+            TokenStatus::Valid => return None,
+            TokenStatus::Invalid => (StatusCode::UNAUTHORIZED, "invalid_token"),
+            TokenStatus::Forbidden => (StatusCode::FORBIDDEN, "insufficient_scope"),
+        };
+        let challenge = format!("Bearer realm=\"{realm}\", error=\"{error}\"");
+        Some((status, challenge_value(challenge)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FileService;
+    use hyper::{service::Service, Request};
+
+    fn encode_base64url(bytes: &[u8]) -> String {
+        const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().fold(0u32, |n, &b| n << 8 | b as u32) << (8 * (3 - chunk.len()));
+            for i in 0..=chunk.len() {
+                out.push(CHARS[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        out
+    }
+
+    fn jwt(secret: &[u8], claims: &str) -> String {
+        let header = encode_base64url(br#"{"alg":"HS256","typ":"JWT"}"#);
+        let signed = format!("{header}.{}", encode_base64url(claims.as_bytes()));
+        let signature = encode_base64url(&hmac(secret, signed.as_bytes()));
+        format!("{signed}.{signature}")
+    }
+
+    #[test]
+    fn test_json_member() {
+        let json = r#"{"a":{"exp":1},"exp": 2 ,"aud":["x","y"],"s":"q\"exp"}"#;
+        assert_eq!(json_member(json, "exp"), Some("2"));
+        assert_eq!(json_member(json, "aud"), Some(r#"["x","y"]"#));
+        assert_eq!(json_member(json, "s"), Some(r#""q\"exp""#));
+        assert_eq!(json_member(json, "b"), None);
+    }
+
+    #[test]
+    fn test_jwt_verify() {
+        let key = JwtKey::hs256("secret").audience("ci");
+        assert!(key.verify(&jwt(b"secret", r#"{"aud":["web","ci"]}"#)));
+        assert!(!key.verify(&jwt(b"other", r#"{"aud":"ci"}"#)));
+        assert!(!key.verify(&jwt(b"secret", r#"{"aud":"web"}"#)));
+        assert!(!key.verify(&jwt(b"secret", r#"{"aud":"ci","exp":1}"#)));
+        let unsigned = format!("{}.e30.", encode_base64url(br#"{"alg":"none"}"#));
+        assert!(!key.verify(&unsigned));
+    }
+
+    #[tokio::test]
+    async fn test_bearer_auth() {
+        let dir = std::env::temp_dir().join("blockless-bearer");
+        std::fs::create_dir_all(dir.join("artifacts")).unwrap();
+        std::fs::write(dir.join("artifacts/a.tar"), b"artifact").unwrap();
+        let auth = BearerAuth::jwt("ci", JwtKey::hs256("secret"));
+        let mut service = FileService::new(dir.to_str().unwrap()).bearer_auth("artifacts/**", auth);
+        let token = format!("Bearer {}", jwt(b"secret", r#"{"sub":"agent"}"#));
+        for (authorization, status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("Bearer bad"), StatusCode::UNAUTHORIZED),
+            (Some(token.as_str()), StatusCode::OK),
+        ] {
+            let mut req = Request::get("/artifacts/a.tar");
+            if let Some(authorization) = authorization {
+                req = req.header(header::AUTHORIZATION, authorization);
+            }
+            let resp = service.call(req.body(()).unwrap()).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+
+        let auth = BearerAuth::validator("ci", |_| Box::pin(async { TokenStatus::Forbidden }));
+        let mut service = FileService::new(dir.to_str().unwrap()).bearer_auth("artifacts/**", auth);
+        let req = Request::get("/artifacts/a.tar").header(header::AUTHORIZATION, "Bearer t");
+        let resp = service.call(req.body(()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let challenge = &resp.headers()[header::WWW_AUTHENTICATE];
+        assert_eq!(
+            challenge,
+            "Bearer realm=\"ci\", error=\"insufficient_scope\""
+        );
+    }
+}
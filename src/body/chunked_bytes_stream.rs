@@ -1 +1,35 @@
+use futures_util::Stream;
+use hyper::body::Bytes;
+use std::{
+    io::Result,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
+/// The stream yields the chunks rendered lazily, e.g. the directory listing,
+/// the length is unknown so the body is sent by chunked.
+pub struct ChunkedBytesStream {
+    chunks: Box<dyn Iterator<Item = Bytes> + Send>,
+}
+
+impl ChunkedBytesStream {
+    pub fn new(chunks: impl Iterator<Item = Bytes> + Send + 'static) -> Self {
+        Self {
+            chunks: Box::new(chunks),
+        }
+    }
+}
+
+impl Stream for ChunkedBytesStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // the empty chunk is skipped, which is the end of the chunked body.
+        loop {
+            match self.chunks.next() {
+                Some(chunk) if chunk.is_empty() => continue,
+                chunk => return Poll::Ready(chunk.map(Ok)),
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use futures_util::Stream;
+use hyper::body::Bytes;
+use std::{
+    io::Result,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::compression::Encoder;
+
+use super::bytes_stream::FileBytesStream;
+
+/// The stream compress the file bytes while streaming.
+pub struct CompressedBytesStream {
+    stream: FileBytesStream,
+    encoder: Encoder,
+    completed: bool,
+}
+
+impl CompressedBytesStream {
+    pub fn new(stream: FileBytesStream, encoder: Encoder) -> Self {
+        Self {
+            stream,
+            encoder,
+            completed: false,
+        }
+    }
+}
+
+impl Stream for CompressedBytesStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Self {
+            ref mut stream,
+            ref mut encoder,
+            ref mut completed,
+        } = *self;
+        if *completed {
+            return Poll::Ready(None);
+        }
+        loop {
+            match Pin::new(&mut *stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(b))) => {
+                    if let Err(e) = encoder.write(&b) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    let compressed = encoder.take();
+                    // the encoder may buffer the input, read more.
+                    if !compressed.is_empty() {
+                        return Poll::Ready(Some(Ok(compressed)));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    *completed = true;
+                    return Poll::Ready(Some(encoder.finish()));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
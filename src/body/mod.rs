@@ -6,20 +6,55 @@ use std::{
     pin::Pin,
     task::{Context, Poll},
 };
+use tokio::sync::OwnedSemaphorePermit;
 
 pub use bytes_stream::FileBytesStream;
+pub use chunked_bytes_stream::ChunkedBytesStream;
+pub use compressed_bytes_stream::CompressedBytesStream;
+pub use observed_bytes_stream::ObservedBytesStream;
 pub use range_bytes_stream::MultiRangeBytesStream;
 pub use range_bytes_stream::RangeBytesStream;
+pub use throttled_bytes_stream::ThrottledBytesStream;
+#[cfg(feature = "tokio")]
+pub use timeout_bytes_stream::TimeoutBytesStream;
 
 mod bytes_stream;
 mod chunked_bytes_stream;
+mod compressed_bytes_stream;
+mod observed_bytes_stream;
 mod range_bytes_stream;
+mod throttled_bytes_stream;
+#[cfg(feature = "tokio")]
+mod timeout_bytes_stream;
 
 pub enum Body {
     Empty,
     Full(FileBytesStream),
     RangeBytesStream(RangeBytesStream),
     MultiRangeBytesStream(MultiRangeBytesStream),
+    Compressed(CompressedBytesStream),
+    // the bytes in memory, e.g. the directory listing.
+    Bytes(Option<Bytes>),
+    Chunked(ChunkedBytesStream),
+    // the body holding the permit of the open files or in flight until it is dropped.
+    Limited(Box<Body>, OwnedSemaphorePermit),
+    Throttled(ThrottledBytesStream),
+    #[cfg(feature = "tokio")]
+    Timeout(TimeoutBytesStream),
+    // the body counting the bytes sent, e.g. for the logs.
+    Observed(ObservedBytesStream),
+}
+
+impl Body {
+    /// the body without any chunk to send, e.g. of `304 Not Modified`.
+    pub(crate) fn is_empty(&self) -> bool {
+        match *self {
+            Body::Empty | Body::Bytes(None) => true,
+            Body::Limited(ref body, _) => body.is_empty(),
+            Body::Observed(ref o) => o.body().is_empty(),
+            _ => false,
+        }
+    }
 }
 
 impl hyper::body::HttpBody for Body {
@@ -35,6 +70,14 @@ impl hyper::body::HttpBody for Body {
             Body::MultiRangeBytesStream(ref mut mr) => Pin::new(mr).poll_next(cx),
             Body::RangeBytesStream(ref mut r) => Pin::new(r).poll_next(cx),
             Body::Full(ref mut r) => Pin::new(r).poll_next(cx),
+            Body::Compressed(ref mut c) => Pin::new(c).poll_next(cx),
+            Body::Chunked(ref mut c) => Pin::new(c).poll_next(cx),
+            Body::Bytes(ref mut b) => Poll::Ready(b.take().map(Ok)),
+            Body::Throttled(ref mut t) => Pin::new(t).poll_next(cx),
+            #[cfg(feature = "tokio")]
+            Body::Timeout(ref mut t) => Pin::new(t).poll_next(cx),
+            Body::Observed(ref mut o) => Pin::new(o).poll_next(cx),
+            Body::Limited(ref mut body, _) => Pin::new(&mut **body).poll_data(cx),
             Body::Empty => Poll::Ready(None),
         }
     }
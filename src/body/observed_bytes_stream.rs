@@ -0,0 +1,91 @@
+use futures_util::Stream;
+use hyper::body::{Bytes, HttpBody};
+use std::{
+    io::Result,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::Body;
+
+/// the callback of the bytes sent, and the body is sent completely or not.
+pub(crate) type OnFinish = Box<dyn FnOnce(u64, bool) + Send>;
+
+/// The stream counts the bytes sent to the client, the callback is called once when the
+/// body is finished, failed or dropped by the closed connection.
+pub struct ObservedBytesStream {
+    body: Box<Body>,
+    sent: u64,
+    on_finish: Option<OnFinish>,
+}
+
+impl ObservedBytesStream {
+    pub(crate) fn new(body: Body, on_finish: OnFinish) -> Self {
+        Self {
+            body: Box::new(body),
+            sent: 0,
+            on_finish: Some(on_finish),
+        }
+    }
+
+    fn finish(&mut self, is_complete: bool) {
+        if let Some(on_finish) = self.on_finish.take() {
+            on_finish(self.sent, is_complete);
+        }
+    }
+
+    /// the observed body.
+    pub(crate) fn body(&self) -> &Body {
+        &self.body
+    }
+}
+
+impl Stream for ObservedBytesStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let rs = Pin::new(&mut *self.body).poll_data(cx);
+        match rs {
+            Poll::Ready(Some(Ok(ref chunk))) => self.sent += chunk.len() as u64,
+            Poll::Ready(Some(Err(_))) => self.finish(false),
+            Poll::Ready(None) => self.finish(true),
+            Poll::Pending => (),
+        }
+        rs
+    }
+}
+
+impl Drop for ObservedBytesStream {
+    fn drop(&mut self) {
+        // the empty body may be never polled, e.g. `304 Not Modified`.
+        let is_complete = self.body.is_empty();
+        self.finish(is_complete);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_observed() {
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let observe = |body| {
+            let finished = finished.clone();
+            let on_finish = move |sent, is_complete| {
+                finished.lock().unwrap().push((sent, is_complete));
+            };
+            ObservedBytesStream::new(body, Box::new(on_finish))
+        };
+        let mut stream = observe(Body::Bytes(Some(Bytes::from_static(b"hello"))));
+        while futures_util::StreamExt::next(&mut stream).await.is_some() {}
+        drop(stream);
+        drop(observe(Body::Bytes(Some(Bytes::from_static(b"hello")))));
+        drop(observe(Body::Empty));
+        assert_eq!(
+            *finished.lock().unwrap(),
+            [(5, true), (0, false), (0, true)]
+        );
+    }
+}
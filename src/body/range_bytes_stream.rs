@@ -144,7 +144,8 @@ impl MultiRangeBytesStream {
         write!(
             &mut buf,
             "--{boundary}\r\nContent-Range: bytes {}-{}/{file_size}\r\n",
-            range.start, range.length,
+            range.start,
+            range.end(),
         )
         .expect("buf write error");
 
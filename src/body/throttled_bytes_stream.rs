@@ -0,0 +1,99 @@
+use super::Body;
+use crate::{
+    runtime::{self, Sleep},
+    throttle::TokenBucket,
+};
+use futures_util::{ready, Stream};
+use hyper::body::{Bytes, HttpBody};
+use std::{
+    io::Result,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// The stream yields the chunks of the body no faster than the rate of the bucket
+/// of the response and the bucket shared by all the responses, the chunk larger
+/// than the burst size is split.
+pub struct ThrottledBytesStream {
+    body: Box<Body>,
+    bucket: Option<TokenBucket>,
+    shared: Option<Arc<Mutex<TokenBucket>>>,
+    // the rest of the chunk not sent yet.
+    pending: Option<Bytes>,
+    sleep: Option<Sleep>,
+}
+
+impl ThrottledBytesStream {
+    pub(crate) fn new(
+        body: Body,
+        bucket: Option<TokenBucket>,
+        shared: Option<Arc<Mutex<TokenBucket>>>,
+    ) -> Self {
+        Self {
+            body: Box::new(body),
+            bucket,
+            shared,
+            pending: None,
+            sleep: None,
+        }
+    }
+
+    /// take the tokens of the chunk from both the buckets, return the bytes can be sent,
+    /// or the duration to wait.
+    fn take(&mut self, len: usize) -> std::result::Result<usize, Duration> {
+        let mut shared = self.shared.as_ref().map(|s| s.lock().unwrap());
+        let mut buckets: Vec<&mut TokenBucket> = self
+            .bucket
+            .iter_mut()
+            .chain(shared.as_deref_mut())
+            .collect();
+        let n = buckets
+            .iter()
+            .map(|b| b.capacity() as usize)
+            .fold(len, usize::min);
+        if let Some(wait) = buckets
+            .iter_mut()
+            .filter_map(|b| b.wait_time(n as u64))
+            .max()
+        {
+            return Err(wait);
+        }
+        buckets.iter_mut().for_each(|b| b.take(n as u64));
+        Ok(n)
+    }
+}
+
+impl Stream for ThrottledBytesStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            let mut chunk = match self.pending.take() {
+                Some(chunk) => chunk,
+                None => match ready!(Pin::new(&mut *self.body).poll_data(cx)) {
+                    Some(Ok(chunk)) => chunk,
+                    other => return Poll::Ready(other),
+                },
+            };
+            match self.take(chunk.len()) {
+                Ok(n) => {
+                    let rest = chunk.split_off(n);
+                    if !rest.is_empty() {
+                        self.pending = Some(rest);
+                    }
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+                Err(wait) => {
+                    self.pending = Some(chunk);
+                    self.sleep = Some(runtime::sleep(wait));
+                }
+            }
+        }
+    }
+}
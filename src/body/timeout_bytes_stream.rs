@@ -0,0 +1,130 @@
+use futures_util::Stream;
+use hyper::body::{Bytes, HttpBody};
+use std::{
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    sync::{Arc, Mutex, Weak},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+use super::Body;
+
+struct Shared {
+    // the body is dropped by the watchdog on timeout, the file is closed.
+    body: Option<Body>,
+    // the time of the last chunk read by the client.
+    progress: Instant,
+    is_timed_out: bool,
+    waker: Option<Waker>,
+}
+
+/// The stream aborted if the response is not sent before the deadline or the client stops
+/// reading for the stall timeout. the watchdog task drops the body on timeout even if
+/// the stream is not polled, e.g. by the dead connection.
+pub struct TimeoutBytesStream {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl TimeoutBytesStream {
+    /// spawn the watchdog on the current tokio runtime.
+    pub(crate) fn new(body: Body, deadline: Option<Instant>, stall: Option<Duration>) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            body: Some(body),
+            progress: Instant::now(),
+            is_timed_out: false,
+            waker: None,
+        }));
+        tokio::spawn(watch(Arc::downgrade(&shared), deadline, stall));
+        Self { shared }
+    }
+}
+
+/// the time of the timeout, the earlier one of the deadline and the stall.
+fn expires_at(shared: &Shared, deadline: Option<Instant>, stall: Option<Duration>) -> Instant {
+    let stall = stall.map(|stall| shared.progress + stall);
+    deadline
+        .into_iter()
+        .chain(stall)
+        .min()
+        .unwrap_or(shared.progress)
+}
+
+async fn watch(shared: Weak<Mutex<Shared>>, deadline: Option<Instant>, stall: Option<Duration>) {
+    loop {
+        let at = match shared.upgrade() {
+            Some(shared) => expires_at(&shared.lock().unwrap(), deadline, stall),
+            None => return,
+        };
+        tokio::time::sleep_until(at).await;
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+        let mut shared = shared.lock().unwrap();
+        if shared.body.is_none() {
+            return;
+        }
+        if expires_at(&shared, deadline, stall) <= Instant::now() {
+            shared.body = None;
+            shared.is_timed_out = true;
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+            return;
+        }
+    }
+}
+
+impl Stream for TimeoutBytesStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.is_timed_out {
+            let error = Error::new(ErrorKind::TimedOut, "the response is timed out");
+            return Poll::Ready(Some(Err(error)));
+        }
+        let body = match shared.body.as_mut() {
+            Some(body) => body,
+            None => return Poll::Ready(None),
+        };
+        let rs = Pin::new(body).poll_data(cx);
+        match rs {
+            Poll::Ready(None) => shared.body = None,
+            Poll::Ready(Some(_)) => shared.progress = Instant::now(),
+            Poll::Pending => shared.waker = Some(cx.waker().clone()),
+        }
+        rs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stall() {
+        let body = Body::Bytes(Some(Bytes::from_static(b"hello")));
+        let mut stream = TimeoutBytesStream::new(body, None, Some(Duration::from_secs(5)));
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        // the body is dropped by the watchdog without the poll.
+        assert!(stream.shared.lock().unwrap().body.is_none());
+        let chunk = futures_util::StreamExt::next(&mut stream).await.unwrap();
+        assert_eq!(chunk.unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_progress() {
+        let body = Body::Bytes(Some(Bytes::from_static(b"hello")));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut stream = TimeoutBytesStream::new(body, Some(deadline), None);
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        let chunk = futures_util::StreamExt::next(&mut stream).await.unwrap();
+        assert_eq!(&chunk.unwrap()[..], b"hello");
+        assert!(futures_util::StreamExt::next(&mut stream).await.is_none());
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(futures_util::StreamExt::next(&mut stream).await.is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::Duration,
+};
+
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    http::request::Parts,
+    Method, Request, Response, StatusCode,
+};
+use regex::Regex;
+
+#[cfg(feature = "bearer")]
+use crate::bearer::BearerAuth;
+use crate::{
+    access_log::AccessLog,
+    auth::BasicAuth,
+    authorize::AuthorizeFuture,
+    body::Body,
+    compression::CompressionConfig,
+    cors::Cors,
+    error::RequestMeta,
+    error_page::ErrorPage,
+    etag::EtagStrategy,
+    file::{FileMeta, SymlinkPolicy},
+    filesvr::FileService,
+    hotlink::Hotlink,
+    ip::IpNet,
+    limit::ConcurrencyLimit,
+    listing::DirEntries,
+    memory::MemoryFiles,
+    metrics::Metrics,
+    rate_limit::RateLimit,
+    resolver::{Resolver, RootResolver},
+    rewrite::RewriteRule,
+    security::{Hsts, SecurityHeaders},
+    signed_url::SignedUrl,
+};
+
+/// The builder of the file service, every option is set by the chained setter of the
+/// same name as the service, and the unset options keep the defaults, so the
+/// configuration stays compatible as the options are added. the invalid option,
+/// e.g. the malformed CIDR block, is returned by `build` instead of the panic.
+pub struct FileServiceBuilder<R = RootResolver> {
+    service: FileService<R>,
+    // the first invalid option.
+    error: Option<Error>,
+}
+
+impl FileServiceBuilder {
+    pub fn new(root: impl Into<String>) -> Self {
+        FileService::new(root).into()
+    }
+}
+
+/// configure the service further, e.g. of the resolver or the virtual filesystem.
+impl<R> From<FileService<R>> for FileServiceBuilder<R> {
+    fn from(service: FileService<R>) -> Self {
+        Self {
+            service,
+            error: None,
+        }
+    }
+}
+
+/// the setters forwarded to the service as they are.
+macro_rules! forward {
+    ($($(#[$attr:meta])* fn $name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            $(#[$attr])*
+            #[doc = concat!("see [`FileService::", stringify!($name), "`].")]
+            pub fn $name(mut self, $($arg: $ty),*) -> Self {
+                self.service = self.service.$name($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+impl<R: Resolver> FileServiceBuilder<R> {
+    /// the service of the options, or the first invalid option.
+    pub fn build(self) -> Result<FileService<R>> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.service),
+        }
+    }
+
+    /// keep the first invalid option, the rest are still checked by the setters.
+    fn invalid(mut self, error: Error) -> Self {
+        self.error.get_or_insert(error);
+        self
+    }
+
+    /// see [`FileService::resolver`].
+    pub fn resolver<T: Resolver>(self, resolver: T) -> FileServiceBuilder<T> {
+        FileServiceBuilder {
+            service: self.service.resolver(resolver),
+            error: self.error,
+        }
+    }
+
+    forward! {
+        fn etag_strategy(strategy: EtagStrategy);
+        fn methods(methods: impl IntoIterator<Item = Method>);
+        fn method_not_allowed_status(status: StatusCode);
+        fn mime_type(ext: &str, content_type: impl Into<String>);
+        fn default_mime_type(content_type: impl Into<String>);
+        fn charset(charset: impl Into<String>);
+        fn attachment(pattern: &str);
+        fn attachment_by_query(enable: bool);
+        fn compression(enable: bool);
+        fn compression_config(config: CompressionConfig);
+        fn precompressed(enable: bool);
+        fn cache_control(pattern: &str, value: impl Into<String>);
+        fn fingerprinted_assets(enable: bool);
+        fn content_security_policy(pattern: &str, policy: Option<&str>);
+        fn header(name: HeaderName, value: HeaderValue);
+        fn header_for(pattern: &str, name: HeaderName, value: HeaderValue);
+        fn vary(name: impl Into<String>);
+        fn symlink_policy(policy: SymlinkPolicy);
+        fn hide_hidden(enable: bool);
+        fn deny(pattern: &str);
+        fn forbid(pattern: &str);
+        fn allow(pattern: &str);
+        fn index_files(index_files: impl IntoIterator<Item = impl Into<String>>);
+        fn try_files(try_files: impl IntoIterator<Item = impl Into<String>>);
+        fn redirect_directory(enable: bool);
+        fn strip_trailing_slash(enable: bool);
+        fn autoindex(enable: bool);
+        fn listing_template(template: impl Fn(&DirEntries) -> String + Send + Sync + 'static);
+        fn listing_page_size(page_size: usize);
+        fn memory_files(files: MemoryFiles);
+        fn overlay_root(root: impl Into<String>);
+        fn fallback_file(path: impl Into<String>);
+        fn language_variants(enable: bool);
+        fn default_language(language: impl Into<String>);
+        fn media_type_variants(enable: bool);
+        fn case_insensitive(enable: bool);
+        fn chunk_size(chunk_size: usize);
+        fn read_ahead(depth: usize);
+        #[cfg(feature = "mmap")]
+        fn mmap(max_size: u64);
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        fn io_uring(enable: bool);
+        fn cache(max_entry_size: u64, max_size: u64);
+        fn fd_cache(max_files: usize, idle_timeout: Duration);
+        fn zip_archives(enable: bool);
+        fn tar_archives(enable: bool);
+        fn max_open_files(max: usize);
+        fn concurrency_limit(limit: ConcurrencyLimit);
+        fn cors(cors: Cors);
+        fn security_headers(headers: SecurityHeaders);
+        fn metrics(metrics: Metrics);
+        fn track_stats(max_paths: usize);
+        fn request_id(name: HeaderName);
+        fn access_log(log: AccessLog);
+        fn hsts(hsts: Hsts);
+        fn response_timeout(timeout: Duration);
+        fn stall_timeout(timeout: Duration);
+        fn with_prefix(prefix: &str);
+        fn rewrite(rule: RewriteRule);
+        fn not_found_page(path: impl Into<String>);
+        fn internal_error_response(enable: bool);
+        fn error_page(status: StatusCode, page: ErrorPage);
+        fn error_handler(
+            handler: impl Fn(&Error, &Request<()>) -> Response<Body> + Send + Sync + 'static
+        );
+        fn on_request(
+            hook: impl Fn(&mut Parts) -> Option<Response<Body>> + Send + Sync + 'static
+        );
+        fn on_response(hook: impl Fn(&Parts, &mut Response<Body>) + Send + Sync + 'static);
+        fn on_error(hook: impl Fn(&Error, &RequestMeta) + Send + Sync + 'static);
+        fn authorize(
+            authorizer: impl Fn(&Request<()>, &FileMeta) -> AuthorizeFuture + Send + Sync + 'static
+        );
+        fn basic_auth(pattern: &str, auth: BasicAuth);
+        #[cfg(feature = "bearer")]
+        fn bearer_auth(pattern: &str, auth: BearerAuth);
+        fn rate_limit(pattern: &str, limit: RateLimit);
+        fn hotlink_protection(hotlink: Hotlink);
+        fn signed_url(pattern: &str, signer: SignedUrl);
+    }
+
+    /// see [`FileService::fingerprint_pattern`], the invalid regex is returned by `build`.
+    pub fn fingerprint_pattern(self, pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(_) => self.map(|s| s.fingerprint_pattern(pattern)),
+            Err(e) => self.invalid(Error::new(ErrorKind::InvalidInput, e)),
+        }
+    }
+
+    /// see [`FileService::throttle`], the zero rate is returned by `build`.
+    pub fn throttle(self, bytes_per_sec: u64) -> Self {
+        self.map_rate(bytes_per_sec, |s| s.throttle(bytes_per_sec))
+    }
+
+    /// see [`FileService::throttle_for`], the zero rate is returned by `build`.
+    pub fn throttle_for(self, pattern: &str, bytes_per_sec: u64) -> Self {
+        self.map_rate(bytes_per_sec, |s| s.throttle_for(pattern, bytes_per_sec))
+    }
+
+    /// see [`FileService::bandwidth_limit`], the zero rate is returned by `build`.
+    pub fn bandwidth_limit(self, bytes_per_sec: u64) -> Self {
+        self.map_rate(bytes_per_sec, |s| s.bandwidth_limit(bytes_per_sec))
+    }
+
+    /// see [`FileService::deny_ip`], the invalid CIDR block is returned by `build`.
+    pub fn deny_ip(self, cidr: &str) -> Self {
+        self.map_cidr(cidr, |s| s.deny_ip(cidr))
+    }
+
+    /// see [`FileService::allow_ip`], the invalid CIDR block is returned by `build`.
+    pub fn allow_ip(self, cidr: &str) -> Self {
+        self.map_cidr(cidr, |s| s.allow_ip(cidr))
+    }
+
+    /// see [`FileService::trusted_proxy`], the invalid CIDR block is returned by `build`.
+    pub fn trusted_proxy(self, cidr: &str) -> Self {
+        self.map_cidr(cidr, |s| s.trusted_proxy(cidr))
+    }
+
+    /// see [`FileService::watch`], the error of the watcher is returned by `build`.
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    pub fn watch(self) -> Self {
+        let Self { service, error } = self;
+        match service.clone().watch() {
+            Ok(service) => Self { service, error },
+            Err(e) => Self { service, error }.invalid(e),
+        }
+    }
+
+    fn map(mut self, f: impl FnOnce(FileService<R>) -> FileService<R>) -> Self {
+        self.service = f(self.service);
+        self
+    }
+
+    fn map_rate(
+        self,
+        bytes_per_sec: u64,
+        f: impl FnOnce(FileService<R>) -> FileService<R>,
+    ) -> Self {
+        if bytes_per_sec > 0 {
+            return self.map(f);
+        }
+        self.invalid(Error::new(
+            ErrorKind::InvalidInput,
+            "the rate must be positive",
+        ))
+    }
+
+    fn map_cidr(self, cidr: &str, f: impl FnOnce(FileService<R>) -> FileService<R>) -> Self {
+        match cidr.parse::<IpNet>() {
+            Ok(_) => self.map(f),
+            Err(e) => self.invalid(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let dir = std::env::temp_dir().join("blockless-builder");
+        let service = FileServiceBuilder::new(dir.to_str().unwrap())
+            .index_files(["index.html"])
+            .cache_control("*.js", "max-age=60")
+            .allow_ip("10.0.0.0/8")
+            .throttle(1024)
+            .build();
+        assert!(service.is_ok());
+
+        let error = FileServiceBuilder::new(dir.to_str().unwrap())
+            .deny_ip("10.0.0.0/33")
+            .throttle(0)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), "invalid cidr: 10.0.0.0/33");
+
+        let error = FileServiceBuilder::new(dir.to_str().unwrap())
+            .fingerprint_pattern("(")
+            .build();
+        assert!(error.is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use hyper::body::Bytes;
+
+use crate::file::FileMeta;
+
+struct CachedFile {
+    inode: u64,
+    size: u64,
+    modified: SystemTime,
+    content: Bytes,
+    // the tick of the last use.
+    tick: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<PathBuf, CachedFile>,
+    // the paths ordered by the last use, the least recently used is the first.
+    recency: BTreeMap<u64, PathBuf>,
+    tick: u64,
+    // the total bytes of the cached contents.
+    size: u64,
+}
+
+impl CacheState {
+    fn remove(&mut self, path: &Path) -> Option<CachedFile> {
+        let cached = self.entries.remove(path)?;
+        self.recency.remove(&cached.tick);
+        self.size -= cached.size;
+        Some(cached)
+    }
+
+    fn touch(&mut self, path: &Path) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(cached) = self.entries.get_mut(path) {
+            self.recency.remove(&cached.tick);
+            cached.tick = tick;
+            self.recency.insert(tick, path.to_path_buf());
+        }
+    }
+}
+
+/// The size-bounded LRU cache of the small file contents, keyed by the path,
+/// the entry is stale if the mtime, size or inode is changed.
+pub(crate) struct FileCache {
+    max_entry_size: u64,
+    max_size: u64,
+    state: Mutex<CacheState>,
+}
+
+impl FileCache {
+    pub fn new(max_entry_size: u64, max_size: u64) -> Self {
+        Self {
+            max_entry_size,
+            max_size,
+            state: Default::default(),
+        }
+    }
+
+    /// the file without mtime can't be validated, so it is not cached.
+    pub fn is_cacheable(&self, file: &FileMeta) -> bool {
+        file.modified.is_some() && file.size <= self.max_entry_size && file.size <= self.max_size
+    }
+
+    /// the cached content of the file, the stale entry is removed.
+    pub fn get(&self, file: &FileMeta) -> Option<Bytes> {
+        let modified = file.modified?;
+        let mut state = self.state.lock().unwrap();
+        let cached = state.entries.get(&file.path)?;
+        if cached.inode != file.inode || cached.size != file.size || cached.modified != modified {
+            state.remove(&file.path);
+            return None;
+        }
+        let content = cached.content.clone();
+        state.touch(&file.path);
+        Some(content)
+    }
+
+    /// cache the content of the file, the least recently used entries are evicted
+    /// until the total size is within the limit.
+    pub fn insert(&self, file: &FileMeta, content: Bytes) {
+        let modified = match file.modified {
+            Some(modified) if self.is_cacheable(file) => modified,
+            _ => return,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.remove(&file.path);
+        while state.size + file.size > self.max_size {
+            let path = match state.recency.values().next() {
+                Some(path) => path.clone(),
+                None => break,
+            };
+            state.remove(&path);
+        }
+        let cached = CachedFile {
+            inode: file.inode,
+            size: file.size,
+            modified,
+            content,
+            tick: 0,
+        };
+        state.size += file.size;
+        state.entries.insert(file.path.clone(), cached);
+        state.touch(&file.path);
+    }
+
+    /// remove the entries of the path and the paths under it.
+    #[cfg_attr(not(all(feature = "watch", target_os = "linux")), allow(dead_code))]
+    pub fn invalidate(&self, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        let paths: Vec<PathBuf> = state
+            .entries
+            .keys()
+            .filter(|p| p.starts_with(path))
+            .cloned()
+            .collect();
+        for path in paths {
+            state.remove(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn test_file(name: &str, content: &[u8]) -> FileMeta {
+        let path = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        FileMeta::from_path(path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_file_cache() {
+        let cache = FileCache::new(8, 10);
+        let a = test_file("blockless-cache-a.txt", b"aaaa").await;
+        let b = test_file("blockless-cache-b.txt", b"bbbb").await;
+        let c = test_file("blockless-cache-c.txt", b"cccc").await;
+        let large = test_file("blockless-cache-large.txt", b"123456789").await;
+        assert!(!cache.is_cacheable(&large));
+        cache.insert(&a, Bytes::from_static(b"aaaa"));
+        cache.insert(&b, Bytes::from_static(b"bbbb"));
+        assert_eq!(cache.get(&a).unwrap(), "aaaa");
+        // `b` is the least recently used.
+        cache.insert(&c, Bytes::from_static(b"cccc"));
+        assert!(cache.get(&b).is_none());
+        assert_eq!(cache.get(&a).unwrap(), "aaaa");
+        assert_eq!(cache.get(&c).unwrap(), "cccc");
+
+        let mut changed = a.clone();
+        changed.size = 5;
+        assert!(cache.get(&changed).is_none());
+        assert!(cache.get(&a).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let cache = FileCache::new(8, 16);
+        let a = test_file("blockless-cache-dir/a.txt", b"a").await;
+        cache.insert(&a, Bytes::from_static(b"a"));
+        cache.invalidate(&std::env::temp_dir().join("blockless-cache"));
+        assert!(cache.get(&a).is_some());
+        cache.invalidate(&std::env::temp_dir().join("blockless-cache-dir"));
+        assert!(cache.get(&a).is_none());
+    }
+}
@@ -0,0 +1,449 @@
+use std::io::{ErrorKind, Result, Write};
+
+use flate2::{write::GzEncoder, Compression};
+use hyper::body::Bytes;
+
+/// The content encoding of the compressed response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Zstd,
+    Gzip,
+}
+
+/// the encodings of the precompressed files, in the order of the server preference.
+pub(crate) const PRECOMPRESSED_ENCODINGS: &[Encoding] =
+    &[Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+
+/// the supported encodings of the encoder, in the order of the server preference.
+pub(crate) const SUPPORTED_ENCODINGS: &[Encoding] = &[
+    #[cfg(feature = "brotli")]
+    Encoding::Brotli,
+    #[cfg(feature = "zstd")]
+    Encoding::Zstd,
+    Encoding::Gzip,
+];
+
+impl Encoding {
+    /// the value of `Content-Encoding` header.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// the extension of the precompressed file, e.g. `app.js.br`.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zst",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "br" => Some(Encoding::Brotli),
+            "zstd" => Some(Encoding::Zstd),
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            _ => None,
+        }
+    }
+}
+
+/// parse the header of the tokens with the qvalue, e.g. `Accept-Encoding` or `Accept`,
+/// the tokens are lowercase.
+pub(crate) fn parse_qvalues(header: &str) -> Vec<(String, f32)> {
+    header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let coding = parts.next()?.trim().to_ascii_lowercase();
+            if coding.is_empty() {
+                return None;
+            }
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((coding, q))
+        })
+        .collect()
+}
+
+/// the encodings the client accepted from the supported encodings, ordered by the qvalue,
+/// the earlier one is preferred if the qvalues are equal.
+pub(crate) fn accepted(accept_encoding: &str, supported: &[Encoding]) -> Vec<Encoding> {
+    let codings = parse_qvalues(accept_encoding);
+    let wildcard = codings.iter().find(|(c, _)| c == "*").map(|(_, q)| *q);
+    let mut accepted: Vec<(Encoding, f32)> = supported
+        .iter()
+        .filter_map(|encoding| {
+            let q = codings
+                .iter()
+                .find(|(c, _)| Encoding::from_token(c) == Some(*encoding))
+                .map(|(_, q)| *q)
+                .or(wildcard)?;
+            (q > 0.0).then_some((*encoding, q))
+        })
+        .collect();
+    // the stable sort keeps the order of server preference.
+    accepted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    accepted.into_iter().map(|(encoding, _)| encoding).collect()
+}
+
+/// choose the best encoding the client accepted from the supported encodings.
+pub(crate) fn negotiate(accept_encoding: &str, supported: &[Encoding]) -> Option<Encoding> {
+    accepted(accept_encoding, supported).into_iter().next()
+}
+
+/// the mime type without the parameters.
+pub(crate) fn essence(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or("").trim()
+}
+
+/// match the mime type with the pattern, e.g. `text/*` or `application/json`.
+pub(crate) fn is_mime_matched(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some("*") => true,
+        Some(top) => mime
+            .split_once('/')
+            .map(|(t, _)| t.eq_ignore_ascii_case(top))
+            .unwrap_or(false),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+/// the content is already compressed, e.g. images and archives.
+fn is_compressed_format(mime: &str) -> bool {
+    if mime == "image/svg+xml" {
+        return false;
+    }
+    mime.starts_with("image/")
+        || mime.starts_with("video/")
+        || mime.starts_with("audio/")
+        || matches!(
+            mime,
+            "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/x-bzip2"
+                | "application/x-xz"
+                | "application/x-7z-compressed"
+                | "application/vnd.rar"
+                | "application/x-rar-compressed"
+                | "application/zstd"
+                | "application/pdf"
+                | "font/woff"
+                | "font/woff2"
+        )
+}
+
+/// the content type is worth compressing.
+pub(crate) fn is_compressible(content_type: &str) -> bool {
+    let mime = essence(content_type);
+    mime.starts_with("text/")
+        || mime.ends_with("+xml")
+        || mime.ends_with("+json")
+        || matches!(
+            mime,
+            "application/javascript"
+                | "application/json"
+                | "application/xml"
+                | "application/wasm"
+                | "image/svg+xml"
+        )
+}
+
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+/// The policy of the dynamic compression.
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    min_size: u64,
+    level: Option<u32>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+            level: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// the file smaller than the size is not compressed, the default is 1024 bytes.
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// the compression level, it is limited in the range of the encoder,
+    /// gzip is 0-9, brotli is 0-11 and zstd is 1-22. the default is the encoder default.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// compress the mime types matched the pattern, e.g. `text/*`, which replaces
+    /// the built-in compressible types.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// don't compress the mime types matched the pattern, e.g. `application/json`.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    pub(crate) fn compression_level(&self) -> Option<u32> {
+        self.level
+    }
+
+    /// the file is worth compressing, the already compressed formats are always skipped.
+    pub(crate) fn is_eligible(&self, content_type: &str, size: u64) -> bool {
+        let mime = essence(content_type);
+        if size < self.min_size || is_compressed_format(mime) {
+            return false;
+        }
+        if self.exclude.iter().any(|p| is_mime_matched(p, mime)) {
+            return false;
+        }
+        if self.include.is_empty() {
+            is_compressible(mime)
+        } else {
+            self.include.iter().any(|p| is_mime_matched(p, mime))
+        }
+    }
+}
+
+#[cfg(feature = "brotli")]
+const BROTLI_BUF_SIZE: usize = 4096;
+#[cfg(feature = "brotli")]
+const BROTLI_QUALITY: u32 = 5;
+#[cfg(feature = "brotli")]
+const BROTLI_LGWIN: u32 = 22;
+
+/// The streaming encoder, the input is written and the compressed output is taken.
+pub enum Encoder {
+    #[cfg(feature = "brotli")]
+    // the writer is taken when finished.
+    Brotli(Option<Box<brotli::CompressorWriter<Vec<u8>>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Gzip(GzEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    /// create the encoder with the level, the default level of encoder is used if `None`.
+    pub fn new(encoding: Encoding, level: Option<u32>) -> Result<Self> {
+        let encoder = match encoding {
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => Encoder::Brotli(Some(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUF_SIZE,
+                level.map(|l| l.min(11)).unwrap_or(BROTLI_QUALITY),
+                BROTLI_LGWIN,
+            )))),
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => {
+                let level = level.map(|l| l.clamp(1, 22) as i32).unwrap_or(0);
+                Encoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), level)?)
+            }
+            Encoding::Gzip => {
+                let level = level
+                    .map(|l| Compression::new(l.min(9)))
+                    .unwrap_or_default();
+                Encoder::Gzip(GzEncoder::new(Vec::new(), level))
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(std::io::Error::new(
+                    ErrorKind::Unsupported,
+                    "the encoding is not enabled.",
+                ))
+            }
+        };
+        Ok(encoder)
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<()> {
+        match *self {
+            #[cfg(feature = "brotli")]
+            Encoder::Brotli(ref mut e) => match e {
+                Some(e) => e.write_all(buf),
+                None => Err(std::io::Error::other("write after finished.")),
+            },
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut e) => e.write_all(buf),
+            Encoder::Gzip(ref mut e) => e.write_all(buf),
+        }
+    }
+
+    /// take the compressed output.
+    pub fn take(&mut self) -> Bytes {
+        let buf = match *self {
+            #[cfg(feature = "brotli")]
+            Encoder::Brotli(ref mut e) => match e {
+                Some(e) => e.get_mut(),
+                None => return Bytes::new(),
+            },
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut e) => e.get_mut(),
+            Encoder::Gzip(ref mut e) => e.get_mut(),
+        };
+        std::mem::take(buf).into()
+    }
+
+    /// finish the stream and take the rest output.
+    pub fn finish(&mut self) -> Result<Bytes> {
+        match *self {
+            #[cfg(feature = "brotli")]
+            Encoder::Brotli(ref mut e) => {
+                let rest = e.take().map(|e| e.into_inner()).unwrap_or_default();
+                return Ok(rest.into());
+            }
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut e) => e.do_finish()?,
+            Encoder::Gzip(ref mut e) => e.try_finish()?,
+        }
+        Ok(self.take())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let gzip = &[Encoding::Gzip];
+        assert_eq!(negotiate("gzip, deflate", gzip), Some(Encoding::Gzip));
+        assert_eq!(negotiate("deflate, GZIP;q=0.5", gzip), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip;q=0", gzip), None);
+        assert_eq!(negotiate("*", gzip), Some(Encoding::Gzip));
+        assert_eq!(negotiate("*;q=0", gzip), None);
+        assert_eq!(negotiate("*, gzip;q=0", gzip), None);
+        assert_eq!(negotiate("identity", gzip), None);
+        assert_eq!(negotiate("", gzip), None);
+    }
+
+    #[test]
+    fn test_is_compressible() {
+        assert!(is_compressible("text/html; charset=utf-8"));
+        assert!(is_compressible("application/javascript"));
+        assert!(is_compressible("image/svg+xml"));
+        assert!(!is_compressible("image/png"));
+        assert!(!is_compressible("application/zip"));
+    }
+
+    #[test]
+    fn test_encoder() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let compressed = encode(Encoding::Gzip);
+        let mut decoded = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "hello world ".repeat(100));
+    }
+
+    fn encode(encoding: Encoding) -> Vec<u8> {
+        let mut encoder = Encoder::new(encoding, None).unwrap();
+        let mut compressed = Vec::new();
+        for _ in 0..100 {
+            encoder.write(b"hello world ").unwrap();
+            compressed.extend_from_slice(&encoder.take());
+        }
+        compressed.extend_from_slice(&encoder.finish().unwrap());
+        compressed
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_brotli_encoder() {
+        use std::io::Read;
+
+        let compressed = encode(Encoding::Brotli);
+        let mut decoded = String::new();
+        brotli::Decompressor::new(&compressed[..], 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "hello world ".repeat(100));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_encoder() {
+        let compressed = encode(Encoding::Zstd);
+        let decoded = zstd::stream::decode_all(&compressed[..]).unwrap();
+        assert_eq!(decoded, "hello world ".repeat(100).as_bytes());
+    }
+
+    #[test]
+    fn test_negotiate_qvalue() {
+        let supported = PRECOMPRESSED_ENCODINGS;
+        assert_eq!(
+            negotiate("gzip, br, zstd", supported),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(
+            negotiate("gzip;q=0.5, br;q=0.8, zstd;q=0.9", supported),
+            Some(Encoding::Zstd)
+        );
+        assert_eq!(
+            negotiate("gzip;q=1, br;q=0.5", supported),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(negotiate("*", supported), Some(Encoding::Brotli));
+    }
+
+    #[test]
+    fn test_accepted() {
+        let supported = PRECOMPRESSED_ENCODINGS;
+        assert_eq!(
+            accepted("gzip;q=0.9, br;q=0.5, zstd", supported),
+            vec![Encoding::Zstd, Encoding::Gzip, Encoding::Brotli]
+        );
+        assert_eq!(accepted("gzip, br;q=0", supported), vec![Encoding::Gzip]);
+    }
+
+    #[test]
+    fn test_compression_config() {
+        let config = CompressionConfig::new();
+        assert!(config.is_eligible("text/html", 1024));
+        assert!(!config.is_eligible("text/html", 1023));
+        assert!(!config.is_eligible("image/png", 4096));
+        assert!(!config.is_eligible("application/octet-stream", 4096));
+
+        let config = CompressionConfig::new()
+            .min_size(0)
+            .include("*/*")
+            .exclude("application/json");
+        assert!(config.is_eligible("application/octet-stream", 1));
+        assert!(!config.is_eligible("application/json; charset=utf-8", 4096));
+        assert!(!config.is_eligible("application/zip", 4096));
+        assert!(!config.is_eligible("image/jpeg", 4096));
+        assert!(config.is_eligible("image/svg+xml", 4096));
+
+        let config = CompressionConfig::new().include("text/*");
+        assert!(config.is_eligible("text/css", 4096));
+        assert!(!config.is_eligible("application/javascript", 4096));
+    }
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use hyper::{
+    header::{self, HeaderName, HeaderValue},
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+
+use crate::body::Body;
+
+/// The CORS of the responses, the preflight `OPTIONS` request of the allowed origin is
+/// answered without resolving, the other responses get `Access-Control-Allow-Origin`.
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    // any origin is allowed if it is empty.
+    origins: Vec<String>,
+    // the methods of the service are allowed if it is empty.
+    methods: Vec<Method>,
+    // any request headers are allowed if it is empty.
+    headers: Vec<HeaderName>,
+    expose_headers: Vec<HeaderName>,
+    max_age: Option<Duration>,
+    credentials: bool,
+}
+
+impl Cors {
+    /// allow any origin, the methods of the service and any request headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// allow the origin only, e.g. `https://example.com`, any origin is allowed
+    /// if no origin is added.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.origins.push(origin.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// the methods of the preflight allowed, the methods of the service by default.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// the request headers of the preflight allowed, any headers by default.
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.headers = headers.into_iter().collect();
+        self
+    }
+
+    /// the response headers readable by the script, e.g. `Content-Range`.
+    pub fn expose_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.expose_headers = headers.into_iter().collect();
+        self
+    }
+
+    /// the duration the preflight result is cached by the browser.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// send the cookies, the request origin is sent back instead of `*`.
+    pub fn allow_credentials(mut self, enable: bool) -> Self {
+        self.credentials = enable;
+        self
+    }
+
+    /// the `Access-Control-Allow-Origin` of the request origin, `None` if it is not allowed.
+    fn allow_origin_value(&self, headers: &HeaderMap) -> Option<HeaderValue> {
+        let origin = headers.get(header::ORIGIN)?;
+        if self.origins.is_empty() && !self.credentials {
+            return Some(HeaderValue::from_static("*"));
+        }
+        let is_allowed = self.origins.is_empty() || self.origins.iter().any(|o| o == origin);
+        is_allowed.then(|| origin.clone())
+    }
+
+    /// the response of the preflight request, `None` if it is not the preflight of
+    /// the allowed origin and method.
+    pub(crate) fn preflight<B>(
+        &self,
+        request: &Request<B>,
+        methods: &[Method],
+    ) -> Option<Response<Body>> {
+        let headers = request.headers();
+        if request.method() != Method::OPTIONS {
+            return None;
+        }
+        let method = headers.get(header::ACCESS_CONTROL_REQUEST_METHOD)?;
+        let methods = if self.methods.is_empty() {
+            methods
+        } else {
+            &self.methods
+        };
+        let allow_origin = self.allow_origin_value(headers)?;
+        if !methods
+            .iter()
+            .any(|m| m.as_str().as_bytes() == method.as_bytes())
+        {
+            return None;
+        }
+        let methods: Vec<&str> = methods.iter().map(Method::as_str).collect();
+        let mut builder = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, methods.join(", "));
+        let requested = headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS);
+        if self.headers.is_empty() {
+            if let Some(requested) = requested {
+                builder = builder.header(header::ACCESS_CONTROL_ALLOW_HEADERS, requested);
+            }
+        } else {
+            let names: Vec<&str> = self.headers.iter().map(HeaderName::as_str).collect();
+            builder = builder.header(header::ACCESS_CONTROL_ALLOW_HEADERS, names.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            builder = builder.header(header::ACCESS_CONTROL_MAX_AGE, max_age.as_secs());
+        }
+        if self.credentials {
+            builder = builder.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+        }
+        let vary = "Origin, Access-Control-Request-Method, Access-Control-Request-Headers";
+        builder = builder.header(header::VARY, vary);
+        builder.body(Body::Empty).ok()
+    }
+
+    /// add the headers of the allowed origin to the response.
+    pub(crate) fn apply(&self, request: &HeaderMap, resp: &mut Response<Body>) {
+        let allow_origin = self.allow_origin_value(request);
+        let headers = resp.headers_mut();
+        // the response of the origin is different unless any origin is allowed.
+        if allow_origin.as_ref().is_none_or(|o| o != "*") {
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        let allow_origin = match allow_origin {
+            Some(allow_origin) => allow_origin,
+            None => return,
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.credentials {
+            let value = HeaderValue::from_static("true");
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, value);
+        }
+        if !self.expose_headers.is_empty() {
+            let names: Vec<&str> = self.expose_headers.iter().map(HeaderName::as_str).collect();
+            if let Ok(value) = HeaderValue::from_str(&names.join(", ")) {
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn preflight(cors: &Cors, origin: &str, method: &str) -> Option<Response<Body>> {
+        let req = Request::options("/font.woff2")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method)
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "range")
+            .body(())
+            .unwrap();
+        cors.preflight(&req, &[Method::GET, Method::HEAD])
+    }
+
+    #[test]
+    fn test_preflight() {
+        let cors = Cors::new().max_age(Duration::from_secs(600));
+        let resp = preflight(&cors, "https://a.example", "GET").unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, HEAD");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "range");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert!(preflight(&cors, "https://a.example", "POST").is_none());
+
+        let cors = Cors::new()
+            .allow_origin("https://a.example")
+            .allow_credentials(true);
+        let resp = preflight(&cors, "https://a.example", "GET").unwrap();
+        let headers = resp.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://a.example"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert!(preflight(&cors, "https://b.example", "GET").is_none());
+    }
+
+    #[test]
+    fn test_apply() {
+        let cors = Cors::new()
+            .allow_origin("https://a.example")
+            .expose_headers([header::CONTENT_RANGE]);
+        let mut request = HeaderMap::new();
+        request.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://a.example"),
+        );
+        let mut resp = Response::new(Body::Empty);
+        cors.apply(&request, &mut resp);
+        let headers = resp.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://a.example"
+        );
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "content-range"
+        );
+        assert_eq!(headers[header::VARY], "Origin");
+
+        let mut resp = Response::new(Body::Empty);
+        cors.apply(&HeaderMap::new(), &mut resp);
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(resp.headers()[header::VARY], "Origin");
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt::Write;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// the `attr-char` of RFC 5987, the others should be percent encoded.
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// render the `Content-Disposition: attachment` value of the file name.
+/// the non ascii name is encoded with the `filename*` parameter of RFC 5987,
+/// and the `filename` parameter is the ascii fallback.
+pub(crate) fn attachment(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect();
+    let mut value = format!("attachment; filename=\"{fallback}\"");
+    if fallback != filename {
+        let encoded = utf8_percent_encode(filename, ATTR_CHAR);
+        write!(&mut value, "; filename*=UTF-8''{encoded}").expect("buffer write failed");
+    }
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_attachment() {
+        assert_eq!(attachment("a.txt"), "attachment; filename=\"a.txt\"");
+        assert_eq!(
+            attachment("a \"b\".txt"),
+            "attachment; filename=\"a _b_.txt\"; filename*=UTF-8''a%20%22b%22.txt"
+        );
+        assert_eq!(
+            attachment("€ rates.pdf"),
+            "attachment; filename=\"_ rates.pdf\"; filename*=UTF-8''%E2%82%AC%20rates.pdf"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+
+use hyper::{body::Bytes, Request};
+
+use crate::{
+    archive::{Source, Tree},
+    file::{self, FileMeta},
+    request_resolve::{self, ResolveOptions, Resolved},
+};
+
+struct EmbeddedFile {
+    content: Bytes,
+    // the hash of the content, used as the inode of the entity tag.
+    hash: u64,
+}
+
+/// The assets embedded in the binary, e.g. by `include_bytes!` or the `embed_assets!`
+/// macro, served by `FileService::embedded` as the files under the root.
+#[derive(Default)]
+pub struct EmbeddedAssets {
+    files: HashMap<String, EmbeddedFile>,
+    dirs: HashSet<String>,
+}
+
+impl EmbeddedAssets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add the asset of the path relative to the root, e.g. `css/site.css`,
+    /// the invalid path is ignored, e.g. the path has `..`.
+    pub fn add(mut self, path: &str, content: &'static [u8]) -> Self {
+        let path = match request_resolve::normalize_path(path) {
+            Some(path) if !path.is_empty() => path,
+            _ => return self,
+        };
+        let mut dir = path.as_str();
+        while let Some((parent, _)) = dir.rsplit_once('/') {
+            self.dirs.insert(parent.to_string());
+            dir = parent;
+        }
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let file = EmbeddedFile {
+            content: Bytes::from_static(content),
+            hash: hasher.finish(),
+        };
+        self.files.insert(path, file);
+        self
+    }
+
+    /// the meta of the asset, the modification time is unknown.
+    pub(crate) fn find(&self, path: &str) -> Option<FileMeta> {
+        let file = self.files.get(path)?;
+        Some(FileMeta {
+            path: PathBuf::from(path),
+            size: file.content.len() as u64,
+            inode: file.hash,
+            is_dir: false,
+            modified: None,
+            permisions: file::readonly(),
+            source: Some(Source::Memory {
+                content: file.content.clone(),
+                content_type: None,
+            }),
+        })
+    }
+
+    /// resolve the request path by the options as the files under the root.
+    pub(crate) fn resolve<B>(&self, request: &Request<B>, options: &ResolveOptions) -> Resolved {
+        if !options.methods.contains(request.method()) {
+            return Resolved::MethodNotMatched;
+        }
+        let path = match request_resolve::normalized_path(request) {
+            Some(path) => path,
+            None => return Resolved::BadRequest,
+        };
+        if let Some(rejected) = options.reject(&path) {
+            return rejected;
+        }
+        let tree = Tree {
+            prefix: "",
+            is_dir: |path: &str| path.is_empty() || self.dirs.contains(path),
+            find: |path: &str| self.find(path),
+        };
+        tree.resolve(&path, request, options)
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'static [u8])> for EmbeddedAssets {
+    fn from_iter<T: IntoIterator<Item = (&'a str, &'static [u8])>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |assets, (path, content)| {
+                assets.add(path, content)
+            })
+    }
+}
+
+/// embed the files under the directory relative to the crate root into the binary,
+/// e.g. `embed_assets!("ui", "index.html", "js/app.js")`.
+#[macro_export]
+macro_rules! embed_assets {
+    ($dir:literal $(, $path:literal)* $(,)?) => {
+        $crate::EmbeddedAssets::new()
+            $(.add($path, include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $dir, "/", $path))))*
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_embed_assets() {
+        let assets = crate::embed_assets!("src", "lib.rs", "body/mod.rs");
+        let meta = assets.find("body/mod.rs").unwrap();
+        assert_eq!(
+            meta.size,
+            std::fs::metadata("src/body/mod.rs").unwrap().len()
+        );
+        assert!(assets.dirs.contains("body"));
+        assert!(assets.find("body").is_none());
+
+        let assets: EmbeddedAssets = [("a.txt", &b"a"[..]), ("../b.txt", b"b")]
+            .into_iter()
+            .collect();
+        assert!(assets.find("a.txt").is_some());
+        assert!(assets.find("b.txt").is_none());
+    }
+}
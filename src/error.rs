@@ -1,4 +1,11 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    net::IpAddr,
+    sync::Arc,
+};
+
+use hyper::{header::HeaderValue, Method, Uri};
 
 #[derive(Debug)]
 pub enum ParseError {
@@ -16,3 +23,20 @@ impl Display for ParseError {
         }
     }
 }
+
+/// The unexpected error responded as `500 Internal Server Error`, in the extensions
+/// of the response, e.g. to be logged by the middleware.
+#[derive(Debug, Clone)]
+pub struct InternalError(pub Arc<io::Error>);
+
+/// The request of the error observed by the `on_error` hook.
+#[derive(Debug, Clone)]
+pub struct RequestMeta {
+    pub method: Method,
+    /// the uri of the request, it may be stripped and rewritten already.
+    pub uri: Uri,
+    /// the address of the client, `None` if the peer address is not in the extensions.
+    pub remote: Option<IpAddr>,
+    /// the request id of the header set by `FileService::request_id`.
+    pub request_id: Option<HeaderValue>,
+}
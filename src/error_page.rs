@@ -0,0 +1,23 @@
+use hyper::body::Bytes;
+
+/// The body of the error response.
+#[derive(Debug, Clone)]
+pub enum ErrorPage {
+    /// the file relative to the root, the content type is guessed from the extension.
+    File(String),
+    /// the static content with the content type.
+    Static { content_type: String, body: Bytes },
+}
+
+impl ErrorPage {
+    pub fn file(path: impl Into<String>) -> Self {
+        ErrorPage::File(path.into())
+    }
+
+    pub fn static_content(content_type: impl Into<String>, body: impl Into<Bytes>) -> Self {
+        ErrorPage::Static {
+            content_type: content_type.into(),
+            body: body.into(),
+        }
+    }
+}
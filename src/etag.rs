@@ -0,0 +1,370 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter, Write};
+use std::future::poll_fn;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::file::{BlockingFuture, FileMeta};
+use crate::vfs::{self, Vfs};
+
+const HASH_BUF_SIZE: usize = 64 * 1024;
+
+/// The entity tag, the validator of the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    pub tag: String,
+}
+
+impl EntityTag {
+    pub fn new(weak: bool, tag: impl Into<String>) -> Self {
+        Self {
+            weak,
+            tag: tag.into(),
+        }
+    }
+
+    /// generate the weak entity tag from the file meta, inode, size and mtime.
+    pub fn from_meta(file: &FileMeta) -> Self {
+        let mtime = file
+            .modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let tag = format!("{:x}-{:x}-{:x}", file.inode, file.size, mtime);
+        Self::new(true, tag)
+    }
+
+    /// parse the single entity tag, e.g. `"xyz"` or `W/"xyz"`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (weak, s) = match s.strip_prefix("W/") {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let tag = s.strip_prefix('"')?.strip_suffix('"')?;
+        if tag.contains('"') {
+            return None;
+        }
+        Some(Self::new(weak, tag))
+    }
+
+    /// the strong comparison, both must be not weak.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// the weak comparison, ignore the weak flag.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/\"{}\"", self.tag)
+        } else {
+            write!(f, "\"{}\"", self.tag)
+        }
+    }
+}
+
+/// check the `If-Match`/`If-None-Match` header value is matched the entity tag.
+/// the value may be `*` or the list of entity tags, `*` is matched any current representation.
+pub fn is_tag_list_matched(header: &str, etag: Option<&EntityTag>, weak: bool) -> bool {
+    if header.trim() == "*" {
+        return true;
+    }
+    let etag = match etag {
+        Some(etag) => etag,
+        None => return false,
+    };
+    header.split(',').filter_map(EntityTag::parse).any(|t| {
+        if weak {
+            t.weak_eq(etag)
+        } else {
+            t.strong_eq(etag)
+        }
+    })
+}
+
+/// The strategy to generate the entity tag of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EtagStrategy {
+    /// the weak entity tag generated from the inode, size and mtime.
+    #[default]
+    Weak,
+    /// the strong entity tag generated from the SHA-256 hash of the content,
+    /// the hash is computed when the file is first served and cached.
+    Strong,
+    /// don't generate the entity tag.
+    Disabled,
+}
+
+/// the strong entity tags cached at most, the least recently used is evicted.
+const MAX_CACHED_TAGS: usize = 4096;
+
+struct CachedTag {
+    inode: u64,
+    size: u64,
+    modified: SystemTime,
+    etag: EntityTag,
+    // the tick of the last use.
+    tick: u64,
+}
+
+#[derive(Default)]
+struct TagState {
+    entries: HashMap<PathBuf, CachedTag>,
+    // the paths ordered by the last use, the least recently used is the first.
+    recency: BTreeMap<u64, PathBuf>,
+    tick: u64,
+}
+
+impl TagState {
+    fn remove(&mut self, path: &Path) {
+        if let Some(cached) = self.entries.remove(path) {
+            self.recency.remove(&cached.tick);
+        }
+    }
+
+    fn touch(&mut self, path: &Path) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(cached) = self.entries.get_mut(path) {
+            self.recency.remove(&cached.tick);
+            cached.tick = tick;
+            self.recency.insert(tick, path.to_path_buf());
+        }
+    }
+}
+
+/// The LRU cache of the strong entity tags bounded by the entries, the entry is
+/// recomputed when the meta changed.
+pub(crate) struct EtagCache {
+    max_entries: usize,
+    state: Mutex<TagState>,
+}
+
+impl Default for EtagCache {
+    fn default() -> Self {
+        Self::new(MAX_CACHED_TAGS)
+    }
+}
+
+impl EtagCache {
+    fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            state: Default::default(),
+        }
+    }
+
+    /// the cached tag of the file, the stale entry is removed.
+    fn get(&self, file: &FileMeta) -> Option<EntityTag> {
+        let modified = file.modified?;
+        let mut state = self.state.lock().unwrap();
+        let cached = state.entries.get(&file.path)?;
+        if cached.inode != file.inode || cached.size != file.size || cached.modified != modified {
+            state.remove(&file.path);
+            return None;
+        }
+        let etag = cached.etag.clone();
+        state.touch(&file.path);
+        Some(etag)
+    }
+
+    fn insert(&self, file: &FileMeta, etag: EntityTag) {
+        // without mtime, can't tell the content changed.
+        let modified = match file.modified {
+            Some(modified) if self.max_entries > 0 => modified,
+            _ => return,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.remove(&file.path);
+        while state.entries.len() >= self.max_entries {
+            let path = match state.recency.values().next() {
+                Some(path) => path.clone(),
+                None => break,
+            };
+            state.remove(&path);
+        }
+        let cached = CachedTag {
+            inode: file.inode,
+            size: file.size,
+            modified,
+            etag,
+            tick: 0,
+        };
+        state.entries.insert(file.path.clone(), cached);
+        state.touch(&file.path);
+    }
+
+    /// remove the tags of the path and the paths under it.
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    pub(crate) fn invalidate(&self, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        let paths: Vec<PathBuf> = state
+            .entries
+            .keys()
+            .filter(|p| p.starts_with(path))
+            .cloned()
+            .collect();
+        for path in paths {
+            state.remove(&path);
+        }
+    }
+}
+
+fn strong_tag(hasher: Sha256) -> EntityTag {
+    let mut tag = String::with_capacity(64);
+    for b in hasher.finalize() {
+        write!(&mut tag, "{b:02x}").expect("buffer write failed");
+    }
+    EntityTag::new(false, tag)
+}
+
+fn content_hash(file: FileMeta) -> io::Result<EntityTag> {
+    let mut file = crate::file::open_std(&file)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; HASH_BUF_SIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(strong_tag(hasher))
+}
+
+/// hash the content of the file read by the virtual filesystem.
+async fn vfs_content_hash(vfs: &Vfs, file: &FileMeta) -> io::Result<EntityTag> {
+    let mut reader = vfs.open(file).await?;
+    let mut hasher = Sha256::new();
+    loop {
+        let chunk = poll_fn(|cx| reader.poll_read(cx, u64::MAX, HASH_BUF_SIZE)).await?;
+        if chunk.is_empty() {
+            break;
+        }
+        hasher.update(&chunk);
+    }
+    Ok(strong_tag(hasher))
+}
+
+impl EtagStrategy {
+    /// generate the entity tag of the file by the strategy.
+    pub(crate) async fn entity_tag(
+        &self,
+        file: &FileMeta,
+        cache: &Arc<EtagCache>,
+    ) -> io::Result<Option<EntityTag>> {
+        match *self {
+            EtagStrategy::Disabled => Ok(None),
+            EtagStrategy::Weak => Ok(Some(EntityTag::from_meta(file))),
+            EtagStrategy::Strong => {
+                if let Some(etag) = cache.get(file) {
+                    return Ok(Some(etag));
+                }
+                let etag = match vfs::vfs_path(file) {
+                    Some((vfs, _)) => vfs_content_hash(vfs, file).await?,
+                    None => {
+                        let meta = file.clone();
+                        BlockingFuture::spawn(move || content_hash(meta)).await?
+                    }
+                };
+                cache.insert(file, etag.clone());
+                Ok(Some(etag))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            EntityTag::parse("\"abc\""),
+            Some(EntityTag::new(false, "abc"))
+        );
+        assert_eq!(
+            EntityTag::parse(" W/\"abc\" "),
+            Some(EntityTag::new(true, "abc"))
+        );
+        assert_eq!(EntityTag::parse("\"\""), Some(EntityTag::new(false, "")));
+        assert_eq!(EntityTag::parse("abc"), None);
+        assert_eq!(EntityTag::parse("\"a\"b\""), None);
+        assert_eq!(EntityTag::new(true, "abc").to_string(), "W/\"abc\"");
+    }
+
+    #[test]
+    fn test_tag_list_matched() {
+        let weak = EntityTag::new(true, "abc");
+        let strong = EntityTag::new(false, "abc");
+        assert!(is_tag_list_matched("*", Some(&weak), true));
+        assert!(is_tag_list_matched("*", None, true));
+        assert!(!is_tag_list_matched("\"abc\"", None, true));
+        assert!(is_tag_list_matched("\"x\", \"abc\"", Some(&weak), true));
+        assert!(!is_tag_list_matched("\"x\", \"abc\"", Some(&weak), false));
+        assert!(is_tag_list_matched("\"x\", \"abc\"", Some(&strong), false));
+        assert!(!is_tag_list_matched("W/\"abc\"", Some(&strong), false));
+        assert!(!is_tag_list_matched("\"y\"", Some(&strong), true));
+    }
+
+    #[tokio::test]
+    async fn test_strong_etag() {
+        use crate::file::{FileReaderOpener, TokioFileReaderOpener};
+
+        let dir = std::env::temp_dir();
+        let name = "blockless-strong-etag.txt";
+        std::fs::write(dir.join(name), b"hello").unwrap();
+        let opener = TokioFileReaderOpener::new(dir);
+        let file = opener.metadata(name).await.unwrap();
+        let cache = Arc::new(EtagCache::default());
+        let etag = EtagStrategy::Strong
+            .entity_tag(&file, &cache)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!etag.weak);
+        assert_eq!(
+            etag.tag,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(cache.get(&file), Some(etag));
+        let disabled = EtagStrategy::Disabled.entity_tag(&file, &cache).await;
+        assert!(disabled.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache_bounded() {
+        use crate::file::{FileReaderOpener, TokioFileReaderOpener};
+
+        let dir = std::env::temp_dir().join("blockless-etag-bounded");
+        std::fs::create_dir_all(&dir).unwrap();
+        let opener = TokioFileReaderOpener::new(&dir);
+        let mut files = Vec::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(dir.join(name), name).unwrap();
+            files.push(opener.metadata(name).await.unwrap());
+        }
+        let cache = EtagCache::new(2);
+        cache.insert(&files[0], EntityTag::new(false, "a"));
+        cache.insert(&files[1], EntityTag::new(false, "b"));
+        // `a` is used recently, so `b` is evicted.
+        assert!(cache.get(&files[0]).is_some());
+        cache.insert(&files[2], EntityTag::new(false, "c"));
+        assert!(cache.get(&files[1]).is_none());
+        assert!(cache.get(&files[0]).is_some());
+        assert!(cache.get(&files[2]).is_some());
+        assert_eq!(cache.state.lock().unwrap().entries.len(), 2);
+    }
+}
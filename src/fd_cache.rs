@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::file::FileMeta;
+
+#[derive(Debug)]
+struct IdleFile {
+    file: File,
+    inode: u64,
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+    since: Instant,
+}
+
+impl IdleFile {
+    /// the file is not replaced or modified since it is opened.
+    fn is_matched(&self, meta: &FileMeta) -> bool {
+        self.inode == meta.inode && self.size == meta.size && self.modified == meta.modified
+    }
+}
+
+#[derive(Debug, Default)]
+struct IdleFiles {
+    files: HashMap<PathBuf, Vec<IdleFile>>,
+    count: usize,
+}
+
+/// The cache of the idle open files, the file is taken by one request at a time
+/// and put back after the body is dropped. the file idle longer than the timeout is closed.
+#[derive(Debug)]
+pub(crate) struct FdCache {
+    max_files: usize,
+    idle_timeout: Duration,
+    idle: Mutex<IdleFiles>,
+}
+
+impl FdCache {
+    pub fn new(max_files: usize, idle_timeout: Duration) -> Self {
+        Self {
+            max_files,
+            idle_timeout,
+            idle: Default::default(),
+        }
+    }
+
+    fn evict_expired(&self, idle: &mut IdleFiles, now: Instant) {
+        let mut closed = 0;
+        idle.files.retain(|_, files| {
+            let len = files.len();
+            files.retain(|f| now.duration_since(f.since) < self.idle_timeout);
+            closed += len - files.len();
+            !files.is_empty()
+        });
+        idle.count -= closed;
+    }
+
+    /// take the idle file of the resolved meta, the stale files of the path are closed.
+    pub fn take(&self, meta: &FileMeta) -> Option<File> {
+        let mut idle = self.idle.lock().unwrap();
+        self.evict_expired(&mut idle, Instant::now());
+        let files = idle.files.get_mut(&meta.path)?;
+        let len = files.len();
+        files.retain(|f| f.is_matched(meta));
+        let file = files.pop();
+        let is_empty = files.is_empty();
+        let taken = len - files.len();
+        if is_empty {
+            idle.files.remove(&meta.path);
+        }
+        idle.count -= taken;
+        file.map(|f| f.file)
+    }
+
+    /// put the file back, the file is closed if the cache is full.
+    pub fn put(&self, meta: &FileMeta, file: File) {
+        let now = Instant::now();
+        let mut idle = self.idle.lock().unwrap();
+        self.evict_expired(&mut idle, now);
+        if idle.count >= self.max_files {
+            return;
+        }
+        idle.count += 1;
+        idle.files
+            .entry(meta.path.clone())
+            .or_default()
+            .push(IdleFile {
+                file,
+                inode: meta.inode,
+                size: meta.size,
+                modified: meta.modified,
+                since: now,
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fd_cache() {
+        let path = std::env::temp_dir().join("blockless-fd-cache.txt");
+        std::fs::write(&path, b"hello").unwrap();
+        let meta = FileMeta::from_path(&path).await.unwrap();
+        let cache = FdCache::new(1, Duration::from_secs(60));
+        assert!(cache.take(&meta).is_none());
+        cache.put(&meta, File::open(&path).unwrap());
+        // the cache is full.
+        cache.put(&meta, File::open(&path).unwrap());
+        assert!(cache.take(&meta).is_some());
+        assert!(cache.take(&meta).is_none());
+
+        cache.put(&meta, File::open(&path).unwrap());
+        let mut changed = meta.clone();
+        changed.size = 6;
+        assert!(cache.take(&changed).is_none());
+        assert!(cache.take(&meta).is_none());
+
+        let cache = FdCache::new(1, Duration::ZERO);
+        cache.put(&meta, File::open(&path).unwrap());
+        assert!(cache.take(&meta).is_none());
+    }
+}
@@ -1,33 +1,125 @@
 use std::{
     cmp::min,
-    fs::{OpenOptions, Permissions},
+    fs::{Metadata, OpenOptions, Permissions},
     future::Future,
     io::SeekFrom,
     io::{Error, ErrorKind, Result},
-    mem::MaybeUninit,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::SystemTime,
 };
 
-use hyper::body::Bytes;
-use tokio::{
-    fs::File,
-    io::{AsyncRead, AsyncSeek, ReadBuf},
-    task::JoinHandle,
+use crate::{
+    archive::{MemberReader, Source},
+    fd_cache::FdCache,
+    read_ahead::ReadAhead,
+    runtime,
+    vfs::VfsReader,
 };
+use bytes::BytesMut;
+use hyper::body::Bytes;
 
-const READ_BUF_SIZE: usize = 4 * 1024;
+use futures_channel::oneshot;
+#[cfg(all(feature = "tokio", not(target_os = "wasi")))]
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
 
-/// file with the meta use for body stream.
-#[derive(Debug)]
-pub struct FileWithMeta {
+#[cfg(any(not(feature = "tokio"), target_os = "wasi"))]
+use crate::wasi::File;
+
+/// the default size of the chunk read from the file.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The meta info of the file, used to resolve the request and the conditional headers
+/// without opening the file.
+#[derive(Debug, Clone)]
+pub struct FileMeta {
+    /// the full path of the file under the root.
+    pub path: PathBuf,
+    /// the size of the file in bytes.
     pub size: u64,
-    pub file: File,
+    /// the inode of the file, `0` if it is not supported by the platform.
+    pub inode: u64,
     pub is_dir: bool,
+    /// the last modification time.
     pub modified: Option<SystemTime>,
     pub permisions: Permissions,
+    // the content is read from the archive, e.g. the member of the zip.
+    pub(crate) source: Option<Source>,
+}
+
+impl FileMeta {
+    pub(crate) fn new(path: PathBuf, meta: &Metadata) -> Self {
+        Self {
+            path,
+            size: meta.len(),
+            inode: inode(meta),
+            is_dir: meta.is_dir(),
+            modified: meta.modified().ok(),
+            permisions: meta.permissions(),
+            source: None,
+        }
+    }
+
+    /// the meta of the file of the virtual filesystem, e.g. the object of the bucket.
+    /// the inode is `0`, set it to identify the content in the entity tag.
+    pub fn virtual_file(path: impl Into<PathBuf>, size: u64, modified: Option<SystemTime>) -> Self {
+        Self {
+            path: path.into(),
+            size,
+            inode: 0,
+            is_dir: false,
+            modified,
+            permisions: readonly(),
+            source: None,
+        }
+    }
+
+    /// the meta of the directory of the virtual filesystem.
+    pub fn virtual_dir(path: impl Into<PathBuf>) -> Self {
+        Self {
+            is_dir: true,
+            ..Self::virtual_file(path, 0, None)
+        }
+    }
+
+    /// get the meta of the path, e.g. the file looked up by the custom resolver.
+    pub async fn from_path(path: impl Into<PathBuf>) -> Result<Self> {
+        FileMetaFuture::new(path.into()).await
+    }
+}
+
+/// file with the meta use for body stream.
+#[derive(Debug)]
+pub struct FileWithMeta {
+    pub meta: FileMeta,
+    // `None` if the content is cached in memory.
+    pub file: Option<std::fs::File>,
+    // the content in memory, e.g. the map of the file or the cached bytes.
+    pub(crate) content: Option<Bytes>,
+    // the file is put back to the cache after the body is dropped.
+    pub(crate) fd_cache: Option<Arc<FdCache>>,
+    // the file opened by the virtual filesystem.
+    pub(crate) vfs: Option<VfsReader>,
+}
+
+/// the read-only permissions of the file without metadata, e.g. the embedded asset.
+pub(crate) fn readonly() -> Permissions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Permissions::from_mode(0o444)
+    }
+    #[cfg(not(unix))]
+    {
+        let mut permissions = std::fs::metadata(std::env::temp_dir())
+            .expect("the permissions of the temporary directory")
+            .permissions();
+        permissions.set_readonly(true);
+        permissions
+    }
 }
 
 /// The file reader which read the bytes from file to fill the body.
@@ -40,23 +132,126 @@ pub trait FileReaderOpener: Send + Sync + 'static {
 
     type Future: Future<Output = Result<Self::Output>> + Unpin + 'static;
 
-    fn open<T: AsRef<Path>>(&self, path: T) -> Self::Future;
+    type MetaFuture: Future<Output = Result<FileMeta>> + Unpin + 'static;
+
+    /// get the meta of the file without opening it.
+    fn metadata<T: AsRef<Path>>(&self, path: T) -> Self::MetaFuture;
+
+    /// open the file resolved by `metadata`.
+    fn open(&self, meta: &FileMeta) -> Self::Future;
 }
 
 /// The file reader which read the bytes from file to fill the body.
 /// Using th tokio file in tokio async runtime.
 pub struct TokioFileReader {
-    file: tokio::fs::File,
-    buf: Box<[MaybeUninit<u8>; READ_BUF_SIZE]>,
+    file: Option<File>,
+    // the chunks are split from the buffer, which is reclaimed after the chunks are dropped.
+    buf: BytesMut,
+    chunk_size: usize,
+    // the chunks are sliced from the content in memory without copying.
+    content: Option<Bytes>,
+    // the position of the next chunk of the content.
+    position: u64,
+    // put the file back to the cache when the reader is dropped.
+    release: Option<(Arc<FdCache>, FileMeta)>,
+    // the member read from the archive file.
+    member: Option<MemberReader>,
+    // the file read by the virtual filesystem.
+    vfs: Option<VfsReader>,
+    // the file read ahead of the chunks sent.
+    read_ahead: Option<ReadAhead>,
+    // the size of the file when it is opened.
+    size: u64,
 }
 
 impl TokioFileReader {
-    fn new(file: File) -> Self {
+    pub(crate) fn new(file: FileWithMeta, chunk_size: usize) -> Self {
+        let FileWithMeta {
+            meta,
+            file,
+            content,
+            fd_cache,
+            vfs,
+        } = file;
+        // the archive is read from the member without the cached content.
+        let member = match (&meta.source, content.is_none()) {
+            (Some(Source::Archive { member, .. }), true) => Some(member.clone()),
+            _ => None,
+        };
+        let (file, member) = match (file, member) {
+            (Some(file), Some(member)) => (None, Some(MemberReader::new(file, member))),
+            (file, _) => (file, None),
+        };
+        // the tokio file is read in the tokio runtime only, the file is read by
+        // the blocking executor otherwise, the mapped file is kept for the size.
+        let (file, read_ahead) = match file {
+            Some(file) if content.is_none() && !runtime::is_tokio() => {
+                (None, Some(ReadAhead::new(file, meta.size, 1)))
+            }
+            file => (file.map(File::from_std), None),
+        };
         Self {
             file,
-            buf: Box::new([MaybeUninit::uninit(); READ_BUF_SIZE]),
+            buf: BytesMut::new(),
+            chunk_size: chunk_size.max(1),
+            content,
+            position: 0,
+            size: meta.size,
+            release: fd_cache.map(|cache| (cache, meta)),
+            member,
+            vfs,
+            read_ahead,
         }
     }
+
+    /// read the chunks ahead of the chunk sent, `0` reads the chunk when it is polled.
+    /// only the file read from the beginning is read ahead, e.g. not the archive member.
+    pub(crate) fn read_ahead(mut self, depth: usize) -> Self {
+        if depth == 0 || self.content.is_some() {
+            return self;
+        }
+        if let Some(ref mut read_ahead) = self.read_ahead {
+            read_ahead.set_depth(depth);
+        }
+        if let Some(file) = self.file.take() {
+            match file.try_into_std() {
+                Ok(file) => self.read_ahead = Some(ReadAhead::new(file, self.size, depth)),
+                Err(file) => self.file = Some(file),
+            }
+        }
+        self
+    }
+
+    /// read the file by io_uring instead of the tokio file, the chunks are read into
+    /// the buffers sent without copying. the file is read as it is if the ring is
+    /// not available.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub(crate) fn io_uring(mut self, enable: bool) -> Self {
+        if !enable || self.content.is_some() || !crate::uring::is_available() {
+            return self;
+        }
+        if let Some(file) = self.file.take() {
+            match file.try_into_std() {
+                Ok(file) => self.read_ahead = Some(ReadAhead::new(file, self.size, 1)),
+                Err(file) => self.file = Some(file),
+            }
+        }
+        if let Some(ref mut read_ahead) = self.read_ahead {
+            read_ahead.set_io_uring(true);
+        }
+        self
+    }
+
+    /// slice the chunk from the content without copying.
+    fn read_content(&mut self, content: &Bytes, readn: u64) -> Result<Bytes> {
+        let start = min(self.position, content.len() as u64);
+        let end = min(
+            start + min(readn, self.chunk_size as u64),
+            content.len() as u64,
+        );
+        self.position = end;
+        Ok(content.slice(start as usize..end as usize))
+    }
 }
 
 impl FileReader for TokioFileReader {
@@ -66,21 +261,41 @@ impl FileReader for TokioFileReader {
         cx: &mut Context<'_>,
         readn: u64,
     ) -> Poll<Result<Bytes>> {
+        if let Some(content) = self.content.clone() {
+            return Poll::Ready(self.read_content(&content, readn));
+        }
         let Self {
             ref mut file,
             ref mut buf,
+            chunk_size,
+            ref mut member,
+            ref mut vfs,
+            ref mut read_ahead,
+            ..
         } = *self;
-        let buf_len = min(readn as usize, buf.len());
-        let mut buf = ReadBuf::uninit(&mut buf[..buf_len]);
-        match Pin::new(file).poll_read(cx, &mut buf) {
+        if let Some(member) = member {
+            return member.poll_read(cx, readn, chunk_size);
+        }
+        if let Some(vfs) = vfs {
+            return vfs.poll_read(cx, readn, chunk_size);
+        }
+        if let Some(read_ahead) = read_ahead {
+            return read_ahead.poll_read(cx, readn, chunk_size);
+        }
+        let file = match file {
+            Some(file) => file,
+            None => return Poll::Ready(Ok(Bytes::new())),
+        };
+        let buf_len = min(readn, chunk_size as u64) as usize;
+        // no allocation if the previous chunks are dropped.
+        buf.reserve(buf_len);
+        let mut read_buf = ReadBuf::uninit(&mut buf.spare_capacity_mut()[..buf_len]);
+        match Pin::new(file).poll_read(cx, &mut read_buf) {
             Poll::Ready(Ok(())) => {
-                let bs = buf.filled();
-                if bs.is_empty() {
-                    Poll::Ready(Ok(Bytes::new()))
-                } else {
-                    let bs = Bytes::copy_from_slice(bs);
-                    Poll::Ready(Ok(bs))
-                }
+                let filled = read_buf.filled().len();
+                // SAFETY: the `filled` bytes of the spare capacity are initialized by the read.
+                unsafe { buf.set_len(filled) };
+                Poll::Ready(Ok(buf.split().freeze()))
             }
             Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
             Poll::Pending => Poll::Pending,
@@ -90,71 +305,313 @@ impl FileReader for TokioFileReader {
 
 impl AsyncSeek for TokioFileReader {
     fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> Result<()> {
-        Pin::new(&mut self.get_mut().file).start_seek(position)
+        let this = self.get_mut();
+        if let (None, Some(member)) = (&this.content, &mut this.member) {
+            return member.start_seek(position);
+        }
+        if let (None, Some(vfs)) = (&this.content, &mut this.vfs) {
+            return vfs.start_seek(position);
+        }
+        if let Some(ref mut read_ahead) = this.read_ahead {
+            return read_ahead.start_seek(position);
+        }
+        let len = match (&this.content, &mut this.file) {
+            (Some(content), _) => content.len() as u64,
+            (None, Some(file)) => return Pin::new(file).start_seek(position),
+            (None, None) => 0,
+        };
+        let position = match position {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => len.checked_add_signed(n),
+            SeekFrom::Current(n) => this.position.checked_add_signed(n),
+        };
+        this.position =
+            position.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek position"))?;
+        Ok(())
     }
 
     fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<u64>> {
-        Pin::new(&mut self.get_mut().file).poll_complete(cx)
+        let this = self.get_mut();
+        if let (None, Some(member)) = (&this.content, &this.member) {
+            return Poll::Ready(Ok(member.position()));
+        }
+        if let (None, Some(vfs)) = (&this.content, &this.vfs) {
+            return Poll::Ready(Ok(vfs.position()));
+        }
+        if let Some(ref read_ahead) = this.read_ahead {
+            return Poll::Ready(Ok(read_ahead.position()));
+        }
+        match (&this.content, &mut this.file) {
+            (None, Some(file)) => Pin::new(file).poll_complete(cx),
+            _ => Poll::Ready(Ok(this.position)),
+        }
+    }
+}
+
+impl Drop for TokioFileReader {
+    fn drop(&mut self) {
+        let (cache, meta) = match self.release.take() {
+            Some(release) => release,
+            None => return,
+        };
+        // the file with the operation in flight is closed.
+        if let Some(Ok(file)) = self.file.take().map(File::try_into_std) {
+            cache.put(&meta, file);
+        } else if let Some(file) = self.read_ahead.take().and_then(ReadAhead::into_file) {
+            cache.put(&meta, file);
+        }
     }
 }
 
 impl From<FileWithMeta> for TokioFileReader {
     fn from(val: FileWithMeta) -> Self {
-        TokioFileReader::new(val.file)
+        TokioFileReader::new(val, DEFAULT_CHUNK_SIZE)
     }
 }
 
-/// The future get the file and meta info
-pub struct FileWithMetaFuture {
-    inner: JoinHandle<Result<FileWithMeta>>,
+#[cfg(unix)]
+fn inode(meta: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.ino()
 }
 
-impl FileWithMetaFuture {
-    fn new(path: PathBuf) -> Self {
-        let inner = tokio::task::spawn_blocking(move || -> Result<FileWithMeta> {
-            let file = OpenOptions::new().read(true).open(path)?;
-            let meta = file.metadata()?;
-            let file = tokio::fs::File::from_std(file);
-            Ok(FileWithMeta {
-                file,
-                size: meta.len(),
-                is_dir: meta.is_dir(),
-                modified: meta.modified().ok(),
-                permisions: meta.permissions(),
-            })
+#[cfg(not(unix))]
+fn inode(_meta: &Metadata) -> u64 {
+    0
+}
+
+/// read the bytes at the offset without moving the position of the file.
+#[cfg(unix)]
+pub(crate) fn pread(file: &std::fs::File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+pub(crate) fn pread(file: &std::fs::File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn pread(mut file: &std::fs::File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    use std::io::{Read, Seek};
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
+}
+
+/// The future execute the blocking file operation in background, by the executor
+/// set by `set_blocking_executor` or the tokio blocking pool.
+pub struct BlockingFuture<T> {
+    inner: oneshot::Receiver<Result<T>>,
+}
+
+impl<T: Send + 'static> BlockingFuture<T> {
+    pub(crate) fn spawn(f: impl FnOnce() -> Result<T> + Send + 'static) -> Self {
+        let (tx, inner) = oneshot::channel();
+        runtime::spawn_blocking(move || {
+            let _ = tx.send(f());
         });
         Self { inner }
     }
+
+    /// the future completed by the sender, e.g. of the read completed by io_uring.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub(crate) fn channel() -> (oneshot::Sender<Result<T>>, Self) {
+        let (tx, inner) = oneshot::channel();
+        (tx, Self { inner })
+    }
 }
 
-impl Future for FileWithMetaFuture {
-    type Output = Result<FileWithMeta>;
+impl<T> Future for BlockingFuture<T> {
+    type Output = Result<T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        // the result is Result<Result<FileWithMeta>>
+        // the result is Result<Result<T>>
         // Poll::Ready(Ok(r)) => Poll::Ready(r) mean return the Poll::Ready(Ok) or Poll::Ready(Err), flatten
         let p = Pin::new(&mut self.inner).poll(cx);
         match p {
             Poll::Ready(Ok(r)) => Poll::Ready(r),
             Poll::Ready(Err(_)) => {
-                //only Joinhandle error.
-                Poll::Ready(Err(Error::new(
-                    ErrorKind::Other,
-                    "error execute in background.",
-                )))
+                //only the task panicked or dropped by the executor.
+                Poll::Ready(Err(Error::other("error execute in background.")))
             }
             Poll::Pending => Poll::Pending,
         }
     }
 }
 
+/// The future get the file and meta info
+pub type FileWithMetaFuture = BlockingFuture<FileWithMeta>;
+
+impl FileWithMetaFuture {
+    /// open the file, the file not larger than `mmap_max_size` is mapped.
+    fn new(path: PathBuf, mmap_max_size: Option<u64>) -> Self {
+        Self::spawn(move || -> Result<FileWithMeta> {
+            let file = OpenOptions::new().read(true).open(&path)?;
+            let metadata = file.metadata()?;
+            // the meta of the opened file, may be changed after the resolving.
+            let meta = FileMeta::new(path, &metadata);
+            let content = match mmap_max_size {
+                #[cfg(all(feature = "mmap", unix))]
+                Some(max_size)
+                    if meta.size > 0
+                        && meta.size <= max_size
+                        && !crate::mmap::is_shared_writable(&metadata) =>
+                {
+                    // fallback to read the file if it is failed to map.
+                    crate::mmap::map(&file, meta.size as usize).ok()
+                }
+                _ => None,
+            };
+            Ok(FileWithMeta {
+                meta,
+                file: Some(file),
+                content,
+                fd_cache: None,
+                vfs: None,
+            })
+        })
+    }
+}
+
+/// The policy of the symbolic link under the root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// follow the symbolic link freely.
+    #[default]
+    Follow,
+    /// follow the symbolic link only if the target is under the root.
+    WithinRoot,
+    /// never follow the symbolic link, the root itself is not checked.
+    Never,
+}
+
+impl SymlinkPolicy {
+    /// check the path under the root, the path violated the policy is not found.
+    pub(crate) fn check(&self, root: &Path, path: &Path) -> Result<()> {
+        let is_allowed = match self {
+            SymlinkPolicy::Follow => true,
+            SymlinkPolicy::WithinRoot => {
+                let root = std::fs::canonicalize(root)?;
+                std::fs::canonicalize(path)?.starts_with(root)
+            }
+            SymlinkPolicy::Never => {
+                let relative = path.strip_prefix(root).unwrap_or(path);
+                let mut current = root.to_path_buf();
+                let mut is_allowed = true;
+                for component in relative.components() {
+                    current.push(component);
+                    if std::fs::symlink_metadata(&current)?
+                        .file_type()
+                        .is_symlink()
+                    {
+                        is_allowed = false;
+                        break;
+                    }
+                }
+                is_allowed
+            }
+        };
+        if is_allowed {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::NotFound))
+        }
+    }
+}
+
+/// open the reader of the content in blocking, the member is read from the archive.
+pub(crate) fn open_std(meta: &FileMeta) -> Result<Box<dyn std::io::Read + Send>> {
+    match meta.source {
+        Some(ref source) => source.open_std(),
+        None => Ok(Box::new(std::fs::File::open(&meta.path)?)),
+    }
+}
+
+/// The future get the meta info only.
+pub type FileMetaFuture = BlockingFuture<FileMeta>;
+
+impl FileMetaFuture {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self::spawn(move || -> Result<FileMeta> {
+            let meta = std::fs::metadata(&path)?;
+            Ok(FileMeta::new(path, &meta))
+        })
+    }
+
+    /// get the meta info after the path is checked by the symbolic link policy.
+    pub(crate) fn with_symlink_policy(root: PathBuf, path: PathBuf, policy: SymlinkPolicy) -> Self {
+        Self::spawn(move || -> Result<FileMeta> {
+            policy.check(&root, &path)?;
+            let meta = std::fs::metadata(&path)?;
+            Ok(FileMeta::new(path, &meta))
+        })
+    }
+}
+
+/// find the path matched case-insensitively under the root segment by segment, the segment
+/// existing as it is is preferred. return `None` if any segment is not found or ambiguous.
+pub(crate) fn find_case_insensitive(root: PathBuf, path: String) -> BlockingFuture<Option<String>> {
+    BlockingFuture::spawn(move || -> Result<Option<String>> {
+        let mut current = root;
+        let mut segments = Vec::new();
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            if current.join(segment).exists() {
+                current.push(segment);
+                segments.push(segment.to_string());
+                continue;
+            }
+            let lowercase = segment.to_lowercase();
+            let mut matched = std::fs::read_dir(&current)?
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| name.to_lowercase() == lowercase);
+            match (matched.next(), matched.next()) {
+                (Some(name), None) => {
+                    current.push(&name);
+                    segments.push(name);
+                }
+                _ => return Ok(None),
+            }
+        }
+        Ok(Some(segments.join("/")))
+    })
+}
+
 pub struct TokioFileReaderOpener {
     root: PathBuf,
+    symlink_policy: SymlinkPolicy,
+    mmap_max_size: Option<u64>,
 }
 
 impl TokioFileReaderOpener {
     pub fn new(root: impl Into<PathBuf>) -> Self {
-        Self { root: root.into() }
+        Self {
+            root: root.into(),
+            symlink_policy: Default::default(),
+            mmap_max_size: None,
+        }
+    }
+
+    /// map the file not larger than the size instead of reading it, it is ignored
+    /// without the `mmap` feature.
+    pub fn mmap_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.mmap_max_size = max_size;
+        self
+    }
+
+    pub fn symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+
+    /// join the path to the root, only the normal components are joined,
+    /// so the absolute path or `..` can't escape the root.
+    fn full_path<T: AsRef<Path>>(&self, path: T) -> PathBuf {
+        let mut full_path = self.root.clone();
+        full_path.extend(path.as_ref().components().filter_map(|c| match c {
+            Component::Normal(c) => Some(c),
+            _ => None,
+        }));
+        full_path
     }
 }
 
@@ -163,9 +620,50 @@ impl FileReaderOpener for TokioFileReaderOpener {
 
     type Future = FileWithMetaFuture;
 
-    fn open<T: AsRef<Path>>(&self, path: T) -> Self::Future {
-        let mut full_path = self.root.clone();
-        full_path.extend(path.as_ref());
-        FileWithMetaFuture::new(full_path)
+    type MetaFuture = FileMetaFuture;
+
+    fn metadata<T: AsRef<Path>>(&self, path: T) -> Self::MetaFuture {
+        let full_path = self.full_path(path);
+        match self.symlink_policy {
+            SymlinkPolicy::Follow => FileMetaFuture::new(full_path),
+            policy => FileMetaFuture::with_symlink_policy(self.root.clone(), full_path, policy),
+        }
+    }
+
+    fn open(&self, meta: &FileMeta) -> Self::Future {
+        FileWithMetaFuture::new(meta.path.clone(), self.mmap_max_size)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_symlink_policy() {
+        let dir = std::env::temp_dir().join("blockless-symlink-policy");
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/in.txt"), b"in").unwrap();
+        std::fs::write(dir.join("out.txt"), b"out").unwrap();
+        let link = |target: &Path, name: &str| {
+            let _ = std::fs::remove_file(root.join(name));
+            std::os::unix::fs::symlink(target, root.join(name)).unwrap();
+        };
+        link(&dir.join("out.txt"), "out.txt");
+        link(&root.join("sub/in.txt"), "in.txt");
+        link(&root.join("sub"), "sub_link");
+
+        let is_found = |policy: SymlinkPolicy, path: &'static str| {
+            let opener = TokioFileReaderOpener::new(&root).symlink_policy(policy);
+            async move { opener.metadata(path).await.is_ok() }
+        };
+        assert!(is_found(SymlinkPolicy::Follow, "out.txt").await);
+        assert!(!is_found(SymlinkPolicy::WithinRoot, "out.txt").await);
+        assert!(is_found(SymlinkPolicy::WithinRoot, "in.txt").await);
+        assert!(is_found(SymlinkPolicy::WithinRoot, "sub_link/in.txt").await);
+        assert!(!is_found(SymlinkPolicy::Never, "in.txt").await);
+        assert!(!is_found(SymlinkPolicy::Never, "sub_link/in.txt").await);
+        assert!(is_found(SymlinkPolicy::Never, "sub/in.txt").await);
     }
 }
@@ -1,62 +1,2383 @@
 use std::{
-    io::{Error, ErrorKind, Result},
+    collections::HashMap,
+    io::{Error, ErrorKind, Read, Result, Seek},
+    path::{Path, PathBuf},
     pin::Pin,
     result::Result as StdResult,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
+    time::Duration,
 };
 
-use hyper::{service::Service, Request, Response, StatusCode};
+use hyper::{
+    body::Bytes,
+    header::{self, HeaderName, HeaderValue},
+    http::{request::Parts, response::Builder},
+    service::Service,
+    HeaderMap, Method, Request, Response, StatusCode, Uri,
+};
+use percent_encoding::utf8_percent_encode;
+use regex::Regex;
 
 use std::future::Future;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[cfg(feature = "tokio")]
+use crate::body::TimeoutBytesStream;
 
+#[cfg(feature = "bearer")]
+use crate::bearer::BearerAuth;
+#[cfg(feature = "tracing")]
+use crate::trace;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+use crate::watch::Watcher;
 use crate::{
-    body::Body,
-    request_resolve::{RequestResolve, Resolved},
+    access_log::AccessLog,
+    archive::{self, ArchiveKind, Archives, Source},
+    auth::{Authentication, BasicAuth},
+    authorize::{AuthorizeFuture, Authorizer, Decision},
+    body::{Body, ChunkedBytesStream, FileBytesStream, ThrottledBytesStream},
+    builder::FileServiceBuilder,
+    cache::FileCache,
+    compression::{self, CompressionConfig, Encoding, PRECOMPRESSED_ENCODINGS},
+    cors::Cors,
+    disposition,
+    embedded::EmbeddedAssets,
+    error::{InternalError, RequestMeta},
+    error_page::ErrorPage,
+    etag::{EtagCache, EtagStrategy},
+    fd_cache::FdCache,
+    file::{
+        self, BlockingFuture, FileMeta, FileMetaFuture, FileReaderOpener, FileWithMeta,
+        SymlinkPolicy, TokioFileReader, TokioFileReaderOpener, DEFAULT_CHUNK_SIZE,
+    },
+    glob::Glob,
+    hotlink::Hotlink,
+    ip::{self, IpNet},
+    limit::{self, ConcurrencyLimit, InFlightPermit},
+    listing::{self, DirEntries, ListingChunks, ListingFormat, ListingQuery, ListingTemplate},
+    memory::MemoryFiles,
+    metrics::Metrics,
+    mime::MimeTypes,
+    negotiate::{self, Variant},
+    rate_limit::RateLimit,
+    request_id,
+    request_resolve::{self, RequestResolve, ResolveOptions, Resolved},
+    resolver::{Resolver, RootResolver},
     resp_builder::ResponseBuilder,
+    rewrite::{RewriteRule, Rewritten},
+    runtime,
+    security::{Hsts, SecurityHeaders},
+    signed_url::SignedUrl,
+    stats::{PathStats, Stats},
+    throttle::TokenBucket,
+    vfs::{self, AsyncVfs, Vfs},
 };
 
+/// the file name is content-hashed, e.g. `app.3f2a9c1b.js`.
+const DEFAULT_FINGERPRINT_PATTERN: &str = r"\.[0-9a-f]{8,}\.";
+
+/// the `Cache-Control` of the content-hashed file.
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// The handler renders the error response, e.g. the problem details of the api.
+/// the kind of the error is `NotFound` for `404`, `PermissionDenied` for `403`,
+/// or the io error itself for `500`.
+pub type ErrorHandler = dyn Fn(&Error, &Request<()>) -> Response<Body> + Send + Sync;
+
+/// The hook of the request before it is resolved, e.g. to add the headers or the
+/// extensions, the response returned is sent instead of the file.
+pub type RequestHook = dyn Fn(&mut Parts) -> Option<Response<Body>> + Send + Sync;
+
+/// The hook of the response before it is sent, e.g. to add the headers or record the
+/// timing by the extension added by the request hook.
+pub type ResponseHook = dyn Fn(&Parts, &mut Response<Body>) + Send + Sync;
+
+/// The hook of the operational error, e.g. `EMFILE`, `EIO` or the permission denied of
+/// the file, whether it is responded as `500` or `403` or returned by the service.
+pub type ErrorHook = dyn Fn(&Error, &RequestMeta) + Send + Sync;
+
+/// the request without the body, e.g. passed to the handler.
+pub(crate) fn request_head<B>(request: &Request<B>) -> Request<()> {
+    let mut head = Request::new(());
+    *head.method_mut() = request.method().clone();
+    *head.uri_mut() = request.uri().clone();
+    *head.version_mut() = request.version();
+    *head.headers_mut() = request.headers().clone();
+    head
+}
+
+/// the error of the error status, the kind is the class of the failure.
+fn status_error(status: StatusCode) -> Error {
+    let kind = match status {
+        StatusCode::NOT_FOUND => ErrorKind::NotFound,
+        StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => ErrorKind::PermissionDenied,
+        StatusCode::BAD_REQUEST => ErrorKind::InvalidInput,
+        StatusCode::METHOD_NOT_ALLOWED => ErrorKind::Unsupported,
+        _ => ErrorKind::Other,
+    };
+    Error::new(kind, status.canonical_reason().unwrap_or("error"))
+}
+
+/// the path of the uri without the prefix, `None` if the path is not under the prefix.
+/// the prefix is matched by the segments, e.g. `/static` matches `/static/a.js`
+/// but not `/statics/a.js`.
+fn strip_prefix(uri: &Uri, prefix: &str) -> Option<Uri> {
+    let rest = uri.path().strip_prefix(prefix)?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/{}?{query}", rest.trim_start_matches('/')),
+        None => format!("/{}", rest.trim_start_matches('/')),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+/// The options of the file service.
 #[derive(Clone)]
-pub struct FileService {
+pub(crate) struct Config {
+    pub etag_strategy: EtagStrategy,
+    pub methods: Vec<Method>,
+    pub method_not_allowed_status: StatusCode,
+    pub mime_types: MimeTypes,
+    pub attachment_globs: Vec<Glob>,
+    pub attachment_by_query: bool,
+    pub compression: Option<CompressionConfig>,
+    pub precompressed: bool,
+    pub vary: Vec<String>,
+    pub cache_control: Vec<(Glob, String)>,
+    // the `Content-Security-Policy` of the paths matched the pattern, `None` sends no policy.
+    pub content_security_policy: Vec<(Glob, Option<String>)>,
+    pub fingerprint: Option<Regex>,
+    // the static headers of the paths matched the pattern, `None` matches all.
+    pub headers: Vec<(Option<Glob>, HeaderName, HeaderValue)>,
+    pub symlink_policy: SymlinkPolicy,
+    pub hide_hidden: bool,
+    pub deny_globs: Vec<(Glob, bool)>,
+    pub allow_globs: Vec<Glob>,
+    pub index_files: Vec<String>,
+    pub redirect_directory: bool,
+    pub strip_trailing_slash: bool,
+    pub autoindex: bool,
+    pub try_files: Vec<String>,
+    // serve the language variant of the not found file, e.g. `foo.html.en`.
+    pub language_variants: bool,
+    pub default_language: Option<String>,
+    // serve the variant of the not found file by the `Accept`, e.g. `data.json`.
+    pub media_type_variants: bool,
+    // resolve the not found path case-insensitively.
+    pub case_insensitive: bool,
+    // the size of the chunk read from the file.
+    pub chunk_size: usize,
+    // the chunks read ahead of the chunk sent.
+    pub read_ahead: usize,
+    // map the file not larger than the size.
+    pub mmap_max_size: Option<u64>,
+    // read the files by io_uring.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub io_uring: bool,
+    // the contents of the small files cached in memory.
+    pub file_cache: Option<Arc<FileCache>>,
+    // the idle open files reused by the requests.
+    pub fd_cache: Option<Arc<FdCache>>,
+    // the bytes per second of the response of the paths matched the pattern,
+    // `None` matches all.
+    pub throttles: Vec<(Option<Glob>, u64)>,
+    // the bytes per second of all the responses.
+    pub bandwidth: Option<Arc<Mutex<TokenBucket>>>,
+    // the archives served as the directories, e.g. `bundle.zip` as `bundle/`.
+    pub archive_kinds: Vec<ArchiveKind>,
+    pub archives: Arc<Archives>,
+    // the roots looked up in order after the root, e.g. the default theme.
+    pub overlay_roots: Vec<String>,
+    // the files in memory shadowing the files under the root.
+    pub memory_files: Option<MemoryFiles>,
+    // the assets served instead of the files under the root.
+    pub embedded: Option<Arc<EmbeddedAssets>>,
+    // the virtual filesystem served instead of the files under the root.
+    pub vfs: Option<Vfs>,
+    // the permits of the concurrent opens and streams.
+    pub open_files: Option<Arc<Semaphore>>,
+    // the permits of the requests in flight.
+    pub concurrency: Option<ConcurrencyLimit>,
+    pub cors: Option<Cors>,
+    pub security_headers: Option<SecurityHeaders>,
+    pub hsts: Option<Hsts>,
+    pub hotlink: Option<Hotlink>,
+    pub access_log: Option<AccessLog>,
+    pub metrics: Option<Metrics>,
+    pub(crate) stats: Option<Stats>,
+    pub request_id: Option<HeaderName>,
+    // the response is aborted if it is not sent in time.
+    pub response_timeout: Option<Duration>,
+    // the body is aborted if the client stops reading for the duration.
+    pub stall_timeout: Option<Duration>,
+    // invalidate the caches by the changes of the files.
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    pub watcher: Option<Arc<Watcher>>,
+    pub listing_template: Option<Arc<ListingTemplate>>,
+    pub listing_page_size: Option<usize>,
+    pub fallback_file: Option<String>,
+    pub error_pages: HashMap<StatusCode, ErrorPage>,
+    pub error_handler: Option<Arc<ErrorHandler>>,
+    pub on_request: Option<Arc<RequestHook>>,
+    pub on_response: Option<Arc<ResponseHook>>,
+    pub on_error: Option<Arc<ErrorHook>>,
+    pub authorizer: Option<Arc<Authorizer>>,
+    // the paths protected by the authentications, the first matched is checked.
+    pub auths: Vec<(Glob, Authentication)>,
+    // the client addresses denied, or only the allowed are served if any.
+    pub ip_deny: Vec<IpNet>,
+    pub ip_allow: Vec<IpNet>,
+    // the proxies of which the forwarded addresses are taken as the client.
+    pub trusted_proxies: Vec<IpNet>,
+    // the limits of the requests per client, the first matched is checked.
+    pub rate_limits: Vec<(Glob, RateLimit)>,
+    pub signed_urls: Vec<(Glob, SignedUrl)>,
+    // respond the unexpected error as `500` instead of returning it.
+    pub internal_error_response: bool,
+    // the url prefix stripped before resolving, e.g. `/static`.
+    pub prefix: Option<String>,
+    pub rewrite_rules: Vec<RewriteRule>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            etag_strategy: Default::default(),
+            methods: vec![Method::GET, Method::HEAD, Method::OPTIONS],
+            method_not_allowed_status: StatusCode::METHOD_NOT_ALLOWED,
+            mime_types: Default::default(),
+            attachment_globs: Vec::new(),
+            attachment_by_query: false,
+            compression: None,
+            precompressed: false,
+            vary: Vec::new(),
+            cache_control: Vec::new(),
+            content_security_policy: Vec::new(),
+            fingerprint: None,
+            headers: Vec::new(),
+            symlink_policy: Default::default(),
+            hide_hidden: false,
+            deny_globs: Vec::new(),
+            allow_globs: Vec::new(),
+            index_files: vec!["index.html".to_string()],
+            redirect_directory: true,
+            strip_trailing_slash: false,
+            autoindex: false,
+            try_files: Vec::new(),
+            language_variants: false,
+            default_language: None,
+            media_type_variants: false,
+            case_insensitive: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            read_ahead: 0,
+            mmap_max_size: None,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            io_uring: false,
+            file_cache: None,
+            fd_cache: None,
+            throttles: Vec::new(),
+            bandwidth: None,
+            archive_kinds: Vec::new(),
+            archives: Default::default(),
+            overlay_roots: Vec::new(),
+            memory_files: None,
+            embedded: None,
+            vfs: None,
+            open_files: None,
+            concurrency: None,
+            cors: None,
+            security_headers: None,
+            hsts: None,
+            hotlink: None,
+            access_log: None,
+            metrics: None,
+            stats: None,
+            request_id: None,
+            response_timeout: None,
+            stall_timeout: None,
+            #[cfg(all(feature = "watch", target_os = "linux"))]
+            watcher: None,
+            listing_template: None,
+            listing_page_size: None,
+            fallback_file: None,
+            error_pages: HashMap::new(),
+            error_handler: None,
+            on_request: None,
+            on_response: None,
+            on_error: None,
+            authorizer: None,
+            auths: Vec::new(),
+            ip_deny: Vec::new(),
+            ip_allow: Vec::new(),
+            trusted_proxies: Vec::new(),
+            rate_limits: Vec::new(),
+            signed_urls: Vec::new(),
+            internal_error_response: true,
+            prefix: None,
+            rewrite_rules: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// the file is sent as attachment when the path is matched or
+    /// the request query has `download=1`.
+    pub fn is_attachment<B>(&self, request: &Request<B>) -> bool {
+        if self.attachment_by_query {
+            let is_download = request
+                .uri()
+                .query()
+                .into_iter()
+                .flat_map(|q| q.split('&'))
+                .any(|kv| kv == "download=1" || kv == "download=true");
+            if is_download {
+                return true;
+            }
+        }
+        if self.attachment_globs.is_empty() {
+            return false;
+        }
+        let path = request_resolve::request_path(request);
+        self.attachment_globs.iter().any(|g| g.is_match(&path))
+    }
+
+    /// choose the encoding to compress the response, the range request is not compressed.
+    pub fn encoding<B>(
+        &self,
+        request: &Request<B>,
+        content_type: Option<&str>,
+        size: u64,
+    ) -> Option<Encoding> {
+        let config = self.compression.as_ref()?;
+        if request.headers().contains_key(header::RANGE) {
+            return None;
+        }
+        if !content_type
+            .map(|ct| config.is_eligible(ct, size))
+            .unwrap_or(false)
+        {
+            return None;
+        }
+        let accept_encoding = request
+            .headers()
+            .get(header::ACCEPT_ENCODING)?
+            .to_str()
+            .ok()?;
+        compression::negotiate(accept_encoding, compression::SUPPORTED_ENCODINGS)
+    }
+
+    /// the `Cache-Control` of the first matched pattern, the fingerprinted file
+    /// is immutable if no pattern is matched.
+    pub fn cache_control<B>(&self, request: &Request<B>) -> Option<String> {
+        if self.cache_control.is_empty() && self.fingerprint.is_none() {
+            return None;
+        }
+        let path = request_resolve::request_path(request);
+        let cache_control = self
+            .cache_control
+            .iter()
+            .find(|(g, _)| g.is_match(&path))
+            .map(|(_, v)| v.clone());
+        cache_control.or_else(|| {
+            let name = path.rsplit('/').next().unwrap_or(&path);
+            self.fingerprint
+                .as_ref()
+                .filter(|r| r.is_match(name))
+                .map(|_| IMMUTABLE_CACHE_CONTROL.to_string())
+        })
+    }
+
+    /// the `Content-Security-Policy` of the first matched pattern.
+    pub fn content_security_policy<B>(&self, request: &Request<B>) -> Option<String> {
+        if self.content_security_policy.is_empty() {
+            return None;
+        }
+        let path = request_resolve::request_path(request);
+        self.content_security_policy
+            .iter()
+            .find(|(g, _)| g.is_match(&path))
+            .and_then(|(_, policy)| policy.clone())
+    }
+
+    /// the static headers of the request path.
+    pub fn headers<B>(&self, request: &Request<B>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if self.headers.is_empty() {
+            return headers;
+        }
+        let path = request_resolve::request_path(request);
+        self.headers
+            .iter()
+            .filter(|(g, _, _)| g.as_ref().map(|g| g.is_match(&path)).unwrap_or(true))
+            .for_each(|(_, name, value)| {
+                headers.append(name.clone(), value.clone());
+            });
+        headers
+    }
+
+    /// the bytes per second of the response, the matched pattern is preferred
+    /// to the rate of all the paths.
+    pub fn throttle_rate<B>(&self, request: &Request<B>) -> Option<u64> {
+        if self.throttles.is_empty() {
+            return None;
+        }
+        let path = request_resolve::request_path(request);
+        let matched = |g: &Option<Glob>| g.as_ref().is_some_and(|g| g.is_match(&path));
+        self.throttles
+            .iter()
+            .find(|(g, _)| matched(g))
+            .or_else(|| self.throttles.iter().rev().find(|(g, _)| g.is_none()))
+            .map(|(_, rate)| *rate)
+    }
+
+    /// the content type of the file in memory, or by the extension of the path.
+    pub fn content_type(&self, meta: &FileMeta, path: &Path) -> Option<String> {
+        match meta.source {
+            Some(Source::Memory {
+                content_type: Some(ref content_type),
+                ..
+            }) => Some(content_type.clone()),
+            _ => self.mime_types.content_type(path),
+        }
+    }
+
+    /// the request headers the response varies by, the negotiated headers are added
+    /// automatically, and the user-added values are kept.
+    pub fn vary(&self) -> Vec<&str> {
+        let mut vary = Vec::new();
+        if self.compression.is_some() || self.precompressed {
+            vary.push(header::ACCEPT_ENCODING.as_str());
+        }
+        vary.extend(self.vary.iter().map(String::as_str));
+        vary
+    }
+
+    pub fn resolve_options(&self) -> ResolveOptions<'_> {
+        ResolveOptions {
+            methods: &self.methods,
+            hide_hidden: self.hide_hidden,
+            deny: &self.deny_globs,
+            allow: &self.allow_globs,
+            index_files: &self.index_files,
+            redirect_directory: self.redirect_directory,
+            strip_trailing_slash: self.strip_trailing_slash,
+            autoindex: self.autoindex,
+            try_files: &self.try_files,
+            signed_urls: &self.signed_urls,
+        }
+    }
+
+    /// the client address is denied by the rules, the unknown address is denied
+    /// if the allowed addresses are set.
+    pub fn is_ip_denied<B>(&self, request: &Request<B>) -> bool {
+        if self.ip_deny.is_empty() && self.ip_allow.is_empty() {
+            return false;
+        }
+        let addr = match ip::client_ip(request, &self.trusted_proxies) {
+            Some(addr) => addr,
+            None => return !self.ip_allow.is_empty(),
+        };
+        if self.ip_deny.iter().any(|net| net.contains(addr)) {
+            return true;
+        }
+        !self.ip_allow.is_empty() && !self.ip_allow.iter().any(|net| net.contains(addr))
+    }
+
+    /// the fallback file is served for the not found `GET` or `HEAD` request
+    /// without the file extension, e.g. the client-side route `/users/1`.
+    pub fn is_fallback<B>(&self, request: &Request<B>) -> bool {
+        if self.fallback_file.is_none() {
+            return false;
+        }
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return false;
+        }
+        let path = request_resolve::request_path(request);
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        !name.contains('.')
+    }
+
+    /// the value of `Allow` header.
+    pub fn allow_header(&self) -> String {
+        let methods: Vec<&str> = self.methods.iter().map(Method::as_str).collect();
+        methods.join(", ")
+    }
+}
+
+#[derive(Clone)]
+pub struct FileService<R = RootResolver> {
     local_root: String,
+    config: Arc<Config>,
+    etag_cache: Arc<EtagCache>,
+    resolver: R,
+    in_flight: InFlightPermit,
 }
 
 impl FileService {
+    /// the builder of the service serving the files under the root, e.g.
+    /// `FileService::builder("./dist").index_files(["index.html"]).build()?`.
+    pub fn builder(root: impl Into<String>) -> FileServiceBuilder {
+        FileServiceBuilder::new(root)
+    }
+
     pub fn new(root: impl Into<String>) -> Self {
         let local_root = root.into();
-        Self { local_root }
+        Self {
+            local_root,
+            config: Default::default(),
+            etag_cache: Default::default(),
+            resolver: RootResolver,
+            in_flight: Default::default(),
+        }
     }
 
-    async fn serv<B>(self, request: Request<B>) -> Result<Response<Body>> {
-        let resolved = RequestResolve::resolve(&self.local_root, &request).await?;
-        let resp = match resolved {
-            Resolved::IsDirectory => Response::builder()
-                .status(StatusCode::FORBIDDEN)
-                .body(Body::Empty),
-            Resolved::MethodNotMatched => Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .body(Body::Empty),
-            Resolved::NotFound => Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Body::Empty),
-            Resolved::PermissionDenied => Response::builder()
-                .status(StatusCode::FORBIDDEN)
-                .body(Body::Empty),
-            Resolved::Found(f) => ResponseBuilder::new().request(&request).build(f),
-        };
-        let resp = match resp {
+    /// serve the assets embedded in the binary instead of the files under a root,
+    /// the headers, tags and compression are the same as the files.
+    pub fn embedded(assets: EmbeddedAssets) -> Self {
+        let mut service = Self::new("");
+        Arc::make_mut(&mut service.config).embedded = Some(Arc::new(assets));
+        service
+    }
+
+    /// serve the files of the virtual filesystem instead of the files under a root,
+    /// e.g. the objects of the bucket, the files are read by `AsyncVfs::read_at`.
+    pub fn vfs(vfs: impl AsyncVfs) -> Self {
+        let mut service = Self::new("");
+        Arc::make_mut(&mut service.config).vfs = Some(Vfs::new(vfs));
+        service
+    }
+}
+
+impl<R: Resolver> FileService<R> {
+    /// look up the request by the resolver before the root, e.g. the alias table.
+    pub fn resolver<T: Resolver>(self, resolver: T) -> FileService<T> {
+        FileService {
+            local_root: self.local_root,
+            config: self.config,
+            etag_cache: self.etag_cache,
+            resolver,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// the content type of the resolved file by the mime types of the service.
+    pub fn content_type(&self, meta: &FileMeta) -> Option<String> {
+        self.config.content_type(meta, &meta.path)
+    }
+
+    /// find the file of the path relative to the root, the directory is not found.
+    async fn find_file(&self, path: &str) -> Option<FileMeta> {
+        let path = request_resolve::normalize_path(path)?;
+        if let Some(meta) = self
+            .config
+            .memory_files
+            .as_ref()
+            .and_then(|f| f.find(&path))
+        {
+            return Some(meta);
+        }
+        if let Some(ref assets) = self.config.embedded {
+            return assets.find(&path);
+        }
+        for vfs in self.root_vfses() {
+            if let Some(meta) = vfs.metadata(&path).await.ok().filter(|meta| !meta.is_dir) {
+                return Some(meta);
+            }
+        }
+        None
+    }
+
+    /// the virtual filesystem or the local files under the root, then the overlaid roots.
+    fn root_vfses(&self) -> Vec<Vfs> {
+        let policy = self.config.symlink_policy;
+        let root = match self.config.vfs {
+            Some(ref vfs) => vfs.clone(),
+            None => Vfs::local(&self.local_root, policy),
+        };
+        let overlays = self.config.overlay_roots.iter();
+        std::iter::once(root)
+            .chain(overlays.map(|root| Vfs::local(root, policy)))
+            .collect()
+    }
+
+    /// find the precompressed file of the accepted encoding, e.g. `app.js.br`.
+    /// the range request is served from the file itself, as the compression.
+    async fn find_precompressed<B>(
+        &self,
+        request: &Request<B>,
+        meta: &FileMeta,
+    ) -> Option<(Encoding, FileMeta)> {
+        if !self.config.precompressed || request.headers().contains_key(header::RANGE) {
+            return None;
+        }
+        let accept_encoding = request.headers().get(header::ACCEPT_ENCODING)?;
+        let accept_encoding = accept_encoding.to_str().ok()?;
+        let served_path = self.served_path(meta);
+        let options = self.config.resolve_options();
+        for encoding in compression::accepted(accept_encoding, PRECOMPRESSED_ENCODINGS) {
+            // the sidecar is checked as the file requested by its path.
+            let served_path = served_path
+                .as_ref()
+                .map(|p| format!("{p}.{}", encoding.extension()));
+            if served_path.is_some_and(|p| options.reject(&p).is_some()) {
+                continue;
+            }
+            let mut path = meta.path.clone().into_os_string();
+            path.push(".");
+            path.push(encoding.extension());
+            let found = match (&self.config.embedded, vfs::vfs_path(meta)) {
+                (Some(assets), _) => assets.find(&path.to_string_lossy()),
+                (None, Some((vfs, path))) => {
+                    let path = format!("{path}.{}", encoding.extension());
+                    vfs.metadata(&path).await.ok()
+                }
+                // the precompressed member of the archive is not looked up.
+                (None, None) if meta.source.is_some() => None,
+                (None, None) => match self.root_of(meta) {
+                    Some(root) => {
+                        let (root, policy) = (root.into(), self.config.symlink_policy);
+                        FileMetaFuture::with_symlink_policy(root, path.into(), policy)
+                            .await
+                            .ok()
+                    }
+                    None => None,
+                },
+            };
+            match found {
+                Some(meta) if !meta.is_dir => return Some((encoding, meta)),
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    /// the extensions of the variants of the path in order, e.g. `en` of `foo.html.en`.
+    /// the variants rejected by the options are skipped.
+    async fn find_variants(&self, path: &str, is_variant: impl Fn(&str) -> bool) -> Vec<String> {
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((dir, name)) => (dir, name),
+            None => ("", path),
+        };
+        if name.is_empty() || self.config.embedded.is_some() {
+            return Vec::new();
+        }
+        let mut entries = Vec::new();
+        for vfs in self.root_vfses() {
+            entries.extend(vfs.read_dir(dir).await.unwrap_or_default());
+        }
+        let options = self.config.resolve_options();
+        let mut extensions: Vec<String> = entries
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .filter_map(|entry| {
+                let extension = entry.name.strip_prefix(name)?.strip_prefix('.')?;
+                let is_rejected = options.reject(&format!("{path}.{extension}")).is_some();
+                (is_variant(extension) && !is_rejected).then(|| extension.to_string())
+            })
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        extensions
+    }
+
+    /// negotiate the language variant of the not found file by the `Accept-Language`,
+    /// return `None` if there is no variant, or `Err` if no variant is acceptable.
+    async fn negotiate_language<B>(
+        &self,
+        request: &Request<B>,
+    ) -> Option<StdResult<(FileMeta, Variant), StatusCode>> {
+        if !self.config.language_variants {
+            return None;
+        }
+        let path = request_resolve::normalize_path(&request_resolve::request_path(request))?;
+        let languages = self.find_variants(&path, negotiate::is_language_tag).await;
+        if languages.is_empty() {
+            return None;
+        }
+        let accept_language = request
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok());
+        let default = self.config.default_language.as_deref();
+        let language = match negotiate::negotiate_language(accept_language, &languages, default) {
+            Some(language) => language,
+            None => return Some(Err(StatusCode::NOT_ACCEPTABLE)),
+        };
+        let meta = self.find_file(&format!("{path}.{language}")).await?;
+        let variant = Variant {
+            language: Some(language.to_string()),
+            vary: Some("accept-language"),
+        };
+        Some(Ok((meta, variant)))
+    }
+
+    /// negotiate the variant of the not found file by the `Accept`, e.g. `data.json`
+    /// for `/data`, return `None` if there is no variant, or `Err` if no variant is acceptable.
+    async fn negotiate_media_type<B>(
+        &self,
+        request: &Request<B>,
+    ) -> Option<StdResult<(FileMeta, Variant), StatusCode>> {
+        if !self.config.media_type_variants {
+            return None;
+        }
+        let path = request_resolve::normalize_path(&request_resolve::request_path(request))?;
+        let content_type = |extension: &str| {
+            let name = format!("variant.{extension}");
+            self.config.mime_types.content_type(Path::new(&name))
+        };
+        let extensions = self
+            .find_variants(&path, |e| !e.contains('.') && content_type(e).is_some())
+            .await;
+        if extensions.is_empty() {
+            return None;
+        }
+        let content_types: Vec<String> =
+            extensions.iter().filter_map(|e| content_type(e)).collect();
+        let accept = request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok());
+        let index = match negotiate::negotiate_media_type(accept, &content_types) {
+            Some(index) => index,
+            None => return Some(Err(StatusCode::NOT_ACCEPTABLE)),
+        };
+        let meta = self
+            .find_file(&format!("{path}.{}", extensions[index]))
+            .await?;
+        let variant = Variant {
+            language: None,
+            vary: Some("accept"),
+        };
+        Some(Ok((meta, variant)))
+    }
+
+    /// the uri of the path matched case-insensitively, `None` if the path is not matched
+    /// or is matched as it is.
+    async fn case_insensitive_uri<B>(&self, request: &Request<B>) -> Option<Uri> {
+        let path = request_resolve::normalize_path(&request_resolve::request_path(request))?;
+        let matched = file::find_case_insensitive(self.local_root.clone().into(), path.clone())
+            .await
+            .ok()
+            .flatten()
+            .filter(|matched| *matched != path)?;
+        let encoded: Vec<String> = matched
+            .split('/')
+            .map(|s| utf8_percent_encode(s, listing::SEGMENT).to_string())
+            .collect();
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("/{}?{query}", encoded.join("/")),
+            None => format!("/{}", encoded.join("/")),
+        };
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = Some(path_and_query.parse().ok()?);
+        Uri::from_parts(parts).ok()
+    }
+
+    /// serve the variant of the not found file, or the fallback file.
+    async fn serv_not_found<B>(&self, request: &Request<B>) -> Result<Response<Body>> {
+        let error = |status| self.error_response(request, status, Response::builder());
+        let negotiated = match self.negotiate_language(request).await {
+            Some(negotiated) => Some(negotiated),
+            None => self.negotiate_media_type(request).await,
+        };
+        match negotiated {
+            Some(Ok((meta, variant))) => return self.serv_file(request, meta, variant).await,
+            Some(Err(status)) => return error(status).await,
+            None => {}
+        }
+        if self.config.is_fallback(request) {
+            let fallback = self.config.fallback_file.as_deref().unwrap_or_default();
+            if let Some(meta) = self.find_file(fallback).await {
+                return self.serv_file(request, meta, Variant::default()).await;
+            }
+        }
+        error(StatusCode::NOT_FOUND).await
+    }
+
+    async fn serv_file<B>(
+        &self,
+        request: &Request<B>,
+        meta: FileMeta,
+        variant: Variant,
+    ) -> Result<Response<Body>> {
+        if let Some(resp) = self.guarded(request, &meta).await? {
+            return Ok(resp);
+        }
+        // the representation headers are from the original file,
+        // the language variant has the content type of the file without the language.
+        let content_type = match variant.language {
+            Some(_) => self
+                .config
+                .content_type(&meta, &meta.path.with_extension("")),
+            None => self.config.content_type(&meta, &meta.path),
+        };
+        let content_disposition = self
+            .config
+            .is_attachment(request)
+            .then(|| meta.path.file_name())
+            .flatten()
+            .map(|name| disposition::attachment(&name.to_string_lossy()));
+        let (meta, precompressed, encoding) = match self.find_precompressed(request, &meta).await {
+            Some((encoding, meta)) => (meta, Some(encoding), None),
+            None => {
+                let encoding = self
+                    .config
+                    .encoding(request, content_type.as_deref(), meta.size);
+                (meta, None, encoding)
+            }
+        };
+        #[cfg(all(feature = "watch", target_os = "linux"))]
+        self.invalidate_changed();
+        let etag = self
+            .config
+            .etag_strategy
+            .entity_tag(&meta, &self.etag_cache)
+            .await?;
+        let mut builder = ResponseBuilder::new();
+        builder
+            .request(request)
+            .etag(etag)
+            .content_type(content_type)
+            .content_disposition(content_disposition)
+            .content_language(variant.language)
+            .precompressed(precompressed)
+            .encoding(encoding)
+            .chunk_size(self.config.chunk_size)
+            .read_ahead(self.config.read_ahead)
+            .compression_level(
+                self.config
+                    .compression
+                    .as_ref()
+                    .and_then(|c| c.compression_level()),
+            );
+        for name in self.config.vary().into_iter().chain(variant.vary) {
+            builder.vary(name);
+        }
+        builder
+            .cache_control(self.config.cache_control(request))
+            .content_security_policy(self.config.content_security_policy(request))
+            .headers(self.config.headers(request));
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        builder.io_uring(self.config.io_uring);
+        let resp = match builder.build_without_file(&meta) {
+            Some(resp) => resp,
+            // open the file only when the content should be sent.
+            None => match self.open_traced_limited(&meta).await {
+                Ok((file, permit)) => {
+                    let is_open = file.file.is_some();
+                    let mut resp = builder.build(file);
+                    if let (Ok(resp), true) = (resp.as_mut(), is_open) {
+                        if let Some(metrics) = self.config.metrics.as_ref() {
+                            metrics.observe_open_file(resp);
+                        }
+                        if let Some(permit) = permit {
+                            let body = std::mem::replace(resp.body_mut(), Body::Empty);
+                            *resp.body_mut() = Body::Limited(Box::new(body), permit);
+                        }
+                    }
+                    resp
+                }
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                    self.observe_error(&e, request);
+                    return self
+                        .error_response(request, StatusCode::FORBIDDEN, Response::builder())
+                        .await;
+                }
+                Err(e) => return Err(e),
+            },
+        };
+        resp.map_err(Error::other)
+    }
+
+    /// remove the cached contents and tags of the changed files.
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    fn invalidate_changed(&self) {
+        let watcher = match self.config.watcher {
+            Some(ref watcher) => watcher,
+            None => return,
+        };
+        for path in watcher.changed() {
+            if let Some(ref cache) = self.config.file_cache {
+                cache.invalidate(&path);
+            }
+            self.etag_cache.invalidate(&path);
+        }
+    }
+
+    /// open the file with the permit of the open files, which is kept only if the
+    /// file is still open, e.g. not the content read into memory.
+    async fn open_traced_limited(
+        &self,
+        meta: &FileMeta,
+    ) -> Result<(FileWithMeta, Option<OwnedSemaphorePermit>)> {
+        let permit = match meta.source {
+            Some(Source::Memory { .. }) => None,
+            _ => self.open_files_permit().await?,
+        };
+        let file = self.open_traced(meta).await?;
+        let permit = permit.filter(|_| file.file.is_some());
+        Ok((file, permit))
+    }
+
+    async fn open_traced(&self, meta: &FileMeta) -> Result<FileWithMeta> {
+        let file = self.open_content(meta).await;
+        #[cfg(feature = "tracing")]
+        trace::opened(meta, &file);
+        file
+    }
+
+    /// open the file to send the content, the cached content is used without opening.
+    async fn open_content(&self, meta: &FileMeta) -> Result<FileWithMeta> {
+        if let Some(Source::Memory { ref content, .. }) = meta.source {
+            return Ok(FileWithMeta {
+                meta: meta.clone(),
+                file: None,
+                content: Some(content.clone()),
+                fd_cache: None,
+                vfs: None,
+            });
+        }
+        let cache = self
+            .config
+            .file_cache
+            .as_ref()
+            .filter(|c| c.is_cacheable(meta));
+        if let Some(cache) = cache {
+            let content = cache.get(meta);
+            if let Some(ref metrics) = self.config.metrics {
+                metrics.cache_lookup(content.is_some());
+            }
+            let content = match content {
+                Some(content) => Some(content),
+                None => {
+                    let content = match vfs::vfs_path(meta) {
+                        Some((vfs, _)) => vfs.read_all(meta, self.config.chunk_size).await?,
+                        None => {
+                            let file = meta.clone();
+                            let content = BlockingFuture::spawn(move || {
+                                let mut content = Vec::new();
+                                file::open_std(&file)?.read_to_end(&mut content)?;
+                                Ok(content)
+                            });
+                            Bytes::from(content.await?)
+                        }
+                    };
+                    // the file is changed after the resolving, read it as usual.
+                    (content.len() as u64 == meta.size).then(|| {
+                        cache.insert(meta, content.clone());
+                        content
+                    })
+                }
+            };
+            if content.is_some() {
+                return Ok(FileWithMeta {
+                    meta: meta.clone(),
+                    file: None,
+                    content,
+                    fd_cache: None,
+                    vfs: None,
+                });
+            }
+        }
+        if let Some((vfs, _)) = vfs::vfs_path(meta) {
+            return Ok(FileWithMeta {
+                meta: meta.clone(),
+                file: None,
+                content: None,
+                fd_cache: None,
+                vfs: Some(vfs.open(meta).await?),
+            });
+        }
+        if let Some(Source::Archive {
+            ref archive,
+            ref member,
+        }) = meta.source
+        {
+            let (archive, member) = (archive.clone(), member.clone());
+            let file = BlockingFuture::spawn(move || archive::open(&archive, &member)).await?;
+            return Ok(FileWithMeta {
+                meta: meta.clone(),
+                file: Some(file),
+                content: None,
+                fd_cache: None,
+                vfs: None,
+            });
+        }
+        let fd_cache = self.config.fd_cache.as_ref();
+        let idle = fd_cache.and_then(|cache| cache.take(meta));
+        // the idle file is rewound, the range reader seeks by itself.
+        if let Some(file) = idle.filter(|mut f| f.rewind().is_ok()) {
+            return Ok(FileWithMeta {
+                meta: meta.clone(),
+                file: Some(file),
+                content: None,
+                fd_cache: fd_cache.cloned(),
+                vfs: None,
+            });
+        }
+        let mut file = TokioFileReaderOpener::new(&self.local_root)
+            .mmap_max_size(self.config.mmap_max_size)
+            .open(meta)
+            .await?;
+        if file.content.is_none() {
+            file.fd_cache = fd_cache.cloned();
+        }
+        Ok(file)
+    }
+
+    /// set the strategy to generate the `ETag`, the default is `EtagStrategy::Weak`.
+    pub fn etag_strategy(mut self, strategy: EtagStrategy) -> Self {
+        Arc::make_mut(&mut self.config).etag_strategy = strategy;
+        self
+    }
+
+    /// set the allowed methods, the default is `GET`, `HEAD` and `OPTIONS`.
+    /// the other methods are not supported by the file service.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        let methods = methods
+            .into_iter()
+            .filter(|m| matches!(*m, Method::GET | Method::HEAD | Method::OPTIONS))
+            .collect();
+        Arc::make_mut(&mut self.config).methods = methods;
+        self
+    }
+
+    /// set the status of the response for the not allowed methods, the default is
+    /// `405 Method Not Allowed` with the `Allow` header.
+    pub fn method_not_allowed_status(mut self, status: StatusCode) -> Self {
+        Arc::make_mut(&mut self.config).method_not_allowed_status = status;
+        self
+    }
+
+    /// register the content type of the extension, e.g. `mime_type("mcap", "application/x-mcap")`,
+    /// which overrides the built-in table.
+    pub fn mime_type(mut self, ext: &str, content_type: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .mime_types
+            .insert(ext, content_type);
+        self
+    }
+
+    /// set the content type for the file with unknown extension.
+    pub fn default_mime_type(mut self, content_type: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .mime_types
+            .set_default(content_type);
+        self
+    }
+
+    /// append the charset parameter to the `text/*` and `application/javascript` content types,
+    /// e.g. `charset("utf-8")`.
+    pub fn charset(mut self, charset: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .mime_types
+            .set_charset(charset);
+        self
+    }
+
+    /// send the files matched the glob pattern as attachment, e.g. `attachment("downloads/**")`.
+    pub fn attachment(mut self, pattern: &str) -> Self {
+        Arc::make_mut(&mut self.config)
+            .attachment_globs
+            .push(Glob::new(pattern));
+        self
+    }
+
+    /// send the file as attachment when the request query has `download=1`.
+    pub fn attachment_by_query(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).attachment_by_query = enable;
+        self
+    }
+
+    /// compress the response when the client accepted, the default is disabled.
+    pub fn compression(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).compression = enable.then(CompressionConfig::default);
+        self
+    }
+
+    /// compress the response with the policy, e.g. the minimum size and the mime types.
+    pub fn compression_config(mut self, config: CompressionConfig) -> Self {
+        Arc::make_mut(&mut self.config).compression = Some(config);
+        self
+    }
+
+    /// serve the precompressed file next to the file when the client accepted the encoding,
+    /// e.g. `app.js.br` or `app.js.gz` for `app.js`. the default is disabled.
+    pub fn precompressed(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).precompressed = enable;
+        self
+    }
+
+    /// set the `Cache-Control` of the paths matched the glob pattern,
+    /// e.g. `*.html` to `no-cache` or `/assets/**` to `max-age=31536000, immutable`.
+    /// the first matched pattern is applied.
+    pub fn cache_control(mut self, pattern: &str, value: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .cache_control
+            .push((Glob::new(pattern), value.into()));
+        self
+    }
+
+    /// send the content-hashed file, e.g. `app.3f2a9c1b.js`, with
+    /// `Cache-Control: public, max-age=31536000, immutable`.
+    /// the default pattern `\.[0-9a-f]{8,}\.` is used to match the file name.
+    pub fn fingerprinted_assets(mut self, enable: bool) -> Self {
+        if enable {
+            return self.fingerprint_pattern(DEFAULT_FINGERPRINT_PATTERN);
+        }
+        Arc::make_mut(&mut self.config).fingerprint = None;
+        self
+    }
+
+    /// the regex pattern to match the content-hashed file name.
+    ///
+    /// # Panics
+    ///
+    /// panic if the pattern is invalid regex.
+    pub fn fingerprint_pattern(mut self, pattern: &str) -> Self {
+        let regex = Regex::new(pattern).expect("invalid fingerprint pattern");
+        Arc::make_mut(&mut self.config).fingerprint = Some(regex);
+        self
+    }
+
+    /// the `Content-Security-Policy` of the files matched the glob pattern, the first matched
+    /// pattern is applied, e.g. `None` for `/assets/*` before the strict policy of `*.html`.
+    /// the `{nonce}` of the policy is substituted by the nonce of every response, which is
+    /// in the [`CspNonce`](crate::CspNonce) extension of the response.
+    pub fn content_security_policy(mut self, pattern: &str, policy: Option<&str>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .content_security_policy
+            .push((Glob::new(pattern), policy.map(String::from)));
+        self
+    }
+
+    /// add the static header to every response, e.g. `X-Content-Type-Options: nosniff`.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        Arc::make_mut(&mut self.config)
+            .headers
+            .push((None, name, value));
+        self
+    }
+
+    /// add the static header to the response of the paths matched the glob pattern,
+    /// e.g. `Access-Control-Allow-Origin: *` for `*.woff2`.
+    pub fn header_for(mut self, pattern: &str, name: HeaderName, value: HeaderValue) -> Self {
+        Arc::make_mut(&mut self.config)
+            .headers
+            .push((Some(Glob::new(pattern)), name, value));
+        self
+    }
+
+    /// add the request header to the `Vary` response header, e.g. `Cookie`,
+    /// the `Accept-Encoding` is added automatically when the compression is enabled.
+    pub fn vary(mut self, name: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).vary.push(name.into());
+        self
+    }
+
+    /// the policy of the symbolic link under the root, the default is to follow freely.
+    /// the path violated the policy is not found.
+    pub fn symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        Arc::make_mut(&mut self.config).symlink_policy = policy;
+        self
+    }
+
+    /// the path has any component starts with `.`, e.g. `.git` or `.env`, is not found.
+    pub fn hide_hidden(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).hide_hidden = enable;
+        self
+    }
+
+    /// the paths matched the glob pattern are not found, e.g. `**/*.key`.
+    pub fn deny(mut self, pattern: &str) -> Self {
+        Arc::make_mut(&mut self.config)
+            .deny_globs
+            .push((Glob::new(pattern), false));
+        self
+    }
+
+    /// the paths matched the glob pattern are forbidden, e.g. `private/**`.
+    pub fn forbid(mut self, pattern: &str) -> Self {
+        Arc::make_mut(&mut self.config)
+            .deny_globs
+            .push((Glob::new(pattern), true));
+        self
+    }
+
+    /// only the paths matched the allowed patterns are served, the others are not found.
+    /// the denied patterns are checked first.
+    pub fn allow(mut self, pattern: &str) -> Self {
+        Arc::make_mut(&mut self.config)
+            .allow_globs
+            .push(Glob::new(pattern));
+        self
+    }
+
+    /// the index files served for the directory in order, the default is `index.html`.
+    /// the directory without the index file is forbidden, the empty list disables it.
+    pub fn index_files<I, S>(mut self, index_files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Arc::make_mut(&mut self.config).index_files =
+            index_files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// the candidates tried in order if the request path is not found, like the `try_files`
+    /// of nginx, e.g. `["$uri.html", "$uri/index.html", "/fallback.html"]`.
+    /// `$uri` is replaced by the request path, the directory candidates are skipped.
+    pub fn try_files<I, S>(mut self, try_files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Arc::make_mut(&mut self.config).try_files = try_files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// redirect the directory without the trailing slash to the slash-terminated url
+    /// with `301`, so the relative links of the index file are resolved correctly.
+    /// the default is enabled.
+    pub fn redirect_directory(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).redirect_directory = enable;
+        self
+    }
+
+    /// redirect the file url with the trailing slash to the url without it with `301`,
+    /// e.g. `/file.txt/` to `/file.txt`. the default is disabled.
+    pub fn strip_trailing_slash(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).strip_trailing_slash = enable;
+        self
+    }
+
+    /// list the directory without the index file as the html page, the default is disabled.
+    /// the entries denied or hidden are not listed.
+    pub fn autoindex(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).autoindex = enable;
+        self
+    }
+
+    /// render the html directory listing with the template instead of the built-in page,
+    /// the names should be escaped by `escape_html` and linked by `DirEntry::href`.
+    pub fn listing_template<F>(mut self, template: F) -> Self
+    where
+        F: Fn(&DirEntries) -> String + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).listing_template = Some(Arc::new(template));
+        self
+    }
+
+    /// paginate the directory listing with the page size, the `per_page` query is
+    /// limited by it. the default is not paginated.
+    pub fn listing_page_size(mut self, page_size: usize) -> Self {
+        Arc::make_mut(&mut self.config).listing_page_size = Some(page_size.max(1));
+        self
+    }
+
+    /// serve the files in memory before the files under the root, e.g. the generated
+    /// `env.js`, the files can be replaced by the clone of `MemoryFiles` at runtime.
+    pub fn memory_files(mut self, files: MemoryFiles) -> Self {
+        Arc::make_mut(&mut self.config).memory_files = Some(files);
+        self
+    }
+
+    /// look up the path in the root after the root and the roots overlaid before, the first
+    /// root having the path is served, e.g. the site overrides the files of the default theme.
+    pub fn overlay_root(mut self, root: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .overlay_roots
+            .push(root.into());
+        self
+    }
+
+    /// serve the file with `200` for the not found request without the file extension,
+    /// e.g. `index.html` of the single page application with client-side routing.
+    /// the path is relative to the root.
+    pub fn fallback_file(mut self, path: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).fallback_file = Some(path.into());
+        self
+    }
+
+    /// serve the language variant for the not found file by the `Accept-Language` header,
+    /// e.g. `foo.html.de` for `/foo.html`, with the `Content-Language`. the default is disabled.
+    pub fn language_variants(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).language_variants = enable;
+        self
+    }
+
+    /// the language variant served if the client has no acceptable language, e.g. `en`,
+    /// otherwise `406 Not Acceptable` is responded.
+    pub fn default_language(mut self, language: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).default_language = Some(language.into());
+        self
+    }
+
+    /// serve the variant for the not found file by the `Accept` header, e.g. `data.json`,
+    /// `data.html` or `data.xml` for `/data`, the variant is chosen by the content type of
+    /// the extension. `406 Not Acceptable` is responded if no variant is acceptable.
+    /// the default is disabled.
+    pub fn media_type_variants(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).media_type_variants = enable;
+        self
+    }
+
+    /// resolve the not found path case-insensitively, e.g. `/Docs/README.md` for
+    /// `docs/readme.md`, the path is not found if the match is ambiguous.
+    /// it scans the directories, the default is disabled.
+    pub fn case_insensitive(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).case_insensitive = enable;
+        self
+    }
+
+    /// the size of the chunk read from the file, the default is 64 KiB.
+    /// the larger chunk has fewer reads for the large files on the fast network.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        Arc::make_mut(&mut self.config).chunk_size = chunk_size;
+        self
+    }
+
+    /// read the next chunks of the file while the chunk is sent, so the latency of
+    /// the disk is hidden behind the network. the default is `0`, the chunk is read
+    /// when the body is polled.
+    pub fn read_ahead(mut self, depth: usize) -> Self {
+        Arc::make_mut(&mut self.config).read_ahead = depth;
+        self
+    }
+
+    /// serve the file not larger than the size by the memory map, the chunks are
+    /// sliced from the map without copying. the files writable by the group or the
+    /// others are read instead. the file truncated in place while it is mapped raises
+    /// `SIGBUS`, so it is for the files replaced by the rename only.
+    #[cfg(feature = "mmap")]
+    pub fn mmap(mut self, max_size: u64) -> Self {
+        Arc::make_mut(&mut self.config).mmap_max_size = Some(max_size);
+        self
+    }
+
+    /// read the files by io_uring on linux instead of the blocking pool, the chunks are
+    /// read into the buffers sent without copying, and the chunks read ahead are queued
+    /// to the ring together. the files are read by the blocking pool if the ring can't
+    /// be set up, e.g. io_uring is disabled by the kernel or the seccomp.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub fn io_uring(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).io_uring = enable;
+        self
+    }
+
+    /// cache the contents of the files not larger than `max_entry_size` in memory,
+    /// the least recently used are evicted if the total size is larger than `max_size`.
+    /// the cached file is validated by the mtime and size.
+    pub fn cache(mut self, max_entry_size: u64, max_size: u64) -> Self {
+        let cache = FileCache::new(max_entry_size, max_size);
+        Arc::make_mut(&mut self.config).file_cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// watch the files under the root by inotify, the cached contents and the strong
+    /// `ETag`s of the changed files are invalidated immediately instead of validated
+    /// by the mtime only.
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    pub fn watch(mut self) -> Result<Self> {
+        let watcher = Watcher::new(&self.local_root)?;
+        Arc::make_mut(&mut self.config).watcher = Some(Arc::new(watcher));
+        Ok(self)
+    }
+
+    /// keep at most `max_files` open files idle for reuse, which avoids opening the hot
+    /// files for every request. the file idle longer than `idle_timeout` is closed,
+    /// and the file replaced or modified since opened is not reused.
+    pub fn fd_cache(mut self, max_files: usize, idle_timeout: Duration) -> Self {
+        let cache = FdCache::new(max_files, idle_timeout);
+        Arc::make_mut(&mut self.config).fd_cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// serve the `.zip` archive as the directory when the path is not found, e.g.
+    /// `/bundle/app.js` is read from `bundle.zip`. the stored members are read from
+    /// the archive as is, and the deflated members are inflated while streaming.
+    pub fn zip_archives(mut self, enable: bool) -> Self {
+        let config = Arc::make_mut(&mut self.config);
+        config.archive_kinds.retain(|k| *k != ArchiveKind::Zip);
+        if enable {
+            config.archive_kinds.push(ArchiveKind::Zip);
+        }
+        self
+    }
+
+    /// serve the `.tar`, `.tar.gz` and `.tgz` archives as the directories like the
+    /// `zip_archives`. the offsets of the members are indexed on the first access, the
+    /// member of the tar is read as is, and the `.tar.gz` is inflated from the beginning
+    /// to the member, which is slow for the large archive.
+    pub fn tar_archives(mut self, enable: bool) -> Self {
+        let config = Arc::make_mut(&mut self.config);
+        config
+            .archive_kinds
+            .retain(|k| !matches!(k, ArchiveKind::Tar | ArchiveKind::TarGz));
+        if enable {
+            config
+                .archive_kinds
+                .extend([ArchiveKind::Tar, ArchiveKind::TarGz]);
+        }
+        self
+    }
+
+    /// cap the bytes per second of every response, e.g. `2 * 1024 * 1024` for 2 MB/s,
+    /// the chunks are delayed so the uplink is not saturated by a single download.
+    ///
+    /// # Panics
+    ///
+    /// panic if the rate is zero.
+    pub fn throttle(mut self, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "the throttle rate must be positive");
+        Arc::make_mut(&mut self.config)
+            .throttles
+            .push((None, bytes_per_sec));
+        self
+    }
+
+    /// cap the bytes per second of the response of the paths matched the glob pattern,
+    /// e.g. `*.iso`, the first matched pattern is used.
+    ///
+    /// # Panics
+    ///
+    /// panic if the rate is zero.
+    pub fn throttle_for(mut self, pattern: &str, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "the throttle rate must be positive");
+        Arc::make_mut(&mut self.config)
+            .throttles
+            .push((Some(Glob::new(pattern)), bytes_per_sec));
+        self
+    }
+
+    /// cap the bytes per second of all the in-flight responses of the service and
+    /// its clones, so the server stays under the egress cap. it works with the
+    /// throttle of the response, the slower one is applied.
+    ///
+    /// # Panics
+    ///
+    /// panic if the rate is zero.
+    pub fn bandwidth_limit(mut self, bytes_per_sec: u64) -> Self {
+        let bucket = TokenBucket::new(bytes_per_sec);
+        Arc::make_mut(&mut self.config).bandwidth = Some(Arc::new(Mutex::new(bucket)));
+        self
+    }
+
+    /// open at most `max` files to send the contents at once, the permit is acquired
+    /// before the file is opened and held until the body is sent, the request waits for
+    /// the permit instead of exhausting the fd limit. the responses without the file,
+    /// e.g. `304 Not Modified`, `HEAD` or `404 Not Found`, don't take the permit.
+    pub fn max_open_files(mut self, max: usize) -> Self {
+        Arc::make_mut(&mut self.config).open_files = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// limit the requests in flight, the permit is held until the body is sent. the
+    /// requests beyond the limit wait in the queue by `poll_ready` or are shed as `503`.
+    pub fn concurrency_limit(mut self, limit: ConcurrencyLimit) -> Self {
+        Arc::make_mut(&mut self.config).concurrency = Some(limit);
+        self
+    }
+
+    /// answer the CORS preflight requests and add the `Access-Control-*` headers to the
+    /// responses of the allowed origins, e.g. the fonts and the wasm of the other origin.
+    pub fn cors(mut self, cors: Cors) -> Self {
+        Arc::make_mut(&mut self.config).cors = Some(cors);
+        self
+    }
+
+    /// add the hardened headers to every response, e.g. `X-Content-Type-Options: nosniff`,
+    /// the headers of the response set by the other options are kept.
+    pub fn security_headers(mut self, headers: SecurityHeaders) -> Self {
+        Arc::make_mut(&mut self.config).security_headers = Some(headers);
+        self
+    }
+
+    /// count the responses, the bytes sent, the cache lookups and the open files, the
+    /// metrics are served at the path of the metrics if it is set, or rendered by
+    /// [`Metrics::render`] of the clone.
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        Arc::make_mut(&mut self.config).metrics = Some(metrics);
+        self
+    }
+
+    /// count the hits and the bytes sent of the resolved paths in memory, the paths
+    /// beyond `max_paths` are not counted.
+    pub fn track_stats(mut self, max_paths: usize) -> Self {
+        Arc::make_mut(&mut self.config).stats = Some(Stats::new(max_paths));
+        self
+    }
+
+    /// the snapshot of the hits and the bytes sent of the resolved paths, the most hit
+    /// first, empty unless the stats are tracked.
+    pub fn stats(&self) -> Vec<(PathBuf, PathStats)> {
+        self.config
+            .stats
+            .as_ref()
+            .map(Stats::snapshot)
+            .unwrap_or_default()
+    }
+
+    /// accept the request id of the header from the proxy or generate it, e.g. `X-Request-Id`,
+    /// the id is echoed on the response and included in the access log and the span.
+    pub fn request_id(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.config).request_id = Some(name);
+        self
+    }
+
+    /// log every response to the access log, e.g. in the Combined Log Format.
+    pub fn access_log(mut self, log: AccessLog) -> Self {
+        Arc::make_mut(&mut self.config).access_log = Some(log);
+        self
+    }
+
+    /// add `Strict-Transport-Security` to the responses of the requests over TLS.
+    pub fn hsts(mut self, hsts: Hsts) -> Self {
+        Arc::make_mut(&mut self.config).hsts = Some(hsts);
+        self
+    }
+
+    /// abort the response not sent in the duration since the request, the response not
+    /// started is the error of `TimedOut`. the timeouts need the tokio runtime and the
+    /// `tokio` feature.
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.config).response_timeout = Some(timeout);
+        self
+    }
+
+    /// abort the body if the client stops reading for the duration, the file is closed
+    /// even if the connection is never polled again.
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.config).stall_timeout = Some(timeout);
+        self
+    }
+
+    /// the `503` response of the request beyond the limit in flight.
+    async fn overloaded<B>(self, request: Request<B>, retry_after: u64) -> Result<Response<Body>> {
+        let builder = Response::builder().header(header::RETRY_AFTER, retry_after);
+        let status = StatusCode::SERVICE_UNAVAILABLE;
+        self.error_response(&request, status, builder).await
+    }
+
+    /// mount the service at the url prefix, e.g. `/static`, the prefix is stripped
+    /// before resolving against the root, the paths outside the prefix are not found.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        Arc::make_mut(&mut self.config).prefix = (!prefix.is_empty()).then(|| format!("/{prefix}"));
+        self
+    }
+
+    /// add the rewrite rule evaluated before resolving, the first matched rule is applied.
+    /// the path is matched after the prefix is stripped.
+    pub fn rewrite(mut self, rule: RewriteRule) -> Self {
+        Arc::make_mut(&mut self.config).rewrite_rules.push(rule);
+        self
+    }
+
+    /// serve the file as the body of the `404 Not Found` response, e.g. `404.html`.
+    /// the path is relative to the root.
+    pub fn not_found_page(self, path: impl Into<String>) -> Self {
+        self.error_page(StatusCode::NOT_FOUND, ErrorPage::file(path))
+    }
+
+    /// respond the unexpected error as `500 Internal Server Error` with the error page
+    /// of the status, the error is in the `InternalError` extension of the response.
+    /// the default is enabled, the error is returned by the service if disabled.
+    pub fn internal_error_response(mut self, enable: bool) -> Self {
+        Arc::make_mut(&mut self.config).internal_error_response = enable;
+        self
+    }
+
+    /// set the body of the error response of the status, e.g. `403` or `405`.
+    pub fn error_page(mut self, status: StatusCode, page: ErrorPage) -> Self {
+        Arc::make_mut(&mut self.config)
+            .error_pages
+            .insert(status, page);
+        self
+    }
+
+    /// render the error responses by the handler instead of the error pages, the headers
+    /// of the status are kept, e.g. `Allow` of `405`.
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Error, &Request<()>) -> Response<Body> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// call the hook with the request before it is resolved, the response returned by
+    /// the hook is sent without resolving, e.g. the maintenance page.
+    pub fn on_request<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut Parts) -> Option<Response<Body>> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).on_request = Some(Arc::new(hook));
+        self
+    }
+
+    /// call the hook with every response before it is sent, the request is the one
+    /// before the prefix is stripped and rewritten.
+    pub fn on_response<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Parts, &mut Response<Body>) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).on_response = Some(Arc::new(hook));
+        self
+    }
+
+    /// call the hook with the operational errors, e.g. to count and alert on them, the
+    /// error is observed even if it is responded as `500 Internal Server Error`.
+    pub fn on_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Error, &RequestMeta) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).on_error = Some(Arc::new(hook));
+        self
+    }
+
+    /// the request of the error hook.
+    fn request_meta<B>(&self, request: &Request<B>) -> RequestMeta {
+        let request_id = self.config.request_id.as_ref();
+        RequestMeta {
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            remote: ip::client_ip(request, &self.config.trusted_proxies),
+            request_id: request_id.and_then(|name| request.headers().get(name).cloned()),
+        }
+    }
+
+    /// call the error hook with the error of the request.
+    fn observe_error<B>(&self, error: &Error, request: &Request<B>) {
+        if let Some(ref hook) = self.config.on_error {
+            hook(error, &self.request_meta(request));
+        }
+    }
+
+    /// authorize the request to the resolved file or directory before it is opened,
+    /// the path of the meta is the one resolved, e.g. under the root.
+    pub fn authorize<F>(mut self, authorizer: F) -> Self
+    where
+        F: Fn(&Request<()>, &FileMeta) -> AuthorizeFuture + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).authorizer = Some(Arc::new(authorizer));
+        self
+    }
+
+    /// protect the paths matched the glob pattern by the basic authentication,
+    /// e.g. `private/**`, the first pattern matched is checked.
+    pub fn basic_auth(mut self, pattern: &str, auth: BasicAuth) -> Self {
+        Arc::make_mut(&mut self.config)
+            .auths
+            .push((Glob::new(pattern), Authentication::Basic(auth)));
+        self
+    }
+
+    /// protect the paths matched the glob pattern by the bearer token, e.g. `artifacts/**`,
+    /// the request without the valid token is responded as `401`.
+    #[cfg(feature = "bearer")]
+    pub fn bearer_auth(mut self, pattern: &str, auth: BearerAuth) -> Self {
+        Arc::make_mut(&mut self.config)
+            .auths
+            .push((Glob::new(pattern), Authentication::Bearer(auth)));
+        self
+    }
+
+    /// the clients of the CIDR block are forbidden, e.g. `203.0.113.0/24`. the client is
+    /// the `SocketAddr` in the extensions of the request, which hyper doesn't insert, the
+    /// caller must insert the peer address of the connection.
+    ///
+    /// # Panics
+    ///
+    /// panic if the CIDR block is invalid.
+    pub fn deny_ip(mut self, cidr: &str) -> Self {
+        let net = cidr.parse().expect("invalid cidr");
+        Arc::make_mut(&mut self.config).ip_deny.push(net);
+        self
+    }
+
+    /// only the clients of the allowed CIDR blocks are served, the others are forbidden.
+    /// the denied blocks are checked first, the client without the `SocketAddr` in the
+    /// extensions of the request is forbidden.
+    ///
+    /// # Panics
+    ///
+    /// panic if the CIDR block is invalid.
+    pub fn allow_ip(mut self, cidr: &str) -> Self {
+        let net = cidr.parse().expect("invalid cidr");
+        Arc::make_mut(&mut self.config).ip_allow.push(net);
+        self
+    }
+
+    /// the client is taken from `Forwarded` or `X-Forwarded-For` if the peer is the trusted
+    /// proxy of the CIDR block, e.g. the load balancer `10.0.0.0/8`.
+    ///
+    /// # Panics
+    ///
+    /// panic if the CIDR block is invalid.
+    pub fn trusted_proxy(mut self, cidr: &str) -> Self {
+        let net = cidr.parse().expect("invalid cidr");
+        Arc::make_mut(&mut self.config).trusted_proxies.push(net);
+        self
+    }
+
+    /// limit the requests of every client to the paths matched the glob pattern, e.g.
+    /// `downloads/**`, the first pattern matched is checked. the pattern is matched on
+    /// the path behind the prefix after the rewrite rules.
+    pub fn rate_limit(mut self, pattern: &str, limit: RateLimit) -> Self {
+        Arc::make_mut(&mut self.config)
+            .rate_limits
+            .push((Glob::new(pattern), limit));
+        self
+    }
+
+    /// the `429` response of the request beyond the limit of the client.
+    async fn rate_limited<B>(&self, request: &Request<B>) -> Result<Option<Response<Body>>> {
+        if self.config.rate_limits.is_empty() {
+            return Ok(None);
+        }
+        let path = request_resolve::request_path(request);
+        let path = request_resolve::normalize_path(&path).unwrap_or_default();
+        let limit = self
+            .config
+            .rate_limits
+            .iter()
+            .find(|(g, _)| g.is_match(&path));
+        let ip = ip::client_ip(request, &self.config.trusted_proxies);
+        let wait = match limit.and_then(|(_, limit)| limit.check(request, ip)) {
+            Some(wait) => wait,
+            None => return Ok(None),
+        };
+        let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        let builder = Response::builder().header(header::RETRY_AFTER, retry_after);
+        let resp = self.error_response(request, StatusCode::TOO_MANY_REQUESTS, builder);
+        resp.await.map(Some)
+    }
+
+    /// forbid or redirect the hotlink of the files of the other sites, e.g. the images.
+    pub fn hotlink_protection(mut self, hotlink: Hotlink) -> Self {
+        Arc::make_mut(&mut self.config).hotlink = Some(hotlink);
+        self
+    }
+
+    /// the response of the hotlink, `None` if it is not the hotlink.
+    async fn hotlinked<B>(&self, request: &Request<B>) -> Result<Option<Response<Body>>> {
+        let hotlink = match self.config.hotlink {
+            Some(ref hotlink) => hotlink,
+            None => return Ok(None),
+        };
+        let path = request_resolve::request_path(request);
+        let path = request_resolve::normalize_path(&path).unwrap_or_default();
+        if !hotlink.is_hotlink(&path, request.headers()) {
+            return Ok(None);
+        }
+        if let Some(location) = hotlink.redirect_location() {
+            let location = match self.config.prefix {
+                Some(ref prefix) if location.starts_with('/') => format!("{prefix}{location}"),
+                _ => location.to_string(),
+            };
+            let resp = Response::builder()
+                .status(StatusCode::FOUND)
+                .header(header::LOCATION, location)
+                .body(Body::Empty);
+            return resp.map(Some).map_err(Error::other);
+        }
+        let resp = self.error_response(request, StatusCode::FORBIDDEN, Response::builder());
+        resp.await.map(Some)
+    }
+
+    /// the paths matched the glob pattern are served by the urls signed by the key only,
+    /// e.g. `downloads/**`, the others are forbidden.
+    pub fn signed_url(mut self, pattern: &str, signer: SignedUrl) -> Self {
+        Arc::make_mut(&mut self.config)
+            .signed_urls
+            .push((Glob::new(pattern), signer));
+        self
+    }
+
+    /// the `401` or `403` response of the request without the valid credentials of the path,
+    /// the request path is checked before the resolve, so the missing file is challenged too.
+    async fn authenticated<B>(
+        &self,
+        request: &Request<B>,
+        path: &str,
+    ) -> Result<Option<Response<Body>>> {
+        let auth = self.config.auths.iter().find(|(g, _)| g.is_match(path));
+        let (status, challenge) = match auth {
+            Some((_, auth)) => match auth.check(request.headers()).await {
+                Some(denied) => denied,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let builder = Response::builder().header(header::WWW_AUTHENTICATE, challenge);
+        let resp = self.error_response(request, status, builder);
+        resp.await.map(Some)
+    }
+
+    /// the local root or the overlaid root the file is under.
+    fn root_of(&self, meta: &FileMeta) -> Option<&str> {
+        std::iter::once(&self.local_root)
+            .chain(&self.config.overlay_roots)
+            .find(|root| meta.path.starts_with(root))
+            .map(String::as_str)
+    }
+
+    /// the path of the served file relative to the root, `None` if the file is not under
+    /// the roots, e.g. the file of the resolver.
+    fn served_path(&self, meta: &FileMeta) -> Option<String> {
+        let relative = match meta.source {
+            Some(Source::Vfs { ref path, .. }) => return Some(path.clone()),
+            Some(Source::Memory { .. }) => meta.path.as_path(),
+            _ => meta.path.strip_prefix(self.root_of(meta)?).ok()?,
+        };
+        let segments: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        Some(segments.join("/"))
+    }
+
+    /// the response of the served file denied by the checks of the path, e.g. the index
+    /// file or the candidate of `try_files` under the path of the authentication or the
+    /// signed urls.
+    async fn guarded<B>(
+        &self,
+        request: &Request<B>,
+        meta: &FileMeta,
+    ) -> Result<Option<Response<Body>>> {
+        if self.config.auths.is_empty() && self.config.signed_urls.is_empty() {
+            return Ok(None);
+        }
+        let path = match self.served_path(meta) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let request_path = request_resolve::request_path(request);
+        let request_path = request_resolve::normalize_path(&request_path).unwrap_or_default();
+        // the url is signed by the request path, e.g. `/downloads/` of `downloads/index.html`.
+        let signed_url = self
+            .config
+            .signed_urls
+            .iter()
+            .find(|(g, _)| g.is_match(&path));
+        if signed_url
+            .is_some_and(|(_, signer)| !signer.verify(&request_path, request.uri().query()))
+        {
+            let resp = self.error_response(request, StatusCode::FORBIDDEN, Response::builder());
+            return resp.await.map(Some);
+        }
+        // the authentication of the request path is checked already.
+        let position = |path: &str| self.config.auths.iter().position(|(g, _)| g.is_match(path));
+        if position(&path) == position(&request_path) {
+            return Ok(None);
+        }
+        self.authenticated(request, &path).await
+    }
+
+    /// the response of the denied request, or none if it is allowed.
+    async fn authorized<B>(
+        &self,
+        request: &Request<B>,
+        resolved: &Resolved,
+    ) -> Result<Option<Response<Body>>> {
+        let (authorizer, meta) = match (&self.config.authorizer, resolved) {
+            (Some(authorizer), Resolved::Found(meta) | Resolved::IsDirectory(meta)) => {
+                (authorizer, meta)
+            }
+            _ => return Ok(None),
+        };
+        let status = match authorizer(&request_head(request), meta).await {
+            Decision::Allow => return Ok(None),
+            Decision::Forbidden => StatusCode::FORBIDDEN,
+            Decision::NotFound => StatusCode::NOT_FOUND,
+            Decision::Respond(resp) => return Ok(Some(*resp)),
+        };
+        let resp = self.error_response(request, status, Response::builder());
+        resp.await.map(Some)
+    }
+
+    /// the error response with the custom page of the status, the empty body is sent
+    /// if the page is not configured or the page file is not found.
+    async fn error_response<B>(
+        &self,
+        request: &Request<B>,
+        status: StatusCode,
+        builder: Builder,
+    ) -> Result<Response<Body>> {
+        let error = status_error(status);
+        self.error_response_of(request, status, builder, &error)
+            .await
+    }
+
+    /// the error response of the error, rendered by the handler if it is set.
+    async fn error_response_of<B>(
+        &self,
+        request: &Request<B>,
+        status: StatusCode,
+        mut builder: Builder,
+        error: &Error,
+    ) -> Result<Response<Body>> {
+        let is_head = request.method() == Method::HEAD;
+        if let Some(ref handler) = self.config.error_handler {
+            let mut resp = handler(error, &request_head(request));
+            for (name, value) in builder.headers_ref().into_iter().flatten() {
+                if !resp.headers().contains_key(name) {
+                    resp.headers_mut().insert(name, value.clone());
+                }
+            }
+            if is_head {
+                *resp.body_mut() = Body::Empty;
+            }
+            return Ok(resp);
+        }
+        builder = builder.status(status);
+        let body = match self.config.error_pages.get(&status) {
+            Some(ErrorPage::Static { content_type, body }) => {
+                builder = builder
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::CONTENT_LENGTH, body.len());
+                Body::Bytes(Some(body.clone()))
+            }
+            Some(ErrorPage::File(path)) => match self.open_file(path).await? {
+                Some((file, permit)) => {
+                    let size = file.meta.size;
+                    if let Some(content_type) = self.config.mime_types.content_type(&file.meta.path)
+                    {
+                        builder = builder.header(header::CONTENT_TYPE, content_type);
+                    }
+                    builder = builder.header(header::CONTENT_LENGTH, size);
+                    let reader = TokioFileReader::new(file, self.config.chunk_size);
+                    let body = Body::Full(FileBytesStream::new_with_limited(reader, size));
+                    match permit {
+                        Some(permit) => Body::Limited(Box::new(body), permit),
+                        None => body,
+                    }
+                }
+                None => Body::Empty,
+            },
+            None => Body::Empty,
+        };
+        let body = if is_head { Body::Empty } else { body };
+        builder.body(body).map_err(Error::other)
+    }
+
+    /// open the file of the path relative to the root, with the permit of the open files.
+    async fn open_file(
+        &self,
+        path: &str,
+    ) -> Result<Option<(FileWithMeta, Option<OwnedSemaphorePermit>)>> {
+        let meta = match self.find_file(path).await {
+            Some(meta) => meta,
+            None => return Ok(None),
+        };
+        let permit = self.open_files_permit().await?;
+        let opener = TokioFileReaderOpener::new(&self.local_root);
+        Ok(opener.open(&meta).await.ok().map(|file| (file, permit)))
+    }
+
+    /// the permit of the open files limit, it is held by the body of the opened file.
+    async fn open_files_permit(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        match self.config.open_files {
+            Some(ref semaphore) => {
+                let permit = semaphore.clone().acquire_owned().await;
+                permit.map(Some).map_err(|_| limit::closed())
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn method_not_allowed<B>(&self, request: &Request<B>) -> Result<Response<Body>> {
+        let status = self.config.method_not_allowed_status;
+        let mut builder = Response::builder();
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            builder = builder.header(header::ALLOW, self.config.allow_header());
+        }
+        self.error_response(request, status, builder).await
+    }
+
+    /// list the directory, the entries rejected by the resolve options are filtered.
+    async fn serv_listing<B>(
+        &self,
+        request: &Request<B>,
+        meta: FileMeta,
+    ) -> Result<Response<Body>> {
+        if let Some(resp) = self.guarded(request, &meta).await? {
+            return Ok(resp);
+        }
+        let path = request_resolve::request_path(request);
+        let path = request_resolve::normalize_path(&path).unwrap_or_default();
+        let options = self.config.resolve_options();
+        let mut entries = match vfs::vfs_path(&meta) {
+            Some((vfs, dir)) => vfs.read_dir(dir).await?,
+            None => listing::read_dir(meta.path).await?,
+        };
+        entries.retain(|e| {
+            let entry_path = if path.is_empty() {
+                e.name.clone()
+            } else {
+                format!("{path}/{}", e.name)
+            };
+            options.reject(&entry_path).is_none()
+        });
+        let listing = ListingQuery::from_request(request).listing(
+            &path,
+            entries,
+            self.config.listing_page_size,
+        );
+        let format = ListingFormat::from_request(request);
+        let mut builder = Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, format.content_type())
+            .header(header::VARY, header::ACCEPT.as_str());
+        let links: Vec<String> = [(&listing.prev, "prev"), (&listing.next, "next")]
+            .iter()
+            .filter_map(|(link, rel)| link.as_ref().map(|l| format!("<{l}>; rel=\"{rel}\"")))
+            .collect();
+        if !links.is_empty() {
+            builder = builder.header(header::LINK, links.join(", "));
+        }
+        let is_head = request.method() == Method::HEAD;
+        let body = match (format, &self.config.listing_template) {
+            (ListingFormat::Html, Some(template)) => {
+                let content = template(&listing);
+                builder = builder.header(header::CONTENT_LENGTH, content.len());
+                Body::Bytes(Some(content.into()))
+            }
+            // the length of the streaming listing is unknown, send by chunked.
+            _ => Body::Chunked(ChunkedBytesStream::new(
+                ListingChunks::new(listing, format).map(Bytes::from),
+            )),
+        };
+        let body = if is_head { Body::Empty } else { body };
+        builder.body(body).map_err(Error::other)
+    }
+
+    /// serve the request in the span of the request with the `tracing` feature, the metrics
+    /// and the access log are observed until the body is sent.
+    async fn serv<B: Sync>(self, mut request: Request<B>) -> Result<Response<Body>> {
+        let request_id = self.config.request_id.clone().map(|name| {
+            let id = request_id::ensure(&mut request, &name);
+            (name, id)
+        });
+        let id_name = request_id.as_ref().map(|(name, _)| name);
+        let access_log = self.config.access_log.clone().map(|log| {
+            let ip = ip::client_ip(&request, &self.config.trusted_proxies);
+            let record = log.record(&request, ip, id_name);
+            (log, record)
+        });
+        let metrics = self.config.metrics.clone();
+        let on_error = self.config.on_error.clone();
+        let error_meta = on_error.as_ref().map(|_| self.request_meta(&request));
+        #[cfg(feature = "tracing")]
+        let span = trace::request_span(&request, id_name);
+        let serve = self.serv_hooked(request);
+        #[cfg(feature = "tracing")]
+        let serve = trace::instrument(serve, span);
+        let mut resp = match serve.await {
             Ok(resp) => resp,
             Err(e) => {
-                let e = Error::new(ErrorKind::Other, e);
+                if let (Some(hook), Some(meta)) = (on_error, error_meta) {
+                    hook(&e, &meta);
+                }
+                if let Some(metrics) = metrics {
+                    metrics.observe(None);
+                }
                 return Err(e);
             }
         };
-        Ok(resp)
+        if let Some((name, id)) = request_id {
+            resp.headers_mut().entry(name).or_insert(id);
+        }
+        if let Some(metrics) = metrics {
+            metrics.observe(Some(&mut resp));
+        }
+        if let Some((log, record)) = access_log {
+            log.observe(record, &mut resp);
+        }
+        Ok(resp)
+    }
+
+    async fn serv_hooked<B: Sync>(self, request: Request<B>) -> Result<Response<Body>> {
+        let (mut parts, body) = request.into_parts();
+        let hooked = self
+            .config
+            .on_request
+            .as_ref()
+            .and_then(|hook| hook(&mut parts));
+        let mut request = Request::from_parts(parts, body);
+        // the hook of the response gets the uri before stripped and rewritten.
+        let uri = request.uri().clone();
+        let (timeout, stall) = (self.config.response_timeout, self.config.stall_timeout);
+        let is_timed = (timeout.is_some() || stall.is_some()) && runtime::is_tokio();
+        let deadline = timeout
+            .filter(|_| is_timed)
+            .map(|t| std::time::Instant::now() + t);
+        let mut resp = match (hooked, deadline) {
+            (Some(resp), _) => resp,
+            (None, Some(deadline)) => {
+                let serve = self.serv_or_error(&mut request);
+                match runtime::timeout_at(deadline, serve).await {
+                    Some(resp) => resp?,
+                    None => {
+                        return Err(Error::new(ErrorKind::TimedOut, "the response is timed out"))
+                    }
+                }
+            }
+            (None, None) => self.serv_or_error(&mut request).await?,
+        };
+        if let Some(ref cors) = self.config.cors {
+            cors.apply(request.headers(), &mut resp);
+        }
+        if let Some(ref security) = self.config.security_headers {
+            security.apply(resp.headers_mut());
+        }
+        if let Some(ref hsts) = self.config.hsts {
+            hsts.apply(&uri, request.headers(), resp.headers_mut());
+        }
+        #[cfg(feature = "tokio")]
+        if is_timed && !resp.body().is_empty() {
+            let body = std::mem::replace(resp.body_mut(), Body::Empty);
+            let deadline = deadline.map(Into::into);
+            *resp.body_mut() = Body::Timeout(TimeoutBytesStream::new(body, deadline, stall));
+        }
+        if let Some(ref hook) = self.config.on_response {
+            let (mut parts, _) = request.into_parts();
+            parts.uri = uri;
+            hook(&parts, &mut resp);
+        }
+        Ok(resp)
+    }
+
+    /// serve the request, the unexpected error is responded as `500` if enabled.
+    async fn serv_or_error<B: Sync>(&self, request: &mut Request<B>) -> Result<Response<Body>> {
+        let error = match self.serv_request(request).await {
+            Ok(resp) => return Ok(resp),
+            Err(e) if !self.config.internal_error_response => return Err(e),
+            Err(e) => e,
+        };
+        self.observe_error(&error, request);
+        let status = StatusCode::INTERNAL_SERVER_ERROR;
+        let mut resp = self
+            .error_response_of(request, status, Response::builder(), &error)
+            .await?;
+        resp.extensions_mut().insert(InternalError(Arc::new(error)));
+        Ok(resp)
+    }
+
+    async fn serv_request<B: Sync>(&self, request: &mut Request<B>) -> Result<Response<Body>> {
+        if self.config.is_ip_denied(request) {
+            return self
+                .error_response(request, StatusCode::FORBIDDEN, Response::builder())
+                .await;
+        }
+        let cors = self.config.cors.as_ref();
+        if let Some(resp) = cors.and_then(|c| c.preflight(request, &self.config.methods)) {
+            return Ok(resp);
+        }
+        let prefix = self.config.prefix.as_deref().unwrap_or_default();
+        if !prefix.is_empty() {
+            match strip_prefix(request.uri(), prefix) {
+                // the mount point should be slash-terminated for the relative links.
+                Some(_) if request.uri().path() == prefix && self.config.redirect_directory => {
+                    let location = match request.uri().query() {
+                        Some(query) => format!("{prefix}/?{query}"),
+                        None => format!("{prefix}/"),
+                    };
+                    return Response::builder()
+                        .status(StatusCode::MOVED_PERMANENTLY)
+                        .header(header::LOCATION, location)
+                        .body(Body::Empty)
+                        .map_err(Error::other);
+                }
+                Some(uri) => *request.uri_mut() = uri,
+                None => {
+                    return self
+                        .error_response(request, StatusCode::NOT_FOUND, Response::builder())
+                        .await
+                }
+            }
+        }
+        match RewriteRule::apply(&self.config.rewrite_rules, request.uri()) {
+            Some(Rewritten::Uri(uri)) => *request.uri_mut() = uri,
+            Some(Rewritten::Redirect(status, location)) => {
+                // the relative location is under the prefix.
+                let location = if location.starts_with('/') {
+                    format!("{prefix}{location}")
+                } else {
+                    location
+                };
+                return Response::builder()
+                    .status(status)
+                    .header(header::LOCATION, location)
+                    .body(Body::Empty)
+                    .map_err(Error::other);
+            }
+            None => {}
+        }
+        // the rules are matched on the path to resolve, behind the prefix and the rewrite.
+        if let Some(resp) = self.rate_limited(request).await? {
+            return Ok(resp);
+        }
+        if let Some(resp) = self.hotlinked(request).await? {
+            return Ok(resp);
+        }
+        if !self.config.auths.is_empty() {
+            let path = request_resolve::request_path(request);
+            let path = request_resolve::normalize_path(&path).unwrap_or_default();
+            if let Some(resp) = self.authenticated(request, &path).await? {
+                return Ok(resp);
+            }
+        }
+        let metrics = self.config.metrics.as_ref();
+        if let Some(resp) = metrics.and_then(|m| m.respond(request)) {
+            return Ok(resp);
+        }
+        let resolved = self.resolve(request).await?;
+        #[cfg(feature = "tracing")]
+        trace::resolved(&resolved);
+        if let Some(resp) = self.authorized(request, &resolved).await? {
+            return Ok(resp);
+        }
+        self.respond(request, resolved).await
+    }
+
+    /// resolve the request by the resolver, or the request path against the root by the
+    /// options of the service. the prefix and the rewrite rules are not applied,
+    /// the path is relative to the root.
+    pub async fn resolve<B: Sync>(&self, request: &Request<B>) -> Result<Resolved> {
+        if let Some(resolved) = self.resolver.resolve(request).await? {
+            return Ok(resolved);
+        }
+        let options = self.config.resolve_options();
+        let memory_files = self.config.memory_files.as_ref();
+        if let Some(resolved) = memory_files.and_then(|f| f.resolve(request, &options)) {
+            return Ok(resolved);
+        }
+        if let Some(ref assets) = self.config.embedded {
+            return Ok(assets.resolve(request, &options));
+        }
+        let mut resolved = self.resolve_request(request, &options).await?;
+        // the case-insensitive lookup and the archives are of the local files only.
+        let is_local = self.config.vfs.is_none();
+        // the matched path is checked by the options again, and the authentication of the
+        // served file is checked before it is sent.
+        if matches!(resolved, Resolved::NotFound) && self.config.case_insensitive && is_local {
+            if let Some(uri) = self.case_insensitive_uri(request).await {
+                let mut matched = Request::new(());
+                *matched.method_mut() = request.method().clone();
+                *matched.uri_mut() = uri;
+                resolved = self.resolve_request(&matched, &options).await?;
+            }
+        }
+        if matches!(resolved, Resolved::NotFound)
+            && !self.config.archive_kinds.is_empty()
+            && is_local
+        {
+            resolved = self.resolve_archive(request, &options).await?;
+        }
+        Ok(resolved)
+    }
+
+    /// resolve the request path against the roots in order, the first root having the path
+    /// wins, but the directory without the index file is the last resort.
+    async fn resolve_request<B>(
+        &self,
+        request: &Request<B>,
+        options: &ResolveOptions<'_>,
+    ) -> Result<Resolved> {
+        let mut resolved = Resolved::NotFound;
+        for vfs in self.root_vfses() {
+            match RequestResolve::resolve(vfs, request, options).await? {
+                Resolved::NotFound => {}
+                Resolved::IsDirectory(_) if matches!(resolved, Resolved::IsDirectory(_)) => {}
+                dir @ Resolved::IsDirectory(_) => resolved = dir,
+                found => return Ok(found),
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// resolve the not found path in the archive mounted as the directory,
+    /// e.g. `bundle/app.js` in `bundle.zip`.
+    async fn resolve_archive<B>(
+        &self,
+        request: &Request<B>,
+        options: &ResolveOptions<'_>,
+    ) -> Result<Resolved> {
+        let path = match request_resolve::normalized_path(request) {
+            Some(path) if options.reject(&path).is_none() => path,
+            _ => return Ok(Resolved::NotFound),
+        };
+        let kinds = self.config.archive_kinds.clone();
+        let archives = self.config.archives.clone();
+        let root = Path::new(&self.local_root).to_path_buf();
+        let policy = self.config.symlink_policy;
+        let mounted =
+            BlockingFuture::spawn(move || archives.find(&kinds, &root, &path, policy)).await?;
+        Ok(match mounted {
+            Some(mounted) => mounted.resolve(request, options),
+            None => Resolved::NotFound,
+        })
+    }
+
+    /// build the response of the resolved request as the service does, e.g. the file
+    /// with the conditional and range requests handled, or the error page. so the
+    /// application can inspect the resolved file before serving it.
+    pub async fn respond<B>(
+        &self,
+        request: &Request<B>,
+        resolved: Resolved,
+    ) -> Result<Response<Body>> {
+        let prefix = self.config.prefix.as_deref().unwrap_or_default();
+        let error = |status| self.error_response(request, status, Response::builder());
+        let resp = match resolved {
+            Resolved::BadRequest => error(StatusCode::BAD_REQUEST).await?,
+            Resolved::Options => Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header(header::ALLOW, self.config.allow_header())
+                .body(Body::Empty)
+                .map_err(Error::other)?,
+            Resolved::Redirect(location) => Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(header::LOCATION, format!("{prefix}{location}"))
+                .body(Body::Empty)
+                .map_err(Error::other)?,
+            Resolved::IsDirectory(meta) if self.config.autoindex => {
+                self.serv_listing(request, meta).await?
+            }
+            Resolved::IsDirectory(_) => error(StatusCode::FORBIDDEN).await?,
+            Resolved::MethodNotMatched => self.method_not_allowed(request).await?,
+            Resolved::NotFound => self.serv_not_found(request).await?,
+            Resolved::PermissionDenied => error(StatusCode::FORBIDDEN).await?,
+            Resolved::Found(meta) => {
+                let stats = self.config.stats.as_ref().map(|s| (s, meta.path.clone()));
+                let mut resp = self.serv_file(request, meta, Variant::default()).await?;
+                if let Some((stats, path)) = stats {
+                    stats.observe(path, &mut resp);
+                }
+                resp
+            }
+        };
+        let bucket = self.config.throttle_rate(request).map(TokenBucket::new);
+        let shared = self.config.bandwidth.clone();
+        if bucket.is_none() && shared.is_none() {
+            return Ok(resp);
+        }
+        Ok(resp.map(|body| Body::Throttled(ThrottledBytesStream::new(body, bucket, shared))))
     }
 }
 
-impl<B> Service<Request<B>> for FileService
+impl<B, R> Service<Request<B>> for FileService<R>
 where
     B: Sync + Send + 'static,
+    R: Resolver,
 {
     type Response = Response<Body>;
 
@@ -65,28 +2386,50 @@ where
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response>> + Send>>;
 
     fn call(&mut self, request: Request<B>) -> Self::Future {
-        Box::pin(self.clone().serv(request))
+        let service = self.clone();
+        let limit = match self.config.concurrency {
+            Some(ref limit) => limit,
+            None => return Box::pin(service.serv(request)),
+        };
+        // the permit of the requests in flight is held by the body until it is sent.
+        let permit = match self.in_flight.take(limit) {
+            Some(permit) => permit,
+            None => return Box::pin(service.overloaded(request, limit.retry_after_secs())),
+        };
+        Box::pin(async move {
+            let resp = service.serv(request).await?;
+            Ok(resp.map(|body| Body::Limited(Box::new(body), permit)))
+        })
     }
 
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
-        Poll::Ready(Ok(()))
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        match self.config.concurrency {
+            Some(ref limit) => self.in_flight.poll_acquire(limit, cx),
+            None => Poll::Ready(Ok(())),
+        }
     }
 }
 
 #[derive(Clone)]
-pub struct FileServiceMaker {
-    local_root: String,
+pub struct FileServiceMaker<R = RootResolver> {
+    service: FileService<R>,
 }
 
 impl FileServiceMaker {
     pub fn new(local_root: impl Into<String>) -> Self {
-        let local_root = local_root.into();
-        Self { local_root }
+        FileService::new(local_root).into()
+    }
+}
+
+/// make the connection services from the configured file service.
+impl<R> From<FileService<R>> for FileServiceMaker<R> {
+    fn from(service: FileService<R>) -> Self {
+        Self { service }
     }
 }
 
-impl<T> Service<T> for FileServiceMaker {
-    type Response = FileService;
+impl<T, R: Resolver> Service<T> for FileServiceMaker<R> {
+    type Response = FileService<R>;
 
     type Error = hyper::Error;
 
@@ -97,7 +2440,1883 @@ impl<T> Service<T> for FileServiceMaker {
     }
 
     fn call(&mut self, _: T) -> Self::Future {
-        let local_root = self.local_root.clone();
-        Box::pin(async move { Ok(FileService::new(local_root)) })
+        let service = self.service.clone();
+        Box::pin(async move { Ok(service) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{LogFormat, MemoryFile};
+
+    fn test_service(name: &str, content: &[u8]) -> FileService {
+        let dir = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("file.txt"), content).unwrap();
+        FileService::new(dir.to_str().unwrap())
+    }
+
+    async fn get<R: Resolver>(service: &mut FileService<R>, req: Request<()>) -> Response<Body> {
+        service.call(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_builder() {
+        let dir = std::env::temp_dir().join("blockless-svr-builder");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("home.html"), b"home").unwrap();
+        let mut service = FileService::builder(dir.to_str().unwrap())
+            .index_files(["home.html"])
+            .cache_control("*.html", "no-cache")
+            .build()
+            .unwrap();
+        let resp = get(&mut service, Request::get("/").body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = get(&mut service, Request::get("/home.html").body(()).unwrap()).await;
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+    }
+
+    #[tokio::test]
+    async fn test_if_modified_since() {
+        let mut service = test_service("blockless-svr-ims", b"hello");
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let last_modified = resp.headers()[header::LAST_MODIFIED].clone();
+
+        let req = Request::get("/file.txt")
+            .header(header::IF_MODIFIED_SINCE, last_modified)
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(body.is_empty());
+
+        let req = Request::get("/file.txt")
+            .header(header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:10 GMT")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_head() {
+        let mut service = test_service("blockless-svr-head", b"hello");
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let get_resp = get(&mut service, req).await;
+        let req = Request::head("/file.txt")
+            .header(header::RANGE, "bytes=0-1")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers(), get_resp.headers());
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "5");
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/plain");
+        assert!(resp.headers().contains_key(header::ETAG));
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_options() {
+        let mut service = test_service("blockless-svr-options", b"hello");
+        let req = Request::options("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[header::ALLOW], "GET, HEAD, OPTIONS");
+
+        let req = Request::options("/not-exists.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let mut service = service.methods([Method::GET, Method::OPTIONS]);
+        let req = Request::options("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::ALLOW], "GET, OPTIONS");
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed() {
+        let mut service = test_service("blockless-svr-not-allowed", b"hello");
+        let req = Request::post("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[header::ALLOW], "GET, HEAD, OPTIONS");
+
+        let mut service = service.method_not_allowed_status(StatusCode::BAD_REQUEST);
+        let req = Request::post("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(!resp.headers().contains_key(header::ALLOW));
+    }
+
+    #[tokio::test]
+    async fn test_attachment() {
+        let mut service = test_service("blockless-svr-attachment", b"hello");
+        let req = Request::get("/file.txt?download=1").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_DISPOSITION));
+
+        let mut service = service.attachment_by_query(true);
+        let req = Request::get("/file.txt?a=b&download=1").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(
+            resp.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"file.txt\""
+        );
+
+        let mut service = service.attachment("*.txt");
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert!(resp.headers().contains_key(header::CONTENT_DISPOSITION));
+    }
+
+    #[tokio::test]
+    async fn test_compression() {
+        let mut service = test_service("blockless-svr-compression", b"hello").compression(true);
+        let req = Request::get("/file.txt")
+            .header(header::ACCEPT_ENCODING, "gzip, deflate")
+            .body(())
+            .unwrap();
+        // the file is smaller than the minimum size.
+        let resp = get(&mut service, req).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+
+        let mut service = service.compression_config(CompressionConfig::new().min_size(0).level(9));
+        let req = Request::get("/file.txt")
+            .header(header::ACCEPT_ENCODING, "gzip, deflate")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding");
+
+        let req = Request::get("/file.txt")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(header::RANGE, "bytes=0-1")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn test_precompressed() {
+        let mut service = test_service("blockless-svr-precompressed", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-precompressed");
+        std::fs::write(dir.join("file.txt.br"), b"brotli").unwrap();
+        std::fs::write(dir.join("file.txt.gz"), b"gzip").unwrap();
+        let req = Request::get("/file.txt")
+            .header(header::ACCEPT_ENCODING, "gzip, br")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+
+        let mut service = service.precompressed(true).compression(true);
+        let req = Request::get("/file.txt")
+            .header(header::ACCEPT_ENCODING, "gzip, br")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "br");
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "6");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"brotli");
+
+        let req = Request::get("/file.txt")
+            .header(header::ACCEPT_ENCODING, "gzip, br;q=0.5")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"gzip");
+
+        for range in ["bytes=0-1", "bytes=0-1,3-4"] {
+            let req = Request::get("/file.txt")
+                .header(header::ACCEPT_ENCODING, "gzip, br")
+                .header(header::RANGE, range)
+                .body(())
+                .unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+            assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            let body = String::from_utf8_lossy(&body);
+            assert!(body.contains("he"));
+            assert!(!body.contains("br"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_precompressed_checked() {
+        let service = test_service("blockless-svr-precompressed-checked", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-precompressed-checked");
+        let outside = std::env::temp_dir().join("blockless-svr-precompressed-outside.br");
+        std::fs::write(&outside, b"outside").unwrap();
+        let _ = std::fs::remove_file(dir.join("file.txt.br"));
+        std::os::unix::fs::symlink(&outside, dir.join("file.txt.br")).unwrap();
+        std::fs::write(dir.join("file.txt.gz"), b"gzip").unwrap();
+        let req = || {
+            Request::get("/file.txt")
+                .header(header::ACCEPT_ENCODING, "gzip, br")
+                .body(())
+                .unwrap()
+        };
+        let mut service = service
+            .precompressed(true)
+            .symlink_policy(SymlinkPolicy::WithinRoot);
+        let resp = get(&mut service, req()).await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let mut service = service.deny("*.gz");
+        let resp = get(&mut service, req()).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_vary() {
+        let mut service = test_service("blockless-svr-vary", b"hello");
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert!(!resp.headers().contains_key(header::VARY));
+
+        let mut service = service
+            .vary("Cookie")
+            .vary("accept-encoding")
+            .compression(true);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding, Cookie");
+        let etag = resp.headers()[header::ETAG].clone();
+
+        let req = Request::get("/file.txt")
+            .header(header::IF_NONE_MATCH, etag)
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding, Cookie");
+    }
+
+    #[tokio::test]
+    async fn test_cache_control() {
+        let service = test_service("blockless-svr-cache-control", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-cache-control");
+        std::fs::write(dir.join("index.html"), b"<html></html>").unwrap();
+        let mut service = service
+            .cache_control("*.html", "no-cache")
+            .cache_control("**", "max-age=3600");
+        let req = Request::get("/index.html").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "max-age=3600");
+        let etag = resp.headers()[header::ETAG].clone();
+
+        let req = Request::get("/file.txt")
+            .header(header::IF_NONE_MATCH, etag)
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "max-age=3600");
+    }
+
+    #[tokio::test]
+    async fn test_fingerprinted_assets() {
+        let service = test_service("blockless-svr-fingerprint", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-fingerprint");
+        std::fs::write(dir.join("app.3f2a9c1b.js"), b"app").unwrap();
+        std::fs::write(dir.join("app.3f2a9c1b.html"), b"app").unwrap();
+        let mut service = service
+            .cache_control("*.html", "no-cache")
+            .fingerprinted_assets(true);
+        let req = Request::get("/app.3f2a9c1b.js").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(
+            resp.headers()[header::CACHE_CONTROL],
+            IMMUTABLE_CACHE_CONTROL
+        );
+
+        let req = Request::get("/app.3f2a9c1b.html").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert!(!resp.headers().contains_key(header::CACHE_CONTROL));
+    }
+
+    #[tokio::test]
+    async fn test_static_headers() {
+        let service = test_service("blockless-svr-headers", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-headers");
+        std::fs::write(dir.join("font.woff2"), b"font").unwrap();
+        let nosniff = HeaderName::from_static("x-content-type-options");
+        let mut service = service
+            .header(nosniff.clone(), HeaderValue::from_static("nosniff"))
+            .header(header::VARY, HeaderValue::from_static("Cookie"))
+            .header_for(
+                "*.woff2",
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            )
+            .compression(true);
+        let req = Request::get("/font.woff2").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[&nosniff], "nosniff");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding, Cookie");
+        assert_eq!(resp.headers().get_all(header::VARY).iter().count(), 1);
+
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[&nosniff], "nosniff");
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_percent_decoded_path() {
+        let mut service = test_service("blockless-svr-percent", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-percent");
+        std::fs::write(dir.join("a b 文件.txt"), b"hello").unwrap();
+        let req = Request::get("/a%20b%20%E6%96%87%E4%BB%B6.txt")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = Request::get("/a%FF.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = Request::get("/a%zz.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_path_traversal() {
+        let mut service = test_service("blockless-svr-traversal", b"hello");
+        for uri in [
+            "/../file.txt",
+            "/%2e%2e/etc/passwd",
+            "/a%00.txt",
+            "/..%5cfile.txt",
+        ] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+        let req = Request::get("//./file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_hide_hidden() {
+        let service = test_service("blockless-svr-hidden", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-hidden");
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join(".git/config"), b"config").unwrap();
+        std::fs::write(dir.join(".env"), b"env").unwrap();
+        let req = Request::get("/.env").body(()).unwrap();
+        let resp = get(&mut service.clone(), req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mut service = service.hide_hidden(true);
+        for uri in ["/.env", "/.git/config", "/%2egit/config"] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_deny_allow() {
+        let service = test_service("blockless-svr-deny", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-deny");
+        std::fs::create_dir_all(dir.join("private")).unwrap();
+        std::fs::write(dir.join("private/a.txt"), b"private").unwrap();
+        std::fs::write(dir.join("server.key"), b"key").unwrap();
+        let mut service = service.deny("**/*.key").forbid("private/**").allow("*.txt");
+        let cases = [
+            ("/server.key", StatusCode::NOT_FOUND),
+            // the deny is checked before the filesystem access.
+            ("/missing.key", StatusCode::NOT_FOUND),
+            ("/private/a.txt", StatusCode::FORBIDDEN),
+            ("/private/missing.txt", StatusCode::FORBIDDEN),
+            ("/file.txt", StatusCode::OK),
+        ];
+        for (uri, status) in cases {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), status, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_files() {
+        let mut service = test_service("blockless-svr-index", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-index");
+        std::fs::create_dir_all(dir.join("a/index.html")).unwrap();
+        std::fs::write(dir.join("a/default.html"), b"default").unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("index.html"), b"index").unwrap();
+        let req = Request::get("/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/html");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"index");
+
+        // the directory `a/index.html` is skipped.
+        let mut service = service.index_files(["index.html", "default.html"]);
+        let req = Request::get("/a/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"default");
+
+        let req = Request::get("/b/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let mut service = service.index_files(Vec::<String>::new());
+        let req = Request::get("/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_trailing_slash_redirect() {
+        let mut service = test_service("blockless-svr-slash", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-slash");
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/index.html"), b"index").unwrap();
+        let req = Request::get("/docs?v=1").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers()[header::LOCATION], "/docs/?v=1");
+
+        let req = Request::get("/file.txt/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mut service = service.strip_trailing_slash(true).redirect_directory(false);
+        let req = Request::get("/file.txt/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers()[header::LOCATION], "/file.txt");
+
+        let req = Request::get("/docs").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_autoindex() {
+        let service = test_service("blockless-svr-autoindex", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-autoindex");
+        std::fs::create_dir_all(dir.join("sub/inner")).unwrap();
+        std::fs::write(dir.join("sub/a b.txt"), b"hello").unwrap();
+        std::fs::write(dir.join("sub/.env"), b"env").unwrap();
+        let req = Request::get("/sub/").body(()).unwrap();
+        let resp = get(&mut service.clone(), req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let mut service = service.autoindex(true).hide_hidden(true);
+        let req = Request::get("/sub").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers()[header::LOCATION], "/sub/");
+
+        let req = Request::get("/sub/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("<a href=\"inner/\">inner/</a>"));
+        assert!(body.contains("<a href=\"a%20b.txt\">a b.txt</a>"));
+        assert!(!body.contains(".env"));
+
+        let req = Request::get("/sub/")
+            .header(header::ACCEPT, "application/json")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with(r#"[{"name":"inner","type":"directory""#));
+        assert!(body.contains(r#"{"name":"a b.txt","type":"file","size":5,"mtime":"#));
+
+        let mut service = service.listing_template(|listing| {
+            let names: Vec<_> = listing.entries.iter().map(|e| e.href()).collect();
+            format!("{} {}", listing.path, names.join(","))
+        });
+        let req = Request::get("/sub/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"/sub/ inner/,a%20b.txt");
+
+        let mut service = service.listing_page_size(1);
+        let req = Request::get("/sub/?format=json&page=2").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(
+            resp.headers()[header::LINK],
+            "<?format=json&page=1>; rel=\"prev\""
+        );
+        assert!(!resp.headers().contains_key(header::CONTENT_LENGTH));
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with(r#"[{"name":"a b.txt""#));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_file() {
+        let service = test_service("blockless-svr-fallback", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-fallback");
+        std::fs::write(dir.join("index.html"), b"app").unwrap();
+        let mut service = service.fallback_file("index.html");
+        let req = Request::get("/users/1").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/html");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"app");
+
+        let req = Request::get("/missing.js").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let mut service = service.fallback_file("missing.html");
+        let req = Request::get("/users/1").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_not_found_page() {
+        let service = test_service("blockless-svr-not-found", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-not-found");
+        std::fs::write(dir.join("404.html"), b"not found").unwrap();
+        let req = Request::get("/missing").body(()).unwrap();
+        let resp = get(&mut service.clone(), req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key(header::CONTENT_TYPE));
+
+        let mut service = service.not_found_page("404.html");
+        let req = Request::get("/missing").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/html");
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "9");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"not found");
+    }
+
+    #[tokio::test]
+    async fn test_error_pages() {
+        let service = test_service("blockless-svr-error-pages", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-error-pages");
+        std::fs::create_dir_all(dir.join("dir")).unwrap();
+        let mut service = service
+            .error_page(
+                StatusCode::FORBIDDEN,
+                ErrorPage::static_content("text/plain", "forbidden"),
+            )
+            .error_page(
+                StatusCode::METHOD_NOT_ALLOWED,
+                ErrorPage::static_content("text/plain", "not allowed"),
+            )
+            .index_files(Vec::<String>::new());
+        let req = Request::get("/dir/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"forbidden");
+
+        let req = Request::post("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(resp.headers().contains_key(header::ALLOW));
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"not allowed");
+
+        let req = Request::get("/missing").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key(header::CONTENT_TYPE));
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        let strip = |uri: &str| {
+            let uri: Uri = uri.parse().unwrap();
+            strip_prefix(&uri, "/static").map(|u| u.to_string())
+        };
+        assert_eq!(strip("/static/a.js?v=1").unwrap(), "/a.js?v=1");
+        assert_eq!(strip("/static").unwrap(), "/");
+        assert_eq!(
+            strip("http://a.com/static//a.js").unwrap(),
+            "http://a.com/a.js"
+        );
+        assert!(strip("/statics/a.js").is_none());
+        assert!(strip("/a.js").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_with_prefix() {
+        let service = test_service("blockless-svr-prefix", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-prefix");
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/index.html"), b"index").unwrap();
+        let mut service = service.with_prefix("/static/");
+        let req = Request::get("/static/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = Request::get("/static/docs").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::LOCATION], "/static/docs/");
+
+        let req = Request::get("/static").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::LOCATION], "/static/");
+    }
+
+    #[tokio::test]
+    async fn test_rewrite() {
+        let mut service = test_service("blockless-svr-rewrite", b"hello")
+            .with_prefix("/static")
+            .rewrite(RewriteRule::exact("/old.txt", "/file.txt"))
+            .rewrite(RewriteRule::regex("^/v1/(.*)$", "/$1").redirect(StatusCode::FOUND));
+        let req = Request::get("/static/old.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+
+        let req = Request::get("/static/v1/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers()[header::LOCATION], "/static/file.txt");
+    }
+
+    #[tokio::test]
+    async fn test_try_files() {
+        let service = test_service("blockless-svr-try-files", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-try-files");
+        std::fs::write(dir.join("about.html"), b"about").unwrap();
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/index.html"), b"docs").unwrap();
+        std::fs::write(dir.join("fallback.html"), b"fallback").unwrap();
+        let mut service =
+            service.try_files(["$uri", "$uri.html", "$uri/index.html", "/fallback.html"]);
+        for (uri, expected) in [
+            ("/file.txt", &b"hello"[..]),
+            ("/about", b"about"),
+            ("/docs/index", b"docs"),
+            ("/missing", b"fallback"),
+        ] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], expected, "{uri}");
+        }
+        let req = Request::get("/about").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/html");
+
+        let mut service = service.try_files(["$uri.html"]);
+        let req = Request::get("/missing").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_language_variants() {
+        let service = test_service("blockless-svr-language", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-language");
+        std::fs::write(dir.join("foo.html.en"), b"hello").unwrap();
+        std::fs::write(dir.join("foo.html.de"), b"hallo").unwrap();
+        let mut service = service.language_variants(true);
+        let req = Request::get("/foo.html")
+            .header(header::ACCEPT_LANGUAGE, "en;q=0.5, de-DE;q=0.8")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/html");
+        assert_eq!(resp.headers()[header::CONTENT_LANGUAGE], "de");
+        assert_eq!(resp.headers()[header::VARY], "accept-language");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hallo");
+
+        let req = || {
+            Request::get("/foo.html")
+                .header(header::ACCEPT_LANGUAGE, "fr")
+                .body(())
+                .unwrap()
+        };
+        let resp = get(&mut service, req()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+        let mut service = service.default_language("en");
+        let resp = get(&mut service, req()).await;
+        assert_eq!(resp.headers()[header::CONTENT_LANGUAGE], "en");
+    }
+
+    #[tokio::test]
+    async fn test_media_type_variants() {
+        let service = test_service("blockless-svr-media-type", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-media-type");
+        std::fs::write(dir.join("data.json"), b"{}").unwrap();
+        std::fs::write(dir.join("data.html"), b"<p></p>").unwrap();
+        std::fs::write(dir.join("data.xml"), b"<data/>").unwrap();
+        let mut service = service.media_type_variants(true);
+        let req = |accept| {
+            Request::get("/data")
+                .header(header::ACCEPT, accept)
+                .body(())
+                .unwrap()
+        };
+        let resp = get(&mut service, req("application/json, */*;q=0.1")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(resp.headers()[header::VARY], "accept");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"{}");
+
+        let resp = get(&mut service, req("text/html")).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"<p></p>");
+
+        let resp = get(&mut service, req("image/png")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive() {
+        let service = test_service("blockless-svr-case", b"hello");
+        let dir = std::env::temp_dir().join("blockless-svr-case");
+        std::fs::create_dir_all(dir.join("Docs")).unwrap();
+        std::fs::write(dir.join("Docs/README.md"), b"readme").unwrap();
+        std::fs::write(dir.join("Docs/a.txt"), b"a").unwrap();
+        std::fs::write(dir.join("Docs/A.TXT"), b"A").unwrap();
+        let req = || Request::get("/docs/readme.md").body(()).unwrap();
+        let resp = get(&mut service.clone(), req()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let mut service = service.case_insensitive(true);
+        let resp = get(&mut service, req()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"readme");
+
+        // the ambiguous match is not found.
+        let req = Request::get("/docs/a.Txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive_protected() {
+        let dir = std::env::temp_dir().join("blockless-svr-case-protected");
+        std::fs::create_dir_all(dir.join("private")).unwrap();
+        std::fs::write(dir.join("private/a.txt"), b"secret").unwrap();
+        std::fs::write(dir.join("denied.txt"), b"denied").unwrap();
+        let auth = BasicAuth::new("files").user("user", "pass");
+        let mut service = FileService::new(dir.to_str().unwrap())
+            .case_insensitive(true)
+            .basic_auth("private/**", auth)
+            .deny("denied.txt");
+        let resp = get(
+            &mut service,
+            Request::get("/PRIVATE/a.txt").body(()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let req =
+            Request::get("/PRIVATE/a.txt").header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = get(&mut service, Request::get("/DENIED.TXT").body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_and_respond() {
+        let service = test_service("blockless-svr-resolve", b"hello");
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resolved = service.resolve(&req).await.unwrap();
+        let meta = match resolved {
+            Resolved::Found(ref meta) => meta,
+            ref r => panic!("unexpected {r:?}"),
+        };
+        assert_eq!(meta.size, 5);
+        assert_eq!(service.content_type(meta).unwrap(), "text/plain");
+        let mut resp = service.respond(&req, resolved).await.unwrap();
+        resp.headers_mut()
+            .insert("x-custom", HeaderValue::from_static("1"));
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+
+        let req = Request::get("/missing.txt").body(()).unwrap();
+        let resolved = service.resolve(&req).await.unwrap();
+        assert!(matches!(resolved, Resolved::NotFound));
+    }
+
+    #[derive(Clone)]
+    struct AliasResolver(std::path::PathBuf);
+
+    impl Resolver for AliasResolver {
+        fn resolve<'a, B: Sync>(&'a self, request: &'a Request<B>) -> crate::ResolveFuture<'a> {
+            Box::pin(async move {
+                if request.uri().path() != "/alias" {
+                    return Ok(None);
+                }
+                Ok(Some(Resolved::Found(FileMeta::from_path(&self.0).await?)))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolver() {
+        let service = test_service("blockless-svr-resolver", b"hello");
+        let path = std::env::temp_dir().join("blockless-svr-resolver/file.txt");
+        let mut service = service.resolver(AliasResolver(path));
+        let req = Request::get("/alias").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_internal_error_response() {
+        let missing = std::env::temp_dir().join("blockless-svr-internal/missing.txt");
+        let page = ErrorPage::static_content("text/plain", "oops");
+        let mut service = test_service("blockless-svr-internal", b"hello")
+            .error_page(StatusCode::INTERNAL_SERVER_ERROR, page)
+            .resolver(AliasResolver(missing));
+        let req = Request::get("/alias").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let error = resp.extensions().get::<InternalError>().unwrap();
+        assert_eq!(error.0.kind(), ErrorKind::NotFound);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"oops");
+
+        let mut service = service.internal_error_response(false);
+        let req = Request::get("/alias").body(()).unwrap();
+        assert!(service.call(req).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_on_error() {
+        let missing = std::env::temp_dir().join("blockless-svr-on-error/missing.txt");
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let observed = errors.clone();
+        let mut service = test_service("blockless-svr-on-error", b"hello")
+            .resolver(AliasResolver(missing))
+            .on_error(move |error, meta| {
+                let path = meta.uri.path().to_string();
+                observed.lock().unwrap().push((error.kind(), path));
+            });
+        let resp = get(&mut service, Request::get("/alias").body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        // the errors of the responses are not observed.
+        get(&mut service, Request::get("/missing").body(()).unwrap()).await;
+        let mut service = service.internal_error_response(false);
+        let req = Request::get("/alias").body(()).unwrap();
+        assert!(service.call(req).await.is_err());
+        let error = (ErrorKind::NotFound, "/alias".to_string());
+        assert_eq!(*errors.lock().unwrap(), [error.clone(), error]);
+    }
+
+    #[tokio::test]
+    async fn test_error_handler() {
+        let mut service = test_service("blockless-svr-error-handler", b"hello").error_handler(
+            |error, request| {
+                let status = match error.kind() {
+                    ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                    _ => StatusCode::BAD_GATEWAY,
+                };
+                let body = format!(r#"{{"path":"{}"}}"#, request.uri().path());
+                Response::builder()
+                    .status(status)
+                    .header(header::CONTENT_TYPE, "application/problem+json")
+                    .body(Body::Bytes(Some(body.into())))
+                    .unwrap()
+            },
+        );
+        let req = Request::get("/missing").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"path":"/missing"}"#);
+
+        let req = Request::post("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        assert!(resp.headers().contains_key(header::ALLOW));
+    }
+
+    #[tokio::test]
+    async fn test_hooks() {
+        let mut service = test_service("blockless-svr-hooks", b"hello")
+            .with_prefix("/static")
+            .on_request(|parts| {
+                if parts.uri.path() == "/static/maintenance" {
+                    return Some(Response::new(Body::Bytes(Some("down".into()))));
+                }
+                parts.extensions.insert(std::time::Instant::now());
+                None
+            })
+            .on_response(|parts, resp| {
+                let timed = parts.extensions.get::<std::time::Instant>().is_some();
+                let value = format!("{} {timed}", parts.uri.path());
+                let value = HeaderValue::from_str(&value).unwrap();
+                resp.headers_mut().insert("x-hooked", value);
+            });
+        let req = Request::get("/static/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["x-hooked"], "/static/file.txt true");
+
+        let req = Request::get("/static/maintenance").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()["x-hooked"], "/static/maintenance false");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"down");
+    }
+
+    #[tokio::test]
+    async fn test_authorize() {
+        let dir = std::env::temp_dir().join("blockless-svr-authorize");
+        std::fs::create_dir_all(dir.join("private")).unwrap();
+        std::fs::write(dir.join("private/a.txt"), b"secret").unwrap();
+        std::fs::write(dir.join("public.txt"), b"public").unwrap();
+        let mut service = FileService::new(dir.to_str().unwrap()).authorize(|req, meta| {
+            let is_private = meta.path.parent().is_some_and(|p| p.ends_with("private"));
+            let has_session = req.headers().contains_key(header::COOKIE);
+            Box::pin(async move {
+                match (is_private, has_session) {
+                    (false, _) | (true, true) => Decision::Allow,
+                    (true, false) => Decision::Forbidden,
+                }
+            })
+        });
+        let req = Request::get("/public.txt").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::OK);
+        let req = Request::get("/private/a.txt").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::FORBIDDEN);
+        let req = Request::get("/private/a.txt").header(header::COOKIE, "session=1");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"secret");
+        // the path not found is never authorized.
+        let req = Request::get("/private/b.txt").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() {
+        let dir = std::env::temp_dir().join("blockless-svr-basic-auth");
+        std::fs::create_dir_all(dir.join("private")).unwrap();
+        std::fs::write(dir.join("private/a.txt"), b"secret").unwrap();
+        let auth = BasicAuth::new("files").user("user", "pass");
+        let mut service = FileService::new(dir.to_str().unwrap()).basic_auth("private/**", auth);
+        let req = Request::get("/private/a.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let challenge = &resp.headers()[header::WWW_AUTHENTICATE];
+        assert_eq!(challenge, "Basic realm=\"files\", charset=\"UTF-8\"");
+        let req =
+            Request::get("/private/a.txt").header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"secret");
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth_index() {
+        let dir = std::env::temp_dir().join("blockless-svr-basic-auth-index");
+        std::fs::create_dir_all(dir.join("private")).unwrap();
+        std::fs::write(dir.join("private/index.html"), b"secret").unwrap();
+        std::fs::write(dir.join("app.html"), b"app").unwrap();
+        let auth = BasicAuth::new("files").user("user", "pass");
+        let mut service = FileService::new(dir.to_str().unwrap())
+            .basic_auth("private/**", auth)
+            .try_files(["$uri", "private/index.html"]);
+        for uri in ["/private/", "/missing"] {
+            let resp = get(&mut service, Request::get(uri).body(()).unwrap()).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+        let req = Request::get("/private/").header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"secret");
+        let resp = get(&mut service, Request::get("/app.html").body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_ip_rules() {
+        let mut service = test_service("blockless-svr-ip", b"hello")
+            .allow_ip("192.0.2.0/24")
+            .deny_ip("192.0.2.66")
+            .trusted_proxy("10.0.0.1");
+        for (peer, forwarded, status) in [
+            (Some("192.0.2.1:1234"), None, StatusCode::OK),
+            (Some("192.0.2.66:1234"), None, StatusCode::FORBIDDEN),
+            (Some("198.51.100.1:1234"), None, StatusCode::FORBIDDEN),
+            (Some("10.0.0.1:1234"), Some("192.0.2.1"), StatusCode::OK),
+            (
+                Some("198.51.100.1:1234"),
+                Some("192.0.2.1"),
+                StatusCode::FORBIDDEN,
+            ),
+            (None, None, StatusCode::FORBIDDEN),
+        ] {
+            let mut req = Request::get("/file.txt");
+            if let Some(forwarded) = forwarded {
+                req = req.header("x-forwarded-for", forwarded);
+            }
+            let mut req = req.body(()).unwrap();
+            if let Some(peer) = peer {
+                req.extensions_mut()
+                    .insert(peer.parse::<std::net::SocketAddr>().unwrap());
+            }
+            assert_eq!(get(&mut service, req).await.status(), status);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid cidr")]
+    fn test_invalid_cidr() {
+        let _ = FileService::new("assets").allow_ip("192.0.2.0/33");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let limit = RateLimit::new(1, Duration::from_secs(10));
+        let mut service = test_service("blockless-svr-rate", b"hello").rate_limit("*.txt", limit);
+        let peer = "192.0.2.1:1234".parse::<std::net::SocketAddr>().unwrap();
+        let request = || {
+            let mut req = Request::get("/file.txt").body(()).unwrap();
+            req.extensions_mut().insert(peer);
+            req
+        };
+        assert_eq!(get(&mut service, request()).await.status(), StatusCode::OK);
+        let resp = get(&mut service, request()).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], "10");
+
+        let limit = RateLimit::new(1, Duration::from_secs(10));
+        let mut service = test_service("blockless-svr-rate-prefix", b"hello")
+            .with_prefix("/static")
+            .rate_limit("file.txt", limit);
+        let request = || {
+            let mut req = Request::get("/static/file.txt").body(()).unwrap();
+            req.extensions_mut().insert(peer);
+            req
+        };
+        assert_eq!(get(&mut service, request()).await.status(), StatusCode::OK);
+        let resp = get(&mut service, request()).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_size() {
+        use hyper::body::HttpBody;
+
+        let content = vec![b'a'; 10000];
+        let mut service = test_service("blockless-svr-chunk", &content).chunk_size(4096);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let mut body = get(&mut service, req).await.into_body();
+        let mut sizes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            sizes.push(chunk.unwrap().len());
+        }
+        assert_eq!(sizes, [4096, 4096, 1808]);
+    }
+
+    #[cfg(all(feature = "mmap", unix))]
+    #[tokio::test]
+    async fn test_mmap() {
+        let mut service = test_service("blockless-svr-mmap", b"hello world").mmap(1024);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello world");
+
+        let req = Request::get("/file.txt")
+            .header(header::RANGE, "bytes=6-")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"world");
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let mut service = test_service("blockless-svr-cache", b"hello").cache(1024, 4096);
+        let path = std::env::temp_dir().join("blockless-svr-cache/file.txt");
+        for _ in 0..2 {
+            let req = Request::get("/file.txt")
+                .header(header::RANGE, "bytes=1-3")
+                .body(())
+                .unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], b"ell");
+        }
+        let meta = FileMeta::from_path(&path).await.unwrap();
+        let cache = service.config.file_cache.as_ref().unwrap();
+        assert_eq!(cache.get(&meta).unwrap(), "hello");
+
+        std::fs::write(&path, b"hello world").unwrap();
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello world");
+    }
+
+    #[tokio::test]
+    async fn test_fd_cache() {
+        let mut service =
+            test_service("blockless-svr-fd-cache", b"hello").fd_cache(16, Duration::from_secs(60));
+        for (range, expected) in [
+            ("bytes=1-3", &b"ell"[..]),
+            ("bytes=0-", b"hello"),
+            ("bytes=2-", b"llo"),
+        ] {
+            let req = Request::get("/file.txt")
+                .header(header::RANGE, range)
+                .body(())
+                .unwrap();
+            let resp = get(&mut service, req).await;
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], expected);
+        }
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let limit = ConcurrencyLimit::new(1).retry_after(Duration::from_secs(5));
+        let mut service =
+            test_service("blockless-svr-concurrency", b"hello").concurrency_limit(limit);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // the permit is held by the body, the request is shed without the queue.
+        let mut other = service.clone();
+        let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+        assert!(Service::<Request<()>>::poll_ready(&mut other, &mut cx).is_ready());
+        let shed = get(&mut other, Request::get("/file.txt").body(()).unwrap()).await;
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()[header::RETRY_AFTER], "5");
+        drop(resp);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        assert_eq!(get(&mut other, req).await.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn test_stall_timeout() {
+        use hyper::body::HttpBody;
+
+        let content = vec![b'a'; 10000];
+        let mut service = test_service("blockless-svr-stall", &content)
+            .chunk_size(4096)
+            .stall_timeout(Duration::from_secs(5))
+            .response_timeout(Duration::from_secs(60));
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let mut body = get(&mut service, req).await.into_body();
+        assert!(body.data().await.unwrap().is_ok());
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        let error = body.data().await.unwrap().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_cors() {
+        let cors = Cors::new().allow_origin("https://a.example");
+        let mut service = test_service("blockless-svr-cors", b"hello").cors(cors);
+        let req = Request::options("/file.txt")
+            .header(header::ORIGIN, "https://a.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let allow_origin = &resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN];
+        assert_eq!(allow_origin, "https://a.example");
+
+        let req = Request::get("/file.txt").header(header::ORIGIN, "https://a.example");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let allow_origin = &resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN];
+        assert_eq!(allow_origin, "https://a.example");
+
+        // the other OPTIONS request is answered by the service.
+        let req = Request::options("/file.txt").header(header::ORIGIN, "https://b.example");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        assert!(resp.headers().contains_key(header::ALLOW));
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_security_headers() {
+        let mut service = test_service("blockless-svr-security", b"hello")
+            .header_for(
+                "*.txt",
+                header::X_FRAME_OPTIONS,
+                HeaderValue::from_static("DENY"),
+            )
+            .security_headers(SecurityHeaders::new());
+        for (path, frame_options) in [("/file.txt", "DENY"), ("/missing", "SAMEORIGIN")] {
+            let resp = get(&mut service, Request::get(path).body(()).unwrap()).await;
+            assert_eq!(resp.headers()[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+            assert_eq!(resp.headers()[header::X_FRAME_OPTIONS], frame_options);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_security_policy() {
+        let dir = std::env::temp_dir().join("blockless-svr-csp");
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("assets/a.txt"), b"a").unwrap();
+        let mut service = test_service("blockless-svr-csp", b"hello")
+            .content_security_policy("/assets/*", None)
+            .content_security_policy("*", Some("default-src 'self'"));
+        let resp = get(&mut service, Request::get("/file.txt").body(()).unwrap()).await;
+        let policy = &resp.headers()[header::CONTENT_SECURITY_POLICY];
+        assert_eq!(policy, "default-src 'self'");
+        let nonce = resp.extensions().get::<crate::CspNonce>();
+        assert!(nonce.is_none());
+        let resp = get(
+            &mut service,
+            Request::get("/assets/a.txt").body(()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+    }
+
+    #[tokio::test]
+    async fn test_hotlink_protection() {
+        let mut service = test_service("blockless-svr-hotlink", b"hello")
+            .hotlink_protection(Hotlink::new(["txt"]).allow_domain("example.com"));
+        let request = |referer| {
+            Request::get("/file.txt")
+                .header(header::REFERER, referer)
+                .body(())
+                .unwrap()
+        };
+        let resp = get(&mut service, request("https://www.example.com/")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = get(&mut service, request("https://other.site/")).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let mut service = service.hotlink_protection(Hotlink::new(["txt"]).redirect("/no.txt"));
+        let resp = get(&mut service, request("https://other.site/")).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers()[header::LOCATION], "/no.txt");
+
+        // the path resolved behind the prefix and the rewrite is checked.
+        let mut service = service
+            .with_prefix("/static")
+            .rewrite(RewriteRule::exact("/latest", "/file.txt"));
+        let request = |path, referer| {
+            Request::get(path)
+                .header(header::REFERER, referer)
+                .body(())
+                .unwrap()
+        };
+        let resp = get(
+            &mut service,
+            request("/static/latest", "https://other.site/"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers()[header::LOCATION], "/static/no.txt");
+        let resp = get(
+            &mut service,
+            request("/static/no.txt", "https://other.site/"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_access_log() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let log = {
+            let lines = lines.clone();
+            AccessLog::callback(LogFormat::Combined, move |line| {
+                lines.lock().unwrap().push(line.to_string())
+            })
+        };
+        let mut service = test_service("blockless-svr-access-log", b"hello").access_log(log);
+        let req = Request::get("/file.txt")
+            .header(header::RANGE, "bytes=1-2")
+            .header(header::USER_AGENT, "curl/8")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        // the line is logged after the body is sent.
+        assert!(lines.lock().unwrap().is_empty());
+        hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        get(&mut service, Request::get("/missing").body(()).unwrap()).await;
+        let lines = lines.lock().unwrap();
+        assert!(lines[0].ends_with("\"GET /file.txt HTTP/1.1\" 206 2 \"-\" \"curl/8\""));
+        assert!(lines[1].ends_with("\"GET /missing HTTP/1.1\" 404 - \"-\" \"-\""));
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let metrics = Metrics::new().path("/metrics");
+        let mut service = test_service("blockless-svr-metrics", b"hello")
+            .metrics(metrics.clone())
+            .cache(1024, 1024);
+        for _ in 0..2 {
+            let resp = get(&mut service, Request::get("/file.txt").body(()).unwrap()).await;
+            hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        }
+        get(&mut service, Request::get("/missing").body(()).unwrap()).await;
+        let resp = get(&mut service, Request::get("/metrics").body(()).unwrap()).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("hyper_file_requests_total{status=\"2xx\"} 2\n"));
+        assert!(body.contains("hyper_file_requests_total{status=\"4xx\"} 1\n"));
+        assert!(body.contains("hyper_file_sent_bytes_total 10\n"));
+        assert!(body.contains("hyper_file_cache_hits_total 1\n"));
+        assert!(body.contains("hyper_file_cache_misses_total 1\n"));
+        assert!(body.contains("hyper_file_open_files 0\n"));
+
+        // the metrics are served behind the prefix and the authentication.
+        let auth = BasicAuth::new("metrics").user("user", "pass");
+        let mut service = service.with_prefix("/static").basic_auth("metrics", auth);
+        let resp = get(&mut service, Request::get("/metrics").body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let req = Request::get("/static/metrics").body(()).unwrap();
+        assert_eq!(
+            get(&mut service, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let req =
+            Request::get("/static/metrics").header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req =
+            Request::post("/static/metrics").header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz");
+        let resp = get(&mut service, req.body(()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()[header::ALLOW], "GET, HEAD");
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let dir = std::env::temp_dir().join("blockless-svr-stats");
+        let mut service = test_service("blockless-svr-stats", b"hello").track_stats(10);
+        for _ in 0..2 {
+            let resp = get(&mut service, Request::get("/file.txt").body(()).unwrap()).await;
+            hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        }
+        get(&mut service, Request::get("/missing").body(()).unwrap()).await;
+        let stats = PathStats { hits: 2, bytes: 10 };
+        assert_eq!(service.stats(), [(dir.join("file.txt"), stats)]);
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let log = {
+            let lines = lines.clone();
+            AccessLog::callback(LogFormat::Json, move |line| {
+                lines.lock().unwrap().push(line.to_string())
+            })
+        };
+        let name = HeaderName::from_static("x-trace-id");
+        let mut service = test_service("blockless-svr-request-id", b"hello")
+            .request_id(name.clone())
+            .access_log(log);
+        let req = Request::get("/file.txt")
+            .header(&name, "t-1")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[&name], "t-1");
+        drop(resp);
+        let resp = get(&mut service, Request::get("/missing").body(()).unwrap()).await;
+        let id = resp.headers()[&name].to_str().unwrap().to_string();
+        assert_eq!(id.len(), 32);
+        drop(resp);
+        let lines = lines.lock().unwrap();
+        assert!(lines[0].ends_with(",\"request_id\":\"t-1\"}"));
+        assert!(lines[1].ends_with(&format!(",\"request_id\":\"{id}\"}}")));
+    }
+
+    #[tokio::test]
+    async fn test_max_open_files() {
+        let mut service = test_service("blockless-svr-open-files", b"hello").max_open_files(1);
+        let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = service.call(req).await.unwrap();
+
+        // the permit is held by the body, the responses without the file don't wait.
+        let head = Request::head("/file.txt").body(()).unwrap();
+        assert_eq!(get(&mut service, head).await.status(), StatusCode::OK);
+        let missing = Request::get("/missing").body(()).unwrap();
+        assert_eq!(
+            get(&mut service, missing).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        let mut other = service.call(Request::get("/file.txt").body(()).unwrap());
+        assert!(Pin::new(&mut other).poll(&mut cx).is_pending());
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+        let body = hyper::body::to_bytes(other.await.unwrap().into_body()).await;
+        assert_eq!(&body.unwrap()[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_throttle() {
+        let content = [b'a'; 150];
+        let mut service = test_service("blockless-svr-throttle", &content)
+            .throttle(1000)
+            .throttle_for("*.txt", 100);
+        let start = std::time::Instant::now();
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], &content[..]);
+        // the burst is 100 bytes, the rest 50 bytes are sent after 0.5s.
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[test]
+    #[should_panic(expected = "the throttle rate must be positive")]
+    fn test_throttle_zero() {
+        let _ = FileService::new("assets").throttle_for("*.iso", 0);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_limit() {
+        let content = [b'a'; 100];
+        let service = test_service("blockless-svr-bandwidth", &content).bandwidth_limit(100);
+        let start = std::time::Instant::now();
+        let requests = (0..2).map(|_| {
+            let mut service = service.clone();
+            async move {
+                let req = Request::get("/file.txt").body(()).unwrap();
+                let resp = get(&mut service, req).await;
+                hyper::body::to_bytes(resp.into_body()).await.unwrap()
+            }
+        });
+        for body in futures_util::future::join_all(requests).await {
+            assert_eq!(&body[..], &content[..]);
+        }
+        // the budget of 1s is shared by the two responses.
+        assert!(start.elapsed() >= Duration::from_millis(800));
+    }
+
+    #[tokio::test]
+    async fn test_zip_archives() {
+        let dir = std::env::temp_dir().join("blockless-svr-zip");
+        let _ = std::fs::remove_dir_all(&dir);
+        let css = "body { color: red; }\n".repeat(100);
+        crate::zip::test::write_zip(
+            &dir.join("bundle.zip"),
+            &[
+                ("index.html", b"<html></html>", false),
+                ("app.js", b"console.log(1)", false),
+                ("css/site.css", css.as_bytes(), true),
+            ],
+        );
+        let mut service = FileService::new(dir.to_str().unwrap()).zip_archives(true);
+        let req = Request::get("/bundle/app.js").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/javascript");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"console.log(1)");
+
+        let req = Request::get("/bundle/css/site.css")
+            .header(header::RANGE, "bytes=1000-1009")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], &css.as_bytes()[1000..1010]);
+        let req = Request::get("/bundle/css/site.css").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], css.as_bytes());
+
+        let req = Request::get("/bundle").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        let req = Request::get("/bundle/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"<html></html>");
+        let req = Request::get("/bundle/css/").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::FORBIDDEN);
+        let req = Request::get("/bundle/missing.js").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_tar_archives() {
+        let dir = std::env::temp_dir().join("blockless-svr-tar");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let content = "0123456789".repeat(100);
+        let tar = crate::tar::test::tar(&[("a.txt", b"hello"), ("b/c.txt", content.as_bytes())]);
+        std::fs::write(dir.join("plain.tar"), &tar).unwrap();
+        std::fs::write(dir.join("gzip.tgz"), crate::tar::test::gzip(&tar)).unwrap();
+        let mut service = FileService::new(dir.to_str().unwrap()).tar_archives(true);
+        for archive in ["plain", "gzip"] {
+            let req = Request::get(format!("/{archive}/a.txt")).body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], b"hello");
+
+            let req = Request::get(format!("/{archive}/b/c.txt"))
+                .header(header::RANGE, "bytes=995-")
+                .body(())
+                .unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], b"56789");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedded() {
+        let assets = EmbeddedAssets::new()
+            .add("index.html", b"<html></html>")
+            .add("js/app.js", b"console.log(1)")
+            .add("js/app.js.br", b"brotli");
+        let mut service = FileService::embedded(assets)
+            .precompressed(true)
+            .etag_strategy(EtagStrategy::Strong);
+        let req = Request::get("/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/html");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"<html></html>");
+
+        let req = Request::get("/js/app.js").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let etag = resp.headers()[header::ETAG].clone();
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"console.log(1)");
+        let req = Request::get("/js/app.js")
+            .header(header::IF_NONE_MATCH, etag)
+            .body(())
+            .unwrap();
+        assert_eq!(
+            get(&mut service, req).await.status(),
+            StatusCode::NOT_MODIFIED
+        );
+
+        let req = Request::get("/js/app.js")
+            .header(header::ACCEPT_ENCODING, "br")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "br");
+        let req = Request::get("/js").body(()).unwrap();
+        assert_eq!(
+            get(&mut service, req).await.status(),
+            StatusCode::MOVED_PERMANENTLY
+        );
+        let req = Request::get("/missing.js").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_overlay_root() {
+        let dir = std::env::temp_dir().join("blockless-svr-overlay");
+        let (site, theme) = (dir.join("site"), dir.join("theme"));
+        std::fs::create_dir_all(site.join("css")).unwrap();
+        std::fs::create_dir_all(theme.join("css")).unwrap();
+        std::fs::write(site.join("css/a.css"), b"site").unwrap();
+        std::fs::write(theme.join("css/a.css"), b"theme").unwrap();
+        std::fs::write(theme.join("css/b.css"), b"theme b").unwrap();
+        std::fs::write(theme.join("index.html"), b"<html></html>").unwrap();
+        let mut service =
+            FileService::new(site.to_str().unwrap()).overlay_root(theme.to_str().unwrap());
+        for (uri, content) in [
+            ("/css/a.css", &b"site"[..]),
+            ("/css/b.css", b"theme b"),
+            ("/", b"<html></html>"),
+        ] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], content, "{uri}");
+        }
+        let req = Request::get("/css/").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::FORBIDDEN);
+        let req = Request::get("/css/c.css").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_memory_files() {
+        let dir = std::env::temp_dir().join("blockless-svr-memory");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.js"), b"disk").unwrap();
+        std::fs::write(dir.join("index.html"), b"<html></html>").unwrap();
+        let files = MemoryFiles::new();
+        files.insert(
+            "env.js",
+            MemoryFile::new("window.env = {}").content_type("application/javascript"),
+        );
+        files.insert("app.js", MemoryFile::new("memory"));
+        let mut service = FileService::new(dir.to_str().unwrap()).memory_files(files.clone());
+        let req = Request::get("/env.js").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "application/javascript"
+        );
+        let last_modified = resp.headers()[header::LAST_MODIFIED].clone();
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"window.env = {}");
+        let req = Request::get("/env.js")
+            .header(header::IF_MODIFIED_SINCE, last_modified)
+            .body(())
+            .unwrap();
+        assert_eq!(
+            get(&mut service, req).await.status(),
+            StatusCode::NOT_MODIFIED
+        );
+
+        let req = Request::get("/app.js").body(()).unwrap();
+        let body = hyper::body::to_bytes(get(&mut service, req).await.into_body()).await;
+        assert_eq!(&body.unwrap()[..], b"memory");
+        files.remove("app.js");
+        let req = Request::get("/app.js").body(()).unwrap();
+        let body = hyper::body::to_bytes(get(&mut service, req).await.into_body()).await;
+        assert_eq!(&body.unwrap()[..], b"disk");
+        let req = Request::get("/").body(()).unwrap();
+        let body = hyper::body::to_bytes(get(&mut service, req).await.into_body()).await;
+        assert_eq!(&body.unwrap()[..], b"<html></html>");
+    }
+
+    #[tokio::test]
+    async fn test_read_ahead() {
+        let content = "0123456789".repeat(100);
+        let mut service = test_service("blockless-svr-read-ahead", content.as_bytes())
+            .chunk_size(64)
+            .read_ahead(4);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], content.as_bytes());
+
+        let req = Request::get("/file.txt")
+            .header(header::RANGE, "bytes=5-9, 993-996")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("\r\n\r\n56789\r\n"));
+        assert!(body.contains("\r\n\r\n3456\r\n"));
+    }
+
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    #[tokio::test]
+    async fn test_io_uring() {
+        let content = "0123456789".repeat(100);
+        for read_ahead in [0, 4] {
+            let mut service = test_service("blockless-svr-io-uring", content.as_bytes())
+                .chunk_size(64)
+                .read_ahead(read_ahead)
+                .io_uring(true);
+            let req = Request::get("/file.txt").body(()).unwrap();
+            let resp = get(&mut service, req).await;
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], content.as_bytes());
+
+            let req = Request::get("/file.txt")
+                .header(header::RANGE, "bytes=995-")
+                .body(())
+                .unwrap();
+            let resp = get(&mut service, req).await;
+            assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], b"56789");
+        }
+    }
+
+    #[test]
+    fn test_without_runtime() {
+        use crate::runtime::test::block_on;
+        let content = "0123456789".repeat(100);
+        let mut service = test_service("blockless-svr-no-runtime", content.as_bytes())
+            .chunk_size(64)
+            .etag_strategy(EtagStrategy::Strong);
+        let req = Request::get("/file.txt").body(()).unwrap();
+        let resp = block_on(get(&mut service, req));
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = block_on(hyper::body::to_bytes(resp.into_body())).unwrap();
+        assert_eq!(&body[..], content.as_bytes());
+
+        let req = Request::get("/file.txt")
+            .header(header::RANGE, "bytes=995-")
+            .body(())
+            .unwrap();
+        let resp = block_on(get(&mut service, req));
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = block_on(hyper::body::to_bytes(resp.into_body())).unwrap();
+        assert_eq!(&body[..], b"56789");
+    }
+
+    /// the files in memory, the directories are implied by the paths.
+    struct MemoryVfs(HashMap<&'static str, &'static [u8]>);
+
+    impl AsyncVfs for MemoryVfs {
+        type File = Bytes;
+
+        fn metadata(&self, path: &str) -> vfs::VfsFuture<FileMeta> {
+            let is_dir = path.is_empty()
+                || self
+                    .0
+                    .keys()
+                    .any(|p| p.strip_prefix(path).is_some_and(|p| p.starts_with('/')));
+            let meta = match self.0.get(path) {
+                Some(content) => Ok(FileMeta::virtual_file(path, content.len() as u64, None)),
+                None if is_dir => Ok(FileMeta::virtual_dir(path)),
+                None => Err(Error::from(ErrorKind::NotFound)),
+            };
+            Box::pin(async move { meta })
+        }
+
+        fn read_dir(&self, path: &str) -> vfs::VfsFuture<Vec<listing::DirEntry>> {
+            let prefix = format!("{path}/");
+            let entries = self
+                .0
+                .iter()
+                .filter_map(|(p, content)| {
+                    let name = p.strip_prefix(&prefix)?;
+                    Some(listing::DirEntry {
+                        name: name.to_string(),
+                        is_dir: false,
+                        size: content.len() as u64,
+                        modified: None,
+                    })
+                })
+                .collect();
+            Box::pin(async move { Ok(entries) })
+        }
+
+        fn open(&self, meta: &FileMeta) -> vfs::VfsFuture<Bytes> {
+            let content = self.0[meta.path.to_str().unwrap()];
+            Box::pin(async move { Ok(Bytes::from_static(content)) })
+        }
+
+        fn read_at(&self, file: Arc<Bytes>, offset: u64, len: usize) -> vfs::VfsFuture<Bytes> {
+            let start = (offset as usize).min(file.len());
+            let end = (start + len).min(file.len());
+            Box::pin(async move { Ok(file.slice(start..end)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vfs() {
+        let files = HashMap::from([
+            ("index.html", &b"<html></html>"[..]),
+            ("docs/a.txt", b"0123456789"),
+            ("docs/a.txt.gz", b"gzip"),
+        ]);
+        let mut service = FileService::vfs(MemoryVfs(files))
+            .chunk_size(4)
+            .precompressed(true)
+            .autoindex(true)
+            .etag_strategy(EtagStrategy::Strong);
+        let req = Request::get("/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"<html></html>");
+
+        let req = Request::get("/docs/a.txt")
+            .header(header::RANGE, "bytes=3-8")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(!resp.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .starts_with("W/"));
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"345678");
+
+        let req = Request::get("/docs/a.txt")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(())
+            .unwrap();
+        let resp = get(&mut service, req).await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let req = Request::get("/docs/").body(()).unwrap();
+        let resp = get(&mut service, req).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("a.txt.gz"));
+        let req = Request::get("/missing.txt").body(()).unwrap();
+        assert_eq!(get(&mut service, req).await.status(), StatusCode::NOT_FOUND);
     }
 }
@@ -0,0 +1,81 @@
+/// The glob pattern to match the request path.
+/// `*` matches any chars except `/`, `**` matches any chars include `/`,
+/// `?` matches one char except `/`. The pattern without `/` matches the file name only,
+/// e.g. `*.html` matches `a/b/index.html`.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: Vec<char>,
+    is_name_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let pattern = pattern.trim_start_matches('/');
+        Self {
+            is_name_only: !pattern.contains('/'),
+            pattern: pattern.chars().collect(),
+        }
+    }
+
+    /// match the request path, the leading `/` is ignored.
+    pub fn is_match(&self, path: &str) -> bool {
+        let path = path.trim_start_matches('/');
+        let path = if self.is_name_only {
+            path.rsplit('/').next().unwrap_or(path)
+        } else {
+            path
+        };
+        let path: Vec<char> = path.chars().collect();
+        Self::match_from(&self.pattern, &path)
+    }
+
+    fn match_from(pattern: &[char], s: &[char]) -> bool {
+        match pattern.first() {
+            None => s.is_empty(),
+            Some('*') if pattern.get(1) == Some(&'*') => {
+                let rest = &pattern[2..];
+                // `**/` also matches the zero directory.
+                if rest.first() == Some(&'/') && Self::match_from(&rest[1..], s) {
+                    return true;
+                }
+                (0..=s.len()).any(|i| Self::match_from(rest, &s[i..]))
+            }
+            Some('*') => {
+                for i in 0..=s.len() {
+                    if Self::match_from(&pattern[1..], &s[i..]) {
+                        return true;
+                    }
+                    if i < s.len() && s[i] == '/' {
+                        break;
+                    }
+                }
+                false
+            }
+            Some('?') => !s.is_empty() && s[0] != '/' && Self::match_from(&pattern[1..], &s[1..]),
+            Some(c) => s.first() == Some(c) && Self::match_from(&pattern[1..], &s[1..]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let is_match = |p: &str, s: &str| Glob::new(p).is_match(s);
+        assert!(is_match("*.html", "index.html"));
+        assert!(is_match("*.html", "/a/b/index.html"));
+        assert!(!is_match("*.html", "index.htm"));
+        assert!(is_match("private/**", "private/a/b.txt"));
+        assert!(!is_match("private/**", "public/private/a"));
+        assert!(is_match("**/*.key", "a/b/server.key"));
+        assert!(is_match("**/*.key", "server.key"));
+        assert!(is_match("/assets/**", "/assets/app.js"));
+        assert!(!is_match("assets/*", "assets/js/app.js"));
+        assert!(is_match("assets/*", "assets/app.js"));
+        assert!(is_match("a?c", "abc"));
+        assert!(!is_match("a/?", "a//"));
+        assert!(is_match("文件/*.txt", "文件/说明.txt"));
+    }
+}
@@ -0,0 +1,136 @@
+use hyper::{header, HeaderMap, Uri};
+
+/// The hotlink protection of the files by the extensions, e.g. the images and videos.
+/// the request of the file with the `Referer` of the other sites is forbidden or
+/// redirected, e.g. to the placeholder image. the own host of the request and the
+/// allowed domains with their subdomains are allowed.
+#[derive(Debug, Clone)]
+pub struct Hotlink {
+    // the lowercase extensions without the dot.
+    extensions: Vec<String>,
+    domains: Vec<String>,
+    allow_empty: bool,
+    redirect: Option<String>,
+}
+
+impl Hotlink {
+    /// protect the files of the extensions, e.g. `["png", "jpg", "mp4"]`.
+    pub fn new<'a>(extensions: impl IntoIterator<Item = &'a str>) -> Self {
+        let extensions = extensions
+            .into_iter()
+            .map(|e| e.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        Self {
+            extensions,
+            domains: Vec::new(),
+            allow_empty: true,
+            redirect: None,
+        }
+    }
+
+    /// allow the `Referer` of the domain and its subdomains, e.g. `example.com`.
+    pub fn allow_domain(mut self, domain: &str) -> Self {
+        let domain = domain.trim_start_matches("*.").trim_matches('.');
+        self.domains.push(domain.to_ascii_lowercase());
+        self
+    }
+
+    /// allow the request without the `Referer`, e.g. the direct visit or the privacy
+    /// settings of the browser, the default is `true`.
+    pub fn allow_empty_referer(mut self, enable: bool) -> Self {
+        self.allow_empty = enable;
+        self
+    }
+
+    /// redirect the hotlink to the location instead of `403 Forbidden`, the location
+    /// itself is not protected. the absolute path is under the prefix of the service.
+    pub fn redirect(mut self, location: &str) -> Self {
+        self.redirect = Some(location.to_string());
+        self
+    }
+
+    /// the location of the redirect.
+    pub(crate) fn redirect_location(&self) -> Option<&str> {
+        self.redirect.as_deref()
+    }
+
+    fn is_allowed_host(&self, host: &str, headers: &HeaderMap) -> bool {
+        let host = host.to_ascii_lowercase();
+        let own = headers
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.rsplit_once(':').map_or(v, |(h, _)| h));
+        if own.is_some_and(|own| own.eq_ignore_ascii_case(&host)) {
+            return true;
+        }
+        self.domains.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+
+    /// the request of the path is the hotlink of the other site.
+    pub(crate) fn is_hotlink(&self, path: &str, headers: &HeaderMap) -> bool {
+        let redirect = self.redirect.as_deref().map(|r| r.trim_start_matches('/'));
+        if redirect == Some(path.trim_start_matches('/')) {
+            return false;
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let is_protected = name
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+        if !is_protected {
+            return false;
+        }
+        let referer = match headers.get(header::REFERER) {
+            Some(referer) => referer,
+            None => return !self.allow_empty,
+        };
+        let host = referer
+            .to_str()
+            .ok()
+            .and_then(|v| v.parse::<Uri>().ok())
+            .and_then(|uri| uri.host().map(String::from));
+        match host {
+            Some(host) => !self.is_allowed_host(&host, headers),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    fn headers(referer: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("cdn.example:8080"));
+        if let Some(referer) = referer {
+            headers.insert(header::REFERER, HeaderValue::from_static(referer));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_is_hotlink() {
+        let hotlink = Hotlink::new(["png", ".MP4"]).allow_domain("example.com");
+        let referer = |r| hotlink.is_hotlink("/img/a.png", &headers(r));
+        assert!(!referer(None));
+        assert!(!referer(Some("https://example.com/page")));
+        assert!(!referer(Some("https://www.example.com/page")));
+        assert!(!referer(Some("http://cdn.example:8080/")));
+        assert!(referer(Some("https://badexample.com/")));
+        assert!(referer(Some("https://other.site/")));
+        assert!(hotlink.is_hotlink("/a.mp4", &headers(Some("https://other.site/"))));
+        assert!(!hotlink.is_hotlink("/a.html", &headers(Some("https://other.site/"))));
+
+        let hotlink = Hotlink::new(["png"])
+            .allow_empty_referer(false)
+            .redirect("/hotlink.png");
+        assert!(hotlink.is_hotlink("/a.png", &headers(None)));
+        assert!(!hotlink.is_hotlink("/hotlink.png", &headers(None)));
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    io::{Error, ErrorKind},
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use hyper::{
+    header::{self, HeaderName},
+    HeaderMap, Request,
+};
+
+/// The CIDR block of the addresses, e.g. `10.0.0.0/8`, `2001:db8::/32` or the single
+/// address `127.0.0.1`. the IPv4-mapped IPv6 address matches the IPv4 block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl FromStr for IpNet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::new(ErrorKind::InvalidInput, format!("invalid cidr: {s}"));
+        let (addr, prefix_len) = s.split_once('/').unwrap_or((s, ""));
+        let addr = addr.parse::<IpAddr>().map_err(|_| invalid())?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            "" => max_len,
+            len => len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(invalid)?,
+        };
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl IpNet {
+    /// the address is in the block.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let addr = canonical(addr);
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// the IPv4 address of the IPv4-mapped IPv6 address, e.g. of the dual-stack listener.
+fn canonical(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+        addr => addr,
+    }
+}
+
+/// the address of `for` in the `Forwarded` element, e.g. `"[2001:db8::1]:4711"`.
+fn forwarded_for(element: &str) -> Option<IpAddr> {
+    let value = element.split(';').find_map(|pair| {
+        let (name, value) = pair.trim().split_once('=')?;
+        name.eq_ignore_ascii_case("for")
+            .then_some(value.trim().trim_matches('"'))
+    })?;
+    if let Some(v6) = value.strip_prefix('[') {
+        return v6.split(']').next()?.parse().ok();
+    }
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|a| a.ip()))
+}
+
+/// the forwarded addresses of the proxies in order, `Forwarded` is preferred to
+/// `X-Forwarded-For`.
+fn forwarded_addrs(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect::<Vec<_>>()
+    };
+    let forwarded = values(header::FORWARDED);
+    if !forwarded.is_empty() {
+        return forwarded.into_iter().map(forwarded_for).collect();
+    }
+    let forwarded = values(HeaderName::from_static("x-forwarded-for"));
+    forwarded
+        .into_iter()
+        .map(|v| v.trim().parse().ok())
+        .collect()
+}
+
+/// the address of the client, the peer is the `SocketAddr` in the extensions of the
+/// request, which is inserted by the caller of the service. the forwarded addresses are taken from the
+/// right while the peer is one of the trusted proxies.
+pub(crate) fn client_ip<B>(request: &Request<B>, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let mut addr = canonical(request.extensions().get::<SocketAddr>()?.ip());
+    if !trusted_proxies.iter().any(|net| net.contains(addr)) {
+        return Some(addr);
+    }
+    for forwarded in forwarded_addrs(request.headers()).into_iter().rev() {
+        // the malformed address of the untrusted hop is never trusted.
+        addr = canonical(forwarded?);
+        if !trusted_proxies.iter().any(|net| net.contains(addr)) {
+            break;
+        }
+    }
+    Some(addr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn net(s: &str) -> IpNet {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(net("10.0.0.0/8").contains(ip("10.1.2.3")));
+        assert!(!net("10.0.0.0/8").contains(ip("11.0.0.1")));
+        assert!(net("10.0.0.0/8").contains(ip("::ffff:10.0.0.1")));
+        assert!(net("0.0.0.0/0").contains(ip("1.2.3.4")));
+        assert!(net("2001:db8::/32").contains(ip("2001:db8::1")));
+        assert!(!net("2001:db8::/32").contains(ip("10.0.0.1")));
+        assert!(net("127.0.0.1").contains(ip("127.0.0.1")));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("10.0.0".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusted = [net("10.0.0.0/8")];
+        let request = |peer: &str, name: &str, value: &str| {
+            let mut req = Request::get("/").header(name, value).body(()).unwrap();
+            req.extensions_mut()
+                .insert(peer.parse::<SocketAddr>().unwrap());
+            client_ip(&req, &trusted)
+        };
+        let xff = "x-forwarded-for";
+        assert_eq!(request("1.1.1.1:80", xff, "2.2.2.2"), Some(ip("1.1.1.1")));
+        let forwarded = request("10.0.0.1:80", xff, "3.3.3.3, 2.2.2.2, 10.0.0.2");
+        assert_eq!(forwarded, Some(ip("2.2.2.2")));
+        let forwarded = request("10.0.0.1:80", "forwarded", "for=\"[2001:db8::1]:4711\"");
+        assert_eq!(forwarded, Some(ip("2001:db8::1")));
+        let forwarded = request("10.0.0.1:80", "forwarded", "for=2.2.2.2;proto=https");
+        assert_eq!(forwarded, Some(ip("2.2.2.2")));
+        assert_eq!(request("10.0.0.1:80", xff, "unknown"), None);
+        assert_eq!(client_ip(&Request::new(()), &trusted), None);
+    }
+}
@@ -1,9 +1,98 @@
+mod access_log;
+mod archive;
+mod auth;
+mod authorize;
+#[cfg(feature = "bearer")]
+mod bearer;
 mod body;
+mod builder;
+mod cache;
+mod compression;
+mod cors;
+mod disposition;
+mod embedded;
 mod error;
+mod error_page;
+mod etag;
+mod fd_cache;
 mod file;
 mod filesvr;
+mod glob;
+mod hotlink;
+mod ip;
+mod limit;
+mod listing;
+mod local;
+mod memory;
+mod metrics;
+mod mime;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+mod negotiate;
 mod range;
+mod rate_limit;
+mod read_ahead;
+mod request_id;
 mod request_resolve;
+mod resolver;
 mod resp_builder;
+mod rewrite;
+mod router;
+mod runtime;
+#[cfg(feature = "s3")]
+mod s3;
+mod security;
+mod sign;
+mod signed_url;
+mod stats;
+mod tar;
+mod throttle;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+mod vfs;
+mod vhost;
+#[cfg(any(not(feature = "tokio"), target_os = "wasi"))]
+mod wasi;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+mod watch;
+mod zip;
 
-pub use filesvr::FileServiceMaker;
+pub use access_log::{AccessLog, LogFormat};
+pub use auth::{BasicAuth, BasicVerifier, VerifyFuture};
+pub use authorize::{AuthorizeFuture, Authorizer, Decision};
+#[cfg(feature = "bearer")]
+pub use bearer::{BearerAuth, JwtKey, TokenFuture, TokenStatus, TokenValidator};
+pub use body::Body;
+pub use builder::FileServiceBuilder;
+pub use compression::CompressionConfig;
+pub use cors::Cors;
+pub use embedded::EmbeddedAssets;
+pub use error::{InternalError, RequestMeta};
+pub use error_page::ErrorPage;
+pub use etag::EtagStrategy;
+pub use file::{FileMeta, SymlinkPolicy};
+pub use filesvr::{
+    ErrorHandler, ErrorHook, FileService, FileServiceMaker, RequestHook, ResponseHook,
+};
+pub use hotlink::Hotlink;
+pub use ip::IpNet;
+pub use limit::ConcurrencyLimit;
+pub use listing::{escape_html, DirEntries, DirEntry, ListingTemplate};
+pub use local::LocalFileService;
+pub use memory::{MemoryFile, MemoryFiles};
+pub use metrics::Metrics;
+pub use rate_limit::RateLimit;
+pub use request_resolve::Resolved;
+pub use resolver::{ResolveFuture, Resolver, RootResolver};
+pub use rewrite::RewriteRule;
+pub use router::{FileRouter, FileRouterMaker};
+pub use runtime::{set_blocking_executor, BlockingExecutor};
+#[cfg(feature = "s3")]
+pub use s3::{S3Object, S3Vfs};
+pub use security::{CspNonce, Hsts, SecurityHeaders, NONCE_PLACEHOLDER};
+pub use signed_url::SignedUrl;
+pub use stats::PathStats;
+pub use vfs::{AsyncVfs, LocalFs, VfsFuture};
+pub use vhost::{VirtualHostService, VirtualHostServiceMaker};
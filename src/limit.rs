@@ -0,0 +1,213 @@
+use std::{
+    future::Future,
+    io::{Error, Result},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+pub(crate) type AcquireFuture =
+    Pin<Box<dyn Future<Output = std::result::Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+/// The permit acquired by `poll_ready` for the next call, the clone of the
+/// service starts without the permit.
+#[derive(Default)]
+pub(crate) struct ReadyPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    // the future is not `Sync`, it is only polled by `&mut self`.
+    acquiring: Mutex<Option<AcquireFuture>>,
+}
+
+impl Clone for ReadyPermit {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+pub(crate) fn closed() -> Error {
+    Error::other("the open files semaphore is closed")
+}
+
+impl ReadyPermit {
+    /// poll for the permit of the semaphore, it is ready if the permit is held.
+    pub fn poll_acquire(
+        &mut self,
+        semaphore: &Arc<Semaphore>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<()>> {
+        if self.permit.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        let acquiring = self.acquiring.get_mut().unwrap();
+        let future = acquiring.get_or_insert_with(|| Box::pin(semaphore.clone().acquire_owned()));
+        let permit = match future.as_mut().poll(cx) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => return Poll::Pending,
+        };
+        *acquiring = None;
+        self.permit = Some(permit.map_err(|_| closed())?);
+        Poll::Ready(Ok(()))
+    }
+
+    /// take the permit acquired by `poll_ready`.
+    pub fn take(&mut self) -> Option<OwnedSemaphorePermit> {
+        self.permit.take()
+    }
+
+    /// stop waiting for the permit.
+    fn cancel(&mut self) {
+        *self.acquiring.get_mut().unwrap() = None;
+    }
+}
+
+/// The limit of the requests in flight, the permit is held until the body is sent.
+/// the requests beyond the limit wait in the bounded queue by `poll_ready`, the others
+/// are responded as `503 Service Unavailable` with `Retry-After`.
+#[derive(Clone)]
+pub struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    queue: usize,
+    waiting: Arc<AtomicUsize>,
+    retry_after: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// at most `max` requests in flight, the others are rejected without the queue.
+    pub fn new(max: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            queue: 0,
+            waiting: Default::default(),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    /// the services waiting for the permit by `poll_ready` at most.
+    pub fn queue(mut self, len: usize) -> Self {
+        self.queue = len;
+        self
+    }
+
+    /// the `Retry-After` of the rejected requests, the default is 1 second.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    pub(crate) fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0)
+    }
+}
+
+/// The permit of the request in flight acquired by `poll_ready`, or the request is shed
+/// if the queue is full.
+#[derive(Default)]
+pub(crate) struct InFlightPermit {
+    ready: ReadyPermit,
+    // the waiting count of the limit is taken by this service.
+    waiting: Option<Arc<AtomicUsize>>,
+    is_shed: bool,
+}
+
+impl Clone for InFlightPermit {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Drop for InFlightPermit {
+    fn drop(&mut self) {
+        self.leave_queue();
+    }
+}
+
+impl InFlightPermit {
+    fn leave_queue(&mut self) {
+        if let Some(waiting) = self.waiting.take() {
+            waiting.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// ready with the permit, or with the request to shed if the queue is full.
+    pub fn poll_acquire(
+        &mut self,
+        limit: &ConcurrencyLimit,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<()>> {
+        if self.is_shed {
+            return Poll::Ready(Ok(()));
+        }
+        if let Poll::Ready(rs) = self.ready.poll_acquire(&limit.semaphore, cx) {
+            self.leave_queue();
+            return Poll::Ready(rs);
+        }
+        if self.waiting.is_none() {
+            let queue = limit.queue;
+            let entered = limit
+                .waiting
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                    (n < queue).then_some(n + 1)
+                });
+            if entered.is_err() {
+                self.ready.cancel();
+                self.is_shed = true;
+                return Poll::Ready(Ok(()));
+            }
+            self.waiting = Some(limit.waiting.clone());
+        }
+        Poll::Pending
+    }
+
+    /// the permit of the call, `None` if the request is shed. the call without
+    /// `poll_ready` takes the available permit without waiting.
+    pub fn take(&mut self, limit: &ConcurrencyLimit) -> Option<OwnedSemaphorePermit> {
+        if std::mem::take(&mut self.is_shed) {
+            return None;
+        }
+        let permit = self.ready.take();
+        permit.or_else(|| limit.semaphore.clone().try_acquire_owned().ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::task::noop_waker_ref;
+
+    #[test]
+    fn test_ready_permit() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut a = ReadyPermit::default();
+        let mut b = a.clone();
+        assert!(a.poll_acquire(&semaphore, &mut cx).is_ready());
+        assert!(b.poll_acquire(&semaphore, &mut cx).is_pending());
+        drop(a.take());
+        assert!(b.poll_acquire(&semaphore, &mut cx).is_ready());
+        assert!(b.take().is_some());
+    }
+
+    #[test]
+    fn test_in_flight_permit() {
+        let limit = ConcurrencyLimit::new(1).queue(1);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut a = InFlightPermit::default();
+        let (mut b, mut c) = (a.clone(), a.clone());
+        assert!(a.poll_acquire(&limit, &mut cx).is_ready());
+        let permit = a.take(&limit).unwrap();
+        // b is queued, c is shed as the queue is full.
+        assert!(b.poll_acquire(&limit, &mut cx).is_pending());
+        assert!(c.poll_acquire(&limit, &mut cx).is_ready());
+        assert!(c.take(&limit).is_none());
+        drop(permit);
+        assert!(b.poll_acquire(&limit, &mut cx).is_ready());
+        assert_eq!(limit.waiting.load(Ordering::SeqCst), 0);
+        assert!(b.take(&limit).is_some());
+    }
+}
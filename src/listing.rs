@@ -0,0 +1,625 @@
+use std::{
+    fmt::Write,
+    io::Result,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{header, Request};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::{compression, file::BlockingFuture};
+
+/// the chars of the file name not encoded in the link, the others are percent encoded.
+pub(crate) const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// The entry of the directory listing.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl DirEntry {
+    /// the percent-encoded relative link of the entry, the directory link ends with `/`.
+    pub fn href(&self) -> String {
+        let href = utf8_percent_encode(&self.name, SEGMENT).to_string();
+        if self.is_dir {
+            href + "/"
+        } else {
+            href
+        }
+    }
+}
+
+/// The directory listing passed to the template.
+#[derive(Debug, Clone)]
+pub struct DirEntries {
+    /// the decoded request path of the directory, starts and ends with `/`.
+    pub path: String,
+    /// the entries of the current page.
+    pub entries: Vec<DirEntry>,
+    /// the current page, starts from 1.
+    pub page: usize,
+    /// the count of the pages, it is 1 if the listing is not paginated.
+    pub pages: usize,
+    /// the relative link of the previous page.
+    pub prev: Option<String>,
+    /// the relative link of the next page.
+    pub next: Option<String>,
+}
+
+impl DirEntries {
+    pub(crate) fn new(path: &str, entries: Vec<DirEntry>) -> Self {
+        let path = format!("/{}", path.trim_matches('/'));
+        let path = if path.len() > 1 { path + "/" } else { path };
+        Self {
+            path,
+            entries,
+            page: 1,
+            pages: 1,
+            prev: None,
+            next: None,
+        }
+    }
+
+    /// the directory is the root.
+    pub fn is_root(&self) -> bool {
+        self.path == "/"
+    }
+}
+
+/// The template renders the directory listing to the html page.
+pub type ListingTemplate = dyn Fn(&DirEntries) -> String + Send + Sync;
+
+/// The format of the directory listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListingFormat {
+    Html,
+    Json,
+}
+
+impl ListingFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ListingFormat::Html => "text/html; charset=utf-8",
+            ListingFormat::Json => "application/json",
+        }
+    }
+
+    /// the `format=json` or `format=html` query is preferred, otherwise the json is used
+    /// when the `Accept` prefers `application/json` to `text/html`.
+    pub fn from_request<B>(request: &Request<B>) -> Self {
+        let format = query_param(request, "format").map(|v| v.to_ascii_lowercase());
+        match format.as_deref() {
+            Some("json") => return ListingFormat::Json,
+            Some("html") => return ListingFormat::Html,
+            _ => {}
+        }
+        let accept = match request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(accept) => compression::parse_qvalues(accept),
+            None => return ListingFormat::Html,
+        };
+        let qvalue = |mime: &str| {
+            accept
+                .iter()
+                .find(|(m, _)| m == mime)
+                .map(|(_, q)| *q)
+                .unwrap_or(0.0)
+        };
+        let json = qvalue("application/json");
+        if json > 0.0 && json > qvalue("text/html") {
+            ListingFormat::Json
+        } else {
+            ListingFormat::Html
+        }
+    }
+}
+
+/// the decoded value of the query parameter, the `+` is decoded as space.
+fn query_param<B>(request: &Request<B>, name: &str) -> Option<String> {
+    request.uri().query()?.split('&').find_map(|kv| {
+        let (key, value) = kv.split_once('=').unwrap_or((kv, ""));
+        (key == name).then(|| {
+            let value = value.replace('+', " ");
+            percent_decode_str(&value).decode_utf8_lossy().into_owned()
+        })
+    })
+}
+
+/// The key to sort the directory listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+/// The sorting and filtering of the listing from the query,
+/// e.g. `?sort=size&order=desc&q=report`.
+#[derive(Debug, Clone)]
+pub(crate) struct ListingQuery {
+    sort: SortKey,
+    descending: bool,
+    // the lowercase name filter.
+    filter: Option<String>,
+    // the requested page, starts from 1.
+    page: usize,
+    per_page: Option<usize>,
+    // the raw query without the `page`, kept in the page links.
+    link_query: String,
+}
+
+impl ListingQuery {
+    pub fn from_request<B>(request: &Request<B>) -> Self {
+        let sort = match query_param(request, "sort").as_deref() {
+            Some("size") => SortKey::Size,
+            Some("mtime") => SortKey::Modified,
+            _ => SortKey::Name,
+        };
+        let descending = query_param(request, "order").as_deref() == Some("desc");
+        let filter = query_param(request, "q")
+            .filter(|q| !q.is_empty())
+            .map(|q| q.to_lowercase());
+        let page = query_param(request, "page")
+            .and_then(|p| p.parse().ok())
+            .filter(|p| *p > 0)
+            .unwrap_or(1);
+        let per_page = query_param(request, "per_page")
+            .and_then(|p| p.parse().ok())
+            .filter(|p| *p > 0);
+        let link_query = request
+            .uri()
+            .query()
+            .unwrap_or("")
+            .split('&')
+            .filter(|kv| !kv.is_empty() && !kv.starts_with("page="))
+            .collect::<Vec<_>>()
+            .join("&");
+        Self {
+            sort,
+            descending,
+            filter,
+            page,
+            per_page,
+            link_query,
+        }
+    }
+
+    fn page_link(&self, page: usize) -> String {
+        if self.link_query.is_empty() {
+            format!("?page={page}")
+        } else {
+            format!("?{}&page={page}", self.link_query)
+        }
+    }
+
+    /// filter, sort and paginate the entries to the listing, the `per_page` query is
+    /// limited by the `page_size`, all entries are listed if both are absent.
+    pub fn listing(
+        &self,
+        path: &str,
+        mut entries: Vec<DirEntry>,
+        page_size: Option<usize>,
+    ) -> DirEntries {
+        self.apply(&mut entries);
+        let per_page = match (self.per_page, page_size) {
+            (Some(per_page), Some(size)) => per_page.min(size),
+            (per_page, size) => match per_page.or(size) {
+                Some(per_page) => per_page,
+                None => return DirEntries::new(path, entries),
+            },
+        };
+        let pages = entries.len().div_ceil(per_page).max(1);
+        let page = self.page.min(pages);
+        let start = (page - 1) * per_page;
+        entries.truncate(start + per_page);
+        entries.drain(..start.min(entries.len()));
+        let mut listing = DirEntries::new(path, entries);
+        listing.page = page;
+        listing.pages = pages;
+        listing.prev = (page > 1).then(|| self.page_link(page - 1));
+        listing.next = (page < pages).then(|| self.page_link(page + 1));
+        listing
+    }
+
+    /// filter the entries by the name case-insensitively, then sort them,
+    /// the directories are always listed first.
+    fn apply(&self, entries: &mut Vec<DirEntry>) {
+        if let Some(ref filter) = self.filter {
+            entries.retain(|e| e.name.to_lowercase().contains(filter));
+        }
+        entries.sort_by(|a, b| {
+            let ordering = match self.sort {
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::Size => a.size.cmp(&b.size).then_with(|| a.name.cmp(&b.name)),
+                SortKey::Modified => a
+                    .modified
+                    .cmp(&b.modified)
+                    .then_with(|| a.name.cmp(&b.name)),
+            };
+            let ordering = if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            b.is_dir.cmp(&a.is_dir).then(ordering)
+        });
+    }
+}
+
+/// The future read the entries of the directory.
+pub(crate) type ReadDirFuture = BlockingFuture<Vec<DirEntry>>;
+
+/// read the entries of the directory, the entry with the non UTF-8 name is skipped.
+pub(crate) fn read_dir(path: PathBuf) -> ReadDirFuture {
+    BlockingFuture::spawn(move || -> Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&path)? {
+            let entry = entry?;
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            // follow the symbolic link, the broken link is listed as it is.
+            let meta = match std::fs::metadata(entry.path()) {
+                Ok(meta) => meta,
+                Err(_) => entry.metadata()?,
+            };
+            entries.push(DirEntry {
+                name,
+                is_dir: meta.is_dir(),
+                size: meta.len(),
+                modified: meta.modified().ok(),
+            });
+        }
+        Ok(entries)
+    })
+}
+
+/// escape the text in the html content and attribute.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// escape the string in the json string literal.
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                write!(&mut escaped, "\\u{:04x}", c as u32).expect("buffer write failed")
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn modified_text(modified: Option<SystemTime>) -> String {
+    modified
+        .filter(|m| m.duration_since(UNIX_EPOCH).is_ok())
+        .map(httpdate::fmt_http_date)
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// the count of the entries rendered in one chunk of the body.
+const ENTRIES_PER_CHUNK: usize = 256;
+
+/// The iterator renders the listing to the body chunks, so the large listing
+/// is not buffered in memory as a whole.
+pub(crate) struct ListingChunks {
+    listing: DirEntries,
+    format: ListingFormat,
+    // the position of the next entry to render, `None` if the head is not rendered.
+    pos: Option<usize>,
+    is_finished: bool,
+}
+
+impl ListingChunks {
+    pub fn new(listing: DirEntries, format: ListingFormat) -> Self {
+        Self {
+            listing,
+            format,
+            pos: None,
+            is_finished: false,
+        }
+    }
+
+    fn head(&self) -> String {
+        match self.format {
+            ListingFormat::Json => "[".to_string(),
+            ListingFormat::Html => {
+                let title = format!("Index of {}", escape_html(&self.listing.path));
+                let mut html = format!(
+                    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                     <title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<table>\n\
+                     <tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n"
+                );
+                if !self.listing.is_root() {
+                    html.push_str(
+                        "<tr><td><a href=\"../\">../</a></td><td>-</td><td>-</td></tr>\n",
+                    );
+                }
+                html
+            }
+        }
+    }
+
+    fn entries(&self, start: usize, end: usize) -> String {
+        let mut content = String::new();
+        for (i, entry) in self.listing.entries[start..end].iter().enumerate() {
+            match self.format {
+                ListingFormat::Json => {
+                    if start + i > 0 {
+                        content.push(',');
+                    }
+                    render_json_entry(&mut content, entry);
+                }
+                ListingFormat::Html => render_html_entry(&mut content, entry),
+            }
+        }
+        content
+    }
+
+    fn foot(&self) -> String {
+        match self.format {
+            ListingFormat::Json => "]".to_string(),
+            ListingFormat::Html => {
+                let mut html = String::from("</table>\n");
+                if self.listing.pages > 1 {
+                    html.push_str("<p>");
+                    if let Some(ref prev) = self.listing.prev {
+                        write!(&mut html, "<a href=\"{}\">prev</a> ", escape_html(prev))
+                            .expect("buffer write failed");
+                    }
+                    write!(
+                        &mut html,
+                        "page {} of {}",
+                        self.listing.page, self.listing.pages
+                    )
+                    .expect("buffer write failed");
+                    if let Some(ref next) = self.listing.next {
+                        write!(&mut html, " <a href=\"{}\">next</a>", escape_html(next))
+                            .expect("buffer write failed");
+                    }
+                    html.push_str("</p>\n");
+                }
+                html.push_str("</body>\n</html>\n");
+                html
+            }
+        }
+    }
+}
+
+impl Iterator for ListingChunks {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_finished {
+            return None;
+        }
+        let start = match self.pos {
+            Some(pos) => pos,
+            None => {
+                self.pos = Some(0);
+                return Some(self.head());
+            }
+        };
+        if start >= self.listing.entries.len() {
+            self.is_finished = true;
+            return Some(self.foot());
+        }
+        let end = (start + ENTRIES_PER_CHUNK).min(self.listing.entries.len());
+        self.pos = Some(end);
+        Some(self.entries(start, end))
+    }
+}
+
+fn render_html_entry(html: &mut String, entry: &DirEntry) {
+    let name = escape_html(&entry.name);
+    let (name, size) = if entry.is_dir {
+        (name + "/", "-".to_string())
+    } else {
+        (name, entry.size.to_string())
+    };
+    writeln!(
+        html,
+        "<tr><td><a href=\"{}\">{name}</a></td><td>{size}</td><td>{}</td></tr>",
+        escape_html(&entry.href()),
+        modified_text(entry.modified),
+    )
+    .expect("buffer write failed");
+}
+
+/// the `mtime` is the seconds since the unix epoch, or `null` if it is unknown.
+fn render_json_entry(json: &mut String, entry: &DirEntry) {
+    let mtime = entry
+        .modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_else(|| "null".to_string());
+    write!(
+        json,
+        "{{\"name\":\"{}\",\"type\":\"{}\",\"size\":{},\"mtime\":{mtime}}}",
+        escape_json(&entry.name),
+        if entry.is_dir { "directory" } else { "file" },
+        entry.size,
+    )
+    .expect("buffer write failed");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(name: &str, is_dir: bool) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            is_dir,
+            size: 12,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_entry_href() {
+        assert_eq!(entry("a b.txt", false).href(), "a%20b.txt");
+        assert_eq!(entry("docs", true).href(), "docs/");
+        assert_eq!(entry("a:b?#.txt", false).href(), "a%3Ab%3F%23.txt");
+        assert_eq!(entry("文件", false).href(), "%E6%96%87%E4%BB%B6");
+    }
+
+    fn render(listing: DirEntries, format: ListingFormat) -> String {
+        ListingChunks::new(listing, format).collect()
+    }
+
+    #[test]
+    fn test_render_html() {
+        let entries = vec![entry("<b>", true), entry("a&b.txt", false)];
+        let html = render(
+            DirEntries::new("x/\"y\"", entries.clone()),
+            ListingFormat::Html,
+        );
+        assert!(html.contains("<title>Index of /x/&quot;y&quot;/</title>"));
+        assert!(html.contains("<a href=\"../\">../</a>"));
+        assert!(html.contains("<a href=\"%3Cb%3E/\">&lt;b&gt;/</a></td><td>-</td>"));
+        assert!(html.contains("<a href=\"a%26b.txt\">a&amp;b.txt</a></td><td>12</td>"));
+        assert!(!render(DirEntries::new("", entries), ListingFormat::Html).contains("../"));
+    }
+
+    #[test]
+    fn test_render_json() {
+        let mut file = entry("a\"b\n.txt", false);
+        file.modified = Some(UNIX_EPOCH + std::time::Duration::from_secs(100));
+        let json = render(
+            DirEntries::new("", vec![entry("dir", true), file]),
+            ListingFormat::Json,
+        );
+        assert_eq!(
+            json,
+            r#"[{"name":"dir","type":"directory","size":12,"mtime":null},{"name":"a\"b\n.txt","type":"file","size":12,"mtime":100}]"#
+        );
+        assert_eq!(
+            render(DirEntries::new("", vec![]), ListingFormat::Json),
+            "[]"
+        );
+
+        let entries = (0..ENTRIES_PER_CHUNK * 2 + 1)
+            .map(|i| entry(&i.to_string(), false))
+            .collect();
+        let chunks = ListingChunks::new(DirEntries::new("", entries), ListingFormat::Json);
+        assert_eq!(chunks.count(), 5);
+    }
+
+    #[test]
+    fn test_listing_format() {
+        let format = |uri: &str, accept: Option<&str>| {
+            let mut r = Request::get(uri);
+            if let Some(accept) = accept {
+                r = r.header(header::ACCEPT, accept);
+            }
+            ListingFormat::from_request(&r.body(()).unwrap())
+        };
+        assert_eq!(format("/", None), ListingFormat::Html);
+        assert_eq!(format("/?format=json", None), ListingFormat::Json);
+        assert_eq!(format("/", Some("application/json")), ListingFormat::Json);
+        assert_eq!(
+            format("/", Some("text/html,application/json;q=0.9")),
+            ListingFormat::Html
+        );
+        assert_eq!(
+            format("/?format=html", Some("application/json")),
+            ListingFormat::Html
+        );
+    }
+
+    #[test]
+    fn test_listing_query() {
+        let apply = |uri: &str| {
+            let r = Request::get(uri).body(()).unwrap();
+            let mut entries = vec![
+                entry("b.txt", false),
+                entry("a", true),
+                entry("c.log", false),
+            ];
+            entries[0].size = 30;
+            entries[2].size = 20;
+            ListingQuery::from_request(&r).apply(&mut entries);
+            let names: Vec<_> = entries.into_iter().map(|e| e.name).collect();
+            names
+        };
+        assert_eq!(apply("/"), ["a", "b.txt", "c.log"]);
+        assert_eq!(apply("/?order=desc"), ["a", "c.log", "b.txt"]);
+        assert_eq!(apply("/?sort=size"), ["a", "c.log", "b.txt"]);
+        assert_eq!(apply("/?sort=size&order=desc"), ["a", "b.txt", "c.log"]);
+        assert_eq!(apply("/?q=TXT"), ["b.txt"]);
+        assert_eq!(apply("/?q=%2Elog"), ["c.log"]);
+    }
+
+    #[test]
+    fn test_listing_pagination() {
+        let listing = |uri: &str, page_size| {
+            let r = Request::get(uri).body(()).unwrap();
+            let entries = (0..5).map(|i| entry(&i.to_string(), false)).collect();
+            ListingQuery::from_request(&r).listing("a", entries, page_size)
+        };
+        let all = listing("/a/", None);
+        assert_eq!((all.entries.len(), all.pages), (5, 1));
+        assert!(all.next.is_none());
+
+        let page = listing("/a/?sort=name&page=2", Some(2));
+        let names: Vec<_> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["2", "3"]);
+        assert_eq!((page.page, page.pages), (2, 3));
+        assert_eq!(page.prev.as_deref(), Some("?sort=name&page=1"));
+        assert_eq!(page.next.as_deref(), Some("?sort=name&page=3"));
+
+        let page = listing("/a/?per_page=4&page=9", Some(3));
+        let names: Vec<_> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["3", "4"]);
+        assert!(page.next.is_none());
+
+        let page = listing("/a/?per_page=4", None);
+        assert_eq!((page.entries.len(), page.pages), (4, 2));
+    }
+
+    #[tokio::test]
+    async fn test_read_dir() {
+        let dir = std::env::temp_dir().join("blockless-read-dir");
+        std::fs::create_dir_all(dir.join("z")).unwrap();
+        std::fs::write(dir.join("a.txt"), b"hello").unwrap();
+        let mut entries = read_dir(dir).await.unwrap();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "z"]);
+        assert_eq!(entries[0].size, 5);
+        assert!(entries[1].is_dir);
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    future::Future,
+    io::Result,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use hyper::{service::Service, Request, Response};
+
+use crate::{
+    body::Body,
+    filesvr::FileService,
+    resolver::{Resolver, RootResolver},
+};
+
+/// The file service of the futures without `Send`, e.g. served by `hyper::server::conn`
+/// on the current thread runtime or in the `LocalSet`. the request body is never read,
+/// so it is not required to be `Send` or `Sync`.
+#[derive(Clone)]
+pub struct LocalFileService<R = RootResolver> {
+    service: FileService<R>,
+}
+
+impl LocalFileService {
+    pub fn new(local_root: impl Into<String>) -> Self {
+        FileService::new(local_root).into()
+    }
+}
+
+/// serve by the configured file service.
+impl<R> From<FileService<R>> for LocalFileService<R> {
+    fn from(service: FileService<R>) -> Self {
+        Self { service }
+    }
+}
+
+impl<B, R> Service<Request<B>> for LocalFileService<R>
+where
+    B: 'static,
+    R: Resolver,
+{
+    type Response = Response<Body>;
+
+    type Error = std::io::Error;
+
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Service::<Request<()>>::poll_ready(&mut self.service, cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        Box::pin(self.service.call(request.map(|_| ())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    #[tokio::test]
+    async fn test_local_file_service() {
+        let dir = std::env::temp_dir().join("blockless-local-svr");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), b"hello").unwrap();
+        let mut service = LocalFileService::new(dir.to_str().unwrap());
+        let local = tokio::task::LocalSet::new();
+        let resp = local
+            .run_until(async move {
+                // the body of `Rc` is neither `Send` nor `Sync`.
+                let req = Request::get("/a.txt").body(Rc::new(())).unwrap();
+                let resp = tokio::task::spawn_local(service.call(req)).await;
+                resp.unwrap().unwrap()
+            })
+            .await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+    }
+}
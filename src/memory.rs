@@ -0,0 +1,149 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use hyper::{body::Bytes, Method, Request};
+
+use crate::{
+    archive::Source,
+    file::{self, FileMeta},
+    request_resolve::{self, ResolveOptions, Resolved},
+};
+
+/// The file generated in memory, e.g. the runtime config `env.js`.
+#[derive(Clone)]
+pub struct MemoryFile {
+    content: Bytes,
+    content_type: Option<String>,
+    modified: SystemTime,
+}
+
+impl MemoryFile {
+    /// the file modified now, the content type is by the extension of the path.
+    pub fn new(content: impl Into<Bytes>) -> Self {
+        Self {
+            content: content.into(),
+            content_type: None,
+            modified: SystemTime::now(),
+        }
+    }
+
+    /// set the content type instead of the one by the extension.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// set the modification time, used by `Last-Modified` and the entity tag.
+    pub fn modified(mut self, modified: SystemTime) -> Self {
+        self.modified = modified;
+        self
+    }
+}
+
+/// The files in memory shadowing or supplementing the files under the root, the files
+/// are shared by the clones, so they can be replaced while the service is running.
+#[derive(Clone, Default)]
+pub struct MemoryFiles {
+    files: Arc<RwLock<HashMap<String, MemoryFile>>>,
+}
+
+impl MemoryFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add or replace the file of the path relative to the root, e.g. `env.js`,
+    /// the invalid path is ignored, e.g. the path has `..`.
+    pub fn insert(&self, path: &str, file: MemoryFile) {
+        if let Some(path) = request_resolve::normalize_path(path).filter(|p| !p.is_empty()) {
+            self.files.write().unwrap().insert(path, file);
+        }
+    }
+
+    pub fn remove(&self, path: &str) {
+        if let Some(path) = request_resolve::normalize_path(path) {
+            self.files.write().unwrap().remove(&path);
+        }
+    }
+
+    /// the meta of the file, the inode is the hash of the content.
+    pub(crate) fn find(&self, path: &str) -> Option<FileMeta> {
+        let files = self.files.read().unwrap();
+        let file = files.get(path)?;
+        let mut hasher = DefaultHasher::new();
+        file.content.hash(&mut hasher);
+        Some(FileMeta {
+            path: PathBuf::from(path),
+            size: file.content.len() as u64,
+            inode: hasher.finish(),
+            is_dir: false,
+            modified: Some(file.modified),
+            permisions: file::readonly(),
+            source: Some(Source::Memory {
+                content: file.content.clone(),
+                content_type: file.content_type.clone(),
+            }),
+        })
+    }
+
+    /// resolve the request path or the index file of the directory path, `None` if
+    /// the file is not in memory, which is resolved against the root.
+    pub(crate) fn resolve<B>(
+        &self,
+        request: &Request<B>,
+        options: &ResolveOptions,
+    ) -> Option<Resolved> {
+        let path = request_resolve::normalized_path(request)?;
+        if !options.methods.contains(request.method()) || options.reject(&path).is_some() {
+            return None;
+        }
+        let meta = match self.find(&path) {
+            Some(meta) => meta,
+            None if request.uri().path().ends_with('/') => {
+                let index = options.index_files.iter().find_map(|index| {
+                    let index = match path.as_str() {
+                        "" => index.clone(),
+                        path => format!("{path}/{index}"),
+                    };
+                    options
+                        .reject(&index)
+                        .is_none()
+                        .then(|| self.find(&index))?
+                });
+                index?
+            }
+            None => return None,
+        };
+        Some(match *request.method() {
+            Method::OPTIONS => Resolved::Options,
+            _ => Resolved::Found(meta),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_files() {
+        let files = MemoryFiles::new();
+        files.insert("/env.js", MemoryFile::new("window.env = {}"));
+        files.insert("../evil.js", MemoryFile::new("evil"));
+        let shared = files.clone();
+        shared.insert(
+            "docs/index.html",
+            MemoryFile::new("<html></html>").content_type("text/html; charset=utf-8"),
+        );
+        assert_eq!(files.find("env.js").unwrap().size, 15);
+        assert!(files.find("evil.js").is_none());
+        assert!(files.find("docs/index.html").is_some());
+        files.remove("env.js");
+        assert!(shared.find("env.js").is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use hyper::{header, Method, Request, Response, StatusCode};
+
+use crate::body::{Body, ObservedBytesStream};
+
+#[derive(Default)]
+struct Counters {
+    // the responses of the status classes `1xx` to `5xx`.
+    requests: [AtomicU64; 5],
+    sent_bytes: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    streams: AtomicU64,
+    open_files: AtomicU64,
+}
+
+/// The counters of the service rendered in the Prometheus text format, the counters are
+/// shared by the clones, e.g. by the services of the virtual hosts.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+    // the path of the metrics served by the service.
+    path: Option<String>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// serve the metrics at the path, e.g. `/metrics`, behind the prefix and the rewrite
+    /// rules. the rate limits and the authentication of the path are checked as the
+    /// files, e.g. by `basic_auth("metrics", auth)`.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// the response of the metrics if it is the request of the metrics path.
+    pub(crate) fn respond<B>(&self, request: &Request<B>) -> Option<Response<Body>> {
+        if self.path.as_deref() != Some(request.uri().path()) {
+            return None;
+        }
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "GET, HEAD")
+                .body(Body::Empty)
+                .ok();
+        }
+        let body = match request.method() {
+            &Method::HEAD => Body::Empty,
+            _ => Body::Bytes(Some(self.render().into())),
+        };
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(body)
+            .ok()
+    }
+
+    /// count the response, the bytes sent and the stream in flight until the body is
+    /// finished. the error is counted as `5xx`.
+    pub(crate) fn observe(&self, resp: Option<&mut Response<Body>>) {
+        let status = resp.as_ref().map_or(500, |r| r.status().as_u16());
+        let class = (status / 100).clamp(1, 5) as usize - 1;
+        self.counters.requests[class].fetch_add(1, Ordering::Relaxed);
+        let resp = match resp {
+            Some(resp) => resp,
+            None => return,
+        };
+        self.counters.streams.fetch_add(1, Ordering::Relaxed);
+        let counters = self.counters.clone();
+        let on_finish = move |sent, _| {
+            counters.sent_bytes.fetch_add(sent, Ordering::Relaxed);
+            counters.streams.fetch_sub(1, Ordering::Relaxed);
+        };
+        let body = std::mem::replace(resp.body_mut(), Body::Empty);
+        *resp.body_mut() = Body::Observed(ObservedBytesStream::new(body, Box::new(on_finish)));
+    }
+
+    /// count the file opened by the response until the body is dropped.
+    pub(crate) fn observe_open_file(&self, resp: &mut Response<Body>) {
+        self.counters.open_files.fetch_add(1, Ordering::Relaxed);
+        let counters = self.counters.clone();
+        let on_finish = move |_, _| {
+            counters.open_files.fetch_sub(1, Ordering::Relaxed);
+        };
+        let body = std::mem::replace(resp.body_mut(), Body::Empty);
+        *resp.body_mut() = Body::Observed(ObservedBytesStream::new(body, Box::new(on_finish)));
+    }
+
+    /// count the lookup of the content cache.
+    pub(crate) fn cache_lookup(&self, is_hit: bool) {
+        let counter = match is_hit {
+            true => &self.counters.cache_hits,
+            false => &self.counters.cache_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let c = &self.counters;
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, u64)]| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{name}{labels} {value}");
+            }
+        };
+        let requests: Vec<(String, u64)> = (0..5)
+            .map(|i| (format!("{{status=\"{}xx\"}}", i + 1), get(&c.requests[i])))
+            .collect();
+        let sample = |counter: &AtomicU64| [(String::new(), get(counter))];
+        metric(
+            "hyper_file_requests_total",
+            "counter",
+            "The responses by the status class.",
+            &requests,
+        );
+        metric(
+            "hyper_file_sent_bytes_total",
+            "counter",
+            "The bytes of the bodies sent.",
+            &sample(&c.sent_bytes),
+        );
+        metric(
+            "hyper_file_cache_hits_total",
+            "counter",
+            "The contents served from the cache.",
+            &sample(&c.cache_hits),
+        );
+        metric(
+            "hyper_file_cache_misses_total",
+            "counter",
+            "The cacheable contents read from the files.",
+            &sample(&c.cache_misses),
+        );
+        metric(
+            "hyper_file_streams_in_flight",
+            "gauge",
+            "The bodies being sent.",
+            &sample(&c.streams),
+        );
+        metric(
+            "hyper_file_open_files",
+            "gauge",
+            "The files opened by the bodies being sent.",
+            &sample(&c.open_files),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::StatusCode;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new().path("/metrics");
+        let mut resp = Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::Bytes(Some("missing".into())))
+            .unwrap();
+        metrics.observe(Some(&mut resp));
+        metrics.observe(None);
+        metrics.cache_lookup(true);
+        let rendered = metrics.render();
+        assert!(rendered.contains("hyper_file_requests_total{status=\"4xx\"} 1\n"));
+        assert!(rendered.contains("hyper_file_requests_total{status=\"5xx\"} 1\n"));
+        assert!(rendered.contains("hyper_file_streams_in_flight 1\n"));
+        assert!(rendered.contains("# TYPE hyper_file_cache_hits_total counter\n"));
+        drop(resp);
+        let rendered = metrics.render();
+        assert!(rendered.contains("hyper_file_sent_bytes_total 0\n"));
+        assert!(rendered.contains("hyper_file_streams_in_flight 0\n"));
+
+        let resp = metrics.respond(&Request::get("/metrics").body(()).unwrap());
+        assert_eq!(resp.unwrap().status(), StatusCode::OK);
+        assert!(metrics
+            .respond(&Request::get("/").body(()).unwrap())
+            .is_none());
+    }
+}
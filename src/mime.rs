@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// guess the content type from the extension of the file.
+pub(crate) fn guess_content_type(path: &Path) -> Option<String> {
+    mime_guess::from_path(path).first_raw().map(String::from)
+}
+
+/// The content types of the extensions, the overrides are layered over the built-in table.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct MimeTypes {
+    // the lowercase extension to content type.
+    overrides: HashMap<String, String>,
+    // the content type for the unknown extensions.
+    default: Option<String>,
+    // the charset parameter append to the text content types.
+    charset: Option<String>,
+}
+
+impl MimeTypes {
+    pub fn insert(&mut self, ext: &str, content_type: impl Into<String>) {
+        let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+        self.overrides.insert(ext, content_type.into());
+    }
+
+    pub fn set_default(&mut self, content_type: impl Into<String>) {
+        self.default = Some(content_type.into());
+    }
+
+    pub fn set_charset(&mut self, charset: impl Into<String>) {
+        self.charset = Some(charset.into());
+    }
+
+    /// append the charset parameter for `text/*` and `application/javascript`.
+    fn with_charset(&self, content_type: String) -> String {
+        let charset = match self.charset {
+            Some(ref charset) => charset,
+            None => return content_type,
+        };
+        let is_text = content_type.starts_with("text/") || content_type == "application/javascript";
+        if is_text && !content_type.contains(';') {
+            format!("{content_type}; charset={charset}")
+        } else {
+            content_type
+        }
+    }
+
+    pub fn content_type(&self, path: &Path) -> Option<String> {
+        let overridden = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.overrides.get(&ext.to_ascii_lowercase()));
+        overridden
+            .cloned()
+            .or_else(|| guess_content_type(path))
+            .or_else(|| self.default.clone())
+            .map(|content_type| self.with_charset(content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guess_content_type() {
+        let guess = |p: &str| guess_content_type(Path::new(p));
+        assert_eq!(guess("index.html").as_deref(), Some("text/html"));
+        assert_eq!(guess("a/b/style.css").as_deref(), Some("text/css"));
+        assert_eq!(guess("app.js").as_deref(), Some("text/javascript"));
+        assert_eq!(guess("app.wasm").as_deref(), Some("application/wasm"));
+        assert_eq!(guess("logo.SVG").as_deref(), Some("image/svg+xml"));
+        assert_eq!(guess("noext"), None);
+    }
+
+    #[test]
+    fn test_mime_types() {
+        let mut mime_types = MimeTypes::default();
+        mime_types.insert(".blockless", "application/x-blockless");
+        mime_types.insert("MCAP", "application/x-mcap");
+        mime_types.insert("js", "application/javascript");
+        let content_type = |m: &MimeTypes, p: &str| m.content_type(Path::new(p));
+        assert_eq!(
+            content_type(&mime_types, "a.blockless").as_deref(),
+            Some("application/x-blockless")
+        );
+        assert_eq!(
+            content_type(&mime_types, "a.mcap").as_deref(),
+            Some("application/x-mcap")
+        );
+        assert_eq!(
+            content_type(&mime_types, "app.js").as_deref(),
+            Some("application/javascript")
+        );
+        assert_eq!(
+            content_type(&mime_types, "a.css").as_deref(),
+            Some("text/css")
+        );
+        assert_eq!(content_type(&mime_types, "noext"), None);
+        mime_types.set_default("application/octet-stream");
+        assert_eq!(
+            content_type(&mime_types, "noext").as_deref(),
+            Some("application/octet-stream")
+        );
+    }
+
+    #[test]
+    fn test_charset() {
+        let mut mime_types = MimeTypes::default();
+        mime_types.insert("mjs", "application/javascript");
+        mime_types.insert("txt2", "text/plain; charset=latin1");
+        mime_types.set_charset("utf-8");
+        let content_type = |p: &str| mime_types.content_type(Path::new(p)).unwrap();
+        assert_eq!(content_type("index.html"), "text/html; charset=utf-8");
+        assert_eq!(
+            content_type("a.mjs"),
+            "application/javascript; charset=utf-8"
+        );
+        assert_eq!(content_type("a.txt2"), "text/plain; charset=latin1");
+        assert_eq!(content_type("a.png"), "image/png");
+    }
+}
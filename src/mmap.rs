@@ -0,0 +1,85 @@
+use std::{
+    fs::{File, Metadata},
+    io::{Error, Result},
+    os::unix::{fs::PermissionsExt, io::AsRawFd},
+    ptr::{self, NonNull},
+};
+
+use hyper::body::Bytes;
+
+/// The read-only map of the whole file, unmapped after all the slices are dropped.
+struct Mmap {
+    ptr: NonNull<libc::c_void>,
+    len: usize,
+}
+
+// SAFETY: the map is read-only and is not aliased mutably.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        // SAFETY: the `len` bytes are mapped until the map is dropped.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr() as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        // SAFETY: the pointer is mapped with the `len` by `map`.
+        unsafe { libc::munmap(self.ptr.as_ptr(), self.len) };
+    }
+}
+
+/// map the `len` bytes of the file as the shared bytes, the empty file can't be mapped.
+pub(crate) fn map(file: &File, len: usize) -> Result<Bytes> {
+    // SAFETY: the new read-only map doesn't overlap the existing memory.
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(Error::last_os_error());
+    }
+    let ptr = NonNull::new(ptr).ok_or_else(Error::last_os_error)?;
+    Ok(Bytes::from_owner(Mmap { ptr, len }))
+}
+
+/// the file is writable by the group or the others, which may truncate it under the map.
+/// the pages beyond the end of the truncated file raise `SIGBUS` when they are read.
+pub(crate) fn is_shared_writable(metadata: &Metadata) -> bool {
+    metadata.permissions().mode() & 0o022 != 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let path = std::env::temp_dir().join("blockless-mmap.txt");
+        std::fs::write(&path, b"hello world").unwrap();
+        let file = File::open(&path).unwrap();
+        let bytes = map(&file, 11).unwrap();
+        assert_eq!(&bytes.slice(6..)[..], b"world");
+    }
+
+    #[test]
+    fn test_is_shared_writable() {
+        let path = std::env::temp_dir().join("blockless-mmap-mode.txt");
+        std::fs::write(&path, b"hello").unwrap();
+        let mode = |mode| {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            is_shared_writable(&std::fs::metadata(&path).unwrap())
+        };
+        assert!(!mode(0o644));
+        assert!(mode(0o664));
+        assert!(mode(0o646));
+    }
+}
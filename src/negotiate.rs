@@ -0,0 +1,149 @@
+use crate::compression::{self, PRECOMPRESSED_ENCODINGS};
+
+/// The representation of the variant chosen by the content negotiation.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Variant {
+    // the language tag of the variant, e.g. `en` of `foo.html.en`.
+    pub language: Option<String>,
+    // the request header the variant is chosen by.
+    pub vary: Option<&'static str>,
+}
+
+/// the file extension is the language tag, e.g. `en` or `zh-CN`.
+/// the extensions of the precompressed files are not.
+pub(crate) fn is_language_tag(tag: &str) -> bool {
+    if PRECOMPRESSED_ENCODINGS.iter().any(|e| e.extension() == tag) {
+        return false;
+    }
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (1..=8).contains(&primary.len())
+        && primary.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags
+            .all(|s| (1..=8).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+/// the qvalue of the language tag and the index of the matched range, the range matches
+/// the tag equals to it or the prefix of it, e.g. `en` matches `en-US`, and vice versa.
+fn language_quality(ranges: &[(String, f32)], tag: &str) -> Option<(f32, usize)> {
+    let tag = tag.to_ascii_lowercase();
+    let is_matched = |range: &str| {
+        range == "*"
+            || range == tag
+            || tag.strip_prefix(range).is_some_and(|s| s.starts_with('-'))
+            || range.strip_prefix(&tag).is_some_and(|s| s.starts_with('-'))
+    };
+    // the specific range is preferred to the wildcard.
+    let (index, (_, q)) = ranges
+        .iter()
+        .enumerate()
+        .filter(|(_, (range, _))| is_matched(range))
+        .min_by_key(|(i, (range, _))| (range == "*", *i))?;
+    (*q > 0.0).then_some((*q, index))
+}
+
+/// choose the best language of the variants by the `Accept-Language` header,
+/// the earlier range is preferred if the qvalues are equal. the default language
+/// is chosen if there is no header or no language is acceptable.
+pub(crate) fn negotiate_language<'a>(
+    accept_language: Option<&str>,
+    languages: &'a [String],
+    default: Option<&str>,
+) -> Option<&'a str> {
+    let default = default.and_then(|d| languages.iter().find(|l| l.eq_ignore_ascii_case(d)));
+    let accept_language = match accept_language {
+        Some(accept_language) => accept_language,
+        None => return default.or(languages.first()).map(String::as_str),
+    };
+    let ranges = compression::parse_qvalues(accept_language);
+    languages
+        .iter()
+        .filter_map(|l| language_quality(&ranges, l).map(|rank| (l, rank)))
+        .min_by(|(_, a), (_, b)| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)))
+        .map(|(l, _)| l)
+        .or(default)
+        .map(String::as_str)
+}
+
+/// the qvalue of the content type and the specificity of the matched range,
+/// the most specific range is used, e.g. `text/html` is more specific than `text/*`.
+fn media_quality(ranges: &[(String, f32)], content_type: &str) -> Option<f32> {
+    let content_type = compression::essence(content_type);
+    let specificity = |range: &str| match range {
+        "*/*" => 0,
+        r if r.ends_with("/*") => 1,
+        _ => 2,
+    };
+    let (_, q) = ranges
+        .iter()
+        .map(|(range, q)| (compression::essence(range), q))
+        .filter(|(range, _)| compression::is_mime_matched(range, content_type))
+        .max_by_key(|(range, _)| specificity(range))?;
+    (*q > 0.0).then_some(*q)
+}
+
+/// choose the best content type of the variants by the `Accept` header, the earlier
+/// variant is preferred if the qvalues are equal. return the index of the variant.
+pub(crate) fn negotiate_media_type(
+    accept: Option<&str>,
+    content_types: &[String],
+) -> Option<usize> {
+    let accept = match accept {
+        Some(accept) => accept,
+        None => return (!content_types.is_empty()).then_some(0),
+    };
+    let ranges = compression::parse_qvalues(accept);
+    content_types
+        .iter()
+        .enumerate()
+        .filter_map(|(i, ct)| media_quality(&ranges, ct).map(|q| (i, q)))
+        .min_by(|(_, a), (_, b)| b.total_cmp(a))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_language_tag() {
+        assert!(is_language_tag("en"));
+        assert!(is_language_tag("zh-CN"));
+        assert!(!is_language_tag("br"));
+        assert!(!is_language_tag("html5"));
+        assert!(!is_language_tag("en-"));
+    }
+
+    #[test]
+    fn test_negotiate_language() {
+        let languages = vec!["de".to_string(), "en".to_string(), "zh-CN".to_string()];
+        let negotiate = |header, default| negotiate_language(header, &languages, default);
+        assert_eq!(negotiate(Some("en;q=0.5, de;q=0.9"), None), Some("de"));
+        assert_eq!(negotiate(Some("en-US, de"), None), Some("en"));
+        assert_eq!(negotiate(Some("zh"), None), Some("zh-CN"));
+        assert_eq!(negotiate(Some("*, en;q=0"), Some("en")), Some("de"));
+        assert_eq!(negotiate(Some("fr"), None), None);
+        assert_eq!(negotiate(Some("fr"), Some("EN")), Some("en"));
+        assert_eq!(negotiate(None, Some("en")), Some("en"));
+        assert_eq!(negotiate(None, None), Some("de"));
+    }
+
+    #[test]
+    fn test_negotiate_media_type() {
+        let content_types = vec![
+            "text/html".to_string(),
+            "application/json".to_string(),
+            "application/xml".to_string(),
+        ];
+        let negotiate = |header| negotiate_media_type(header, &content_types);
+        assert_eq!(negotiate(Some("application/json")), Some(1));
+        assert_eq!(
+            negotiate(Some("application/*;q=0.5, application/xml")),
+            Some(2)
+        );
+        assert_eq!(negotiate(Some("text/html;q=0.1, */*;q=0.8")), Some(1));
+        assert_eq!(negotiate(Some("*/*, text/html;q=0")), Some(1));
+        assert_eq!(negotiate(Some("image/png")), None);
+        assert_eq!(negotiate(None), Some(0));
+    }
+}
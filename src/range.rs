@@ -2,6 +2,10 @@ use crate::error::ParseError;
 
 const HEADER_PREFIX: &[u8] = b"bytes=";
 
+/// the ranges of the request at most after the overlapped ranges are merged, the request
+/// of more ranges is served as the whole file.
+pub const MAX_RANGES: usize = 16;
+
 #[derive(Debug, PartialEq)]
 pub struct HttpRange {
     pub start: u64,
@@ -10,6 +14,11 @@ pub struct HttpRange {
 type Result<T> = std::result::Result<T, ParseError>;
 
 impl HttpRange {
+    /// the last byte position of the range, inclusive.
+    pub fn end(&self) -> u64 {
+        self.start + self.length - 1
+    }
+
     pub fn parse(header: &str, file_size: u64) -> Result<Vec<HttpRange>> {
         Self::parse_bytes(header.as_bytes(), file_size)
     }
@@ -42,6 +51,26 @@ impl HttpRange {
         Ok(ranges)
     }
 
+    /// merge the overlapped or adjacent ranges in the order of the start, the ranges of
+    /// a single range are kept as is.
+    pub fn coalesce(mut ranges: Vec<HttpRange>) -> Vec<HttpRange> {
+        if ranges.len() < 2 {
+            return ranges;
+        }
+        ranges.sort_by_key(|r| r.start);
+        let mut merged: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end() + 1 => {
+                    let end = last.end().max(range.end());
+                    last.length = end - last.start + 1;
+                }
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
     fn parse_single_range(range: &[u8], file_size: u64) -> Result<Option<HttpRange>> {
         let mut split_range = range.splitn(2, |s| *s == b'-');
         let range_start = split_range.next().ok_or(ParseError::InvalidRange)?;
@@ -51,7 +80,7 @@ impl HttpRange {
                 return Err(ParseError::InvalidRange);
             }
             let mut length = range_end.to_u64().ok_or(ParseError::InvalidRange)?;
-            if length == 0 {
+            if length == 0 || file_size == 0 {
                 return Ok(None);
             }
             if length > file_size {
@@ -63,7 +92,7 @@ impl HttpRange {
             }))
         } else {
             let start = range_start.to_u64().ok_or(ParseError::InvalidRange)?;
-            if start > file_size {
+            if start >= file_size {
                 return Ok(None);
             }
             let length = if range_end.is_empty() {
@@ -218,4 +247,40 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_parse_no_overlap() {
+        test_error!("bytes=10-", 10, Err(ParseError::NoOverlap));
+        test_error!("bytes=20-30", 10, Err(ParseError::NoOverlap));
+        test_error!("bytes=-1", 0, Err(ParseError::NoOverlap));
+        test_error!("bytes=5-2", 10, Err(ParseError::InvalidRange));
+        test_range!(
+            "bytes=9-",
+            10,
+            vec![HttpRange {
+                start: 9,
+                length: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let ranges = HttpRange::parse("bytes=50-99,0-9,5-19,20-29,-10", 100).unwrap();
+        assert_eq!(
+            HttpRange::coalesce(ranges),
+            vec![
+                HttpRange {
+                    start: 0,
+                    length: 30
+                },
+                HttpRange {
+                    start: 50,
+                    length: 50
+                }
+            ]
+        );
+        let ranges = HttpRange::parse("bytes=5-9", 100).unwrap();
+        assert_eq!(HttpRange::coalesce(ranges).len(), 1);
+    }
 }
@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{header::HeaderName, Request};
+
+use crate::throttle::TokenBucket;
+
+/// the buckets of the idle clients are dropped if there are more clients.
+const MAX_IDLE_KEYS: usize = 10000;
+
+#[derive(Clone)]
+enum RateKey {
+    Ip,
+    Header(HeaderName),
+}
+
+/// The limit of the requests of every client, the client is keyed by the address or
+/// the header, e.g. the api key. the requests beyond the limit are responded as
+/// `429 Too Many Requests` with `Retry-After`. the requests without the key share one
+/// bucket.
+#[derive(Clone)]
+pub struct RateLimit {
+    requests: u64,
+    per: Duration,
+    key: RateKey,
+    buckets: Arc<Mutex<HashMap<Option<String>, TokenBucket>>>,
+}
+
+impl RateLimit {
+    /// the requests of the duration keyed by the client address, the burst is the
+    /// requests, e.g. `RateLimit::new(60, Duration::from_secs(60))`.
+    pub fn new(requests: u64, per: Duration) -> Self {
+        assert!(requests > 0 && !per.is_zero(), "the rate must be positive");
+        Self {
+            requests,
+            per,
+            key: RateKey::Ip,
+            buckets: Default::default(),
+        }
+    }
+
+    /// key the clients by the header instead of the address.
+    pub fn by_header(mut self, name: HeaderName) -> Self {
+        self.key = RateKey::Header(name);
+        self
+    }
+
+    /// take the token of the client, return the duration to retry if it is limited.
+    pub(crate) fn check<B>(&self, request: &Request<B>, ip: Option<IpAddr>) -> Option<Duration> {
+        let key = match self.key {
+            RateKey::Ip => ip.map(|ip| ip.to_string()),
+            RateKey::Header(ref name) => request
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from),
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_IDLE_KEYS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| !bucket.is_full());
+        }
+        let rate = self.requests as f64 / self.per.as_secs_f64();
+        let bucket = buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::with_capacity(rate, self.requests));
+        let wait = bucket.wait_time(1);
+        if wait.is_none() {
+            bucket.take(1);
+        }
+        wait
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let limit = RateLimit::new(2, Duration::from_secs(60));
+        let req = Request::new(());
+        let ip = |s: &str| Some(s.parse::<IpAddr>().unwrap());
+        assert!(limit.check(&req, ip("192.0.2.1")).is_none());
+        assert!(limit.check(&req, ip("192.0.2.1")).is_none());
+        let wait = limit.check(&req, ip("192.0.2.1")).unwrap();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+        assert!(limit.check(&req, ip("192.0.2.2")).is_none());
+
+        let limit = RateLimit::new(1, Duration::from_secs(1))
+            .by_header(HeaderName::from_static("x-api-key"));
+        let req = Request::get("/").header("x-api-key", "a").body(()).unwrap();
+        assert!(limit.check(&req, None).is_none());
+        assert!(limit.check(&req, None).is_some());
+        assert!(limit.check(&Request::new(()), None).is_none());
+    }
+}
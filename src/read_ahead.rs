@@ -0,0 +1,165 @@
+use std::{
+    cmp::min,
+    collections::VecDeque,
+    fs::File,
+    future::Future,
+    io::{Error, ErrorKind, Result, SeekFrom},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures_util::ready;
+use hyper::body::Bytes;
+
+use crate::file::{self, BlockingFuture};
+
+/// The reader issuing the reads of the next chunks before the chunk is sent,
+/// so the latency of the disk is hidden behind the network.
+pub(crate) struct ReadAhead {
+    file: Arc<File>,
+    // the chunks read in flight at most.
+    depth: usize,
+    size: u64,
+    // the position of the next chunk returned.
+    position: u64,
+    // the position of the next read issued.
+    next: u64,
+    // the reads in flight in order, with the length requested.
+    reads: VecDeque<(usize, BlockingFuture<Bytes>)>,
+    // the reads are issued to io_uring instead of the blocking pool.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    io_uring: bool,
+}
+
+impl ReadAhead {
+    pub fn new(file: File, size: u64, depth: usize) -> Self {
+        Self {
+            file: Arc::new(file),
+            depth: depth.max(1),
+            size,
+            position: 0,
+            next: 0,
+            reads: VecDeque::new(),
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            io_uring: false,
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.max(1);
+    }
+
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub fn set_io_uring(&mut self, enable: bool) {
+        self.io_uring = enable;
+    }
+
+    /// read the chunk at the offset, by the blocking pool if the ring is full.
+    fn read_at(&self, offset: u64, len: usize) -> BlockingFuture<Bytes> {
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if self.io_uring {
+            if let Some(read) = crate::uring::read(&self.file, offset, len) {
+                return read;
+            }
+        }
+        let file = self.file.clone();
+        BlockingFuture::spawn(move || {
+            let mut buf = vec![0u8; len];
+            let n = file::pread(&file, &mut buf, offset)?;
+            buf.truncate(n);
+            Ok(Bytes::from(buf))
+        })
+    }
+
+    /// issue the reads until the depth, the bytes beyond the end are not read.
+    fn issue(&mut self, end: u64, chunk_size: usize) {
+        while self.reads.len() < self.depth && self.next < end {
+            let len = min(chunk_size as u64, end - self.next) as usize;
+            let read = self.read_at(self.next, len);
+            self.reads.push_back((len, read));
+            self.next += len as u64;
+        }
+    }
+
+    /// read the next chunk of `readn` bytes at most, the file is read to the size of
+    /// the meta at most, which is the length of the response.
+    pub fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        readn: u64,
+        chunk_size: usize,
+    ) -> Poll<Result<Bytes>> {
+        let end = min(self.size, self.position.saturating_add(readn));
+        self.issue(end, chunk_size);
+        let (len, read) = match self.reads.front_mut() {
+            Some((len, read)) => (*len, read),
+            None => return Poll::Ready(Ok(Bytes::new())),
+        };
+        let chunk = ready!(Pin::new(read).poll(cx));
+        self.reads.pop_front();
+        let mut chunk = chunk?;
+        chunk.truncate(min(readn, len as u64) as usize);
+        // the file is shrunk, the reads after the end are dropped.
+        if chunk.len() < len {
+            self.reads.clear();
+            self.next = self.position + chunk.len() as u64;
+        }
+        self.position += chunk.len() as u64;
+        self.issue(end, chunk_size);
+        Poll::Ready(Ok(chunk))
+    }
+
+    /// seek to the position, the reads in flight are dropped.
+    pub fn start_seek(&mut self, position: SeekFrom) -> Result<()> {
+        let position = match position {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.size.checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        self.position =
+            position.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek position"))?;
+        self.next = self.position;
+        self.reads.clear();
+        Ok(())
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// the file without the reads in flight, e.g. to put it back to the cache.
+    pub fn into_file(mut self) -> Option<File> {
+        self.reads.clear();
+        Arc::try_unwrap(self.file).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::future::poll_fn;
+
+    #[tokio::test]
+    async fn test_read_ahead() {
+        let path = std::env::temp_dir().join("blockless-read-ahead.txt");
+        std::fs::write(&path, b"0123456789").unwrap();
+        let mut reader = ReadAhead::new(File::open(&path).unwrap(), 10, 2);
+        let mut chunks = Vec::new();
+        loop {
+            let chunk = poll_fn(|cx| reader.poll_read(cx, u64::MAX, 3)).await;
+            let chunk = chunk.unwrap();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks, ["012", "345", "678", "9"]);
+
+        reader.start_seek(SeekFrom::Start(4)).unwrap();
+        let chunk = poll_fn(|cx| reader.poll_read(cx, 2, 3)).await;
+        assert_eq!(chunk.unwrap(), "45");
+        assert_eq!(reader.position(), 6);
+        assert_eq!(reader.reads.len(), 0);
+    }
+}
@@ -0,0 +1,45 @@
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    Request,
+};
+
+use crate::sign;
+
+/// the longest request id accepted from the client or the proxy.
+const MAX_LEN: usize = 200;
+
+/// the request id of the header, the missing or the malformed id is replaced by the
+/// generated one, which is inserted into the request for the hooks and the logs.
+pub(crate) fn ensure<B>(request: &mut Request<B>, name: &HeaderName) -> HeaderValue {
+    let id = request.headers().get(name).filter(|id| {
+        let id = id.as_bytes();
+        // the visible ascii only, which can't forge the log lines.
+        !id.is_empty() && id.len() <= MAX_LEN && id.iter().all(|b| b.is_ascii_graphic())
+    });
+    if let Some(id) = id {
+        return id.clone();
+    }
+    let id = HeaderValue::from_str(&sign::random_hex()).expect("invalid request id");
+    request.headers_mut().insert(name.clone(), id.clone());
+    id
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ensure() {
+        let name = HeaderName::from_static("x-request-id");
+        let mut req = Request::get("/").header(&name, "abc-1").body(()).unwrap();
+        assert_eq!(ensure(&mut req, &name), "abc-1");
+
+        let mut req = Request::get("/").header(&name, "a b").body(()).unwrap();
+        let id = ensure(&mut req, &name);
+        assert_eq!(id.len(), 32);
+        assert_eq!(req.headers()[&name], id);
+
+        let mut req = Request::new(());
+        assert_eq!(ensure(&mut req, &name), req.headers()[&name]);
+    }
+}
@@ -1,22 +1,104 @@
 use hyper::{Method, Request};
+use std::collections::VecDeque;
 use std::future::Future;
 use std::io::{ErrorKind, Result};
 use std::task::{Context, Poll};
-use std::{path::PathBuf, pin::Pin};
+use std::{
+    path::{Component, Path},
+    pin::Pin,
+};
 
-use crate::file::{FileReaderOpener, FileWithMeta, FileWithMetaFuture, TokioFileReaderOpener};
+use crate::{
+    file::FileMeta,
+    glob::Glob,
+    signed_url::SignedUrl,
+    vfs::{Vfs, VfsFuture},
+};
+/// The result of resolving the request path against the root.
 #[derive(Debug)]
 pub enum Resolved {
+    /// the request path is invalid, e.g. the invalid percent-encoded sequences.
+    BadRequest,
+    /// the path is not found, hidden or denied by the rules.
     NotFound,
-    IsDirectory,
+    /// the directory without the index file.
+    IsDirectory(FileMeta),
+    /// the method is not allowed.
     MethodNotMatched,
+    /// the path is forbidden by the rules or the filesystem.
     PermissionDenied,
-    Found(FileWithMeta),
+    /// the `OPTIONS` request of the existing path.
+    Options,
+    /// redirect to the canonical location, e.g. the directory with the trailing slash.
+    Redirect(String),
+    /// the file is found.
+    Found(FileMeta),
+}
+
+/// The options to resolve the request path.
+pub(crate) struct ResolveOptions<'a> {
+    pub methods: &'a [Method],
+    // the path has the component starts with `.` is not found.
+    pub hide_hidden: bool,
+    // the path matched is not found, or the permission denied if the flag is true.
+    pub deny: &'a [(Glob, bool)],
+    // only the path matched is allowed if it is not empty.
+    pub allow: &'a [Glob],
+    // the index files of the directory in order.
+    pub index_files: &'a [String],
+    // redirect the directory without the trailing slash.
+    pub redirect_directory: bool,
+    // redirect the file with the trailing slash.
+    pub strip_trailing_slash: bool,
+    // the directory without the index file is listed.
+    pub autoindex: bool,
+    // the candidates tried in order if the path is not found, `$uri` is the request path.
+    pub try_files: &'a [String],
+    // the paths matched are served by the signed urls only.
+    pub signed_urls: &'a [(Glob, SignedUrl)],
+}
+
+impl ResolveOptions<'_> {
+    /// check the normalized path by the options before the filesystem access.
+    pub fn reject(&self, path: &str) -> Option<Resolved> {
+        if self.hide_hidden && path.split('/').any(|s| s.starts_with('.')) {
+            return Some(Resolved::NotFound);
+        }
+        if let Some((_, is_forbidden)) = self.deny.iter().find(|(g, _)| g.is_match(path)) {
+            return Some(if *is_forbidden {
+                Resolved::PermissionDenied
+            } else {
+                Resolved::NotFound
+            });
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|g| g.is_match(path)) {
+            return Some(Resolved::NotFound);
+        }
+        None
+    }
 }
 
 pub(crate) struct RequestResolve {
-    opener_future: FileWithMetaFuture,
+    vfs: Vfs,
+    // `None` if the request is resolved without the filesystem access.
+    meta_future: Option<VfsFuture<FileMeta>>,
+    // the result resolved without the filesystem access.
+    rejected: Option<Resolved>,
+    // the meta of the requested directory, which is looking for the index file.
+    dir_meta: Option<FileMeta>,
+    // the relative paths of the index files to look for.
+    index_candidates: VecDeque<String>,
+    // the paths tried in order if the request path is not found.
+    try_candidates: VecDeque<String>,
+    // the candidate of `try_candidates` is looking for.
+    is_trying: bool,
+    // the redirect location if the directory is served.
+    dir_location: Option<String>,
+    // the redirect location if the file is served.
+    file_location: Option<String>,
     is_method_match: bool,
+    is_autoindex: bool,
+    is_options: bool,
 }
 
 fn decode_percents(string: &str) -> String {
@@ -25,19 +107,139 @@ fn decode_percents(string: &str) -> String {
         .into_owned()
 }
 
-impl RequestResolve {
-    pub fn resolve<B>(path: impl Into<PathBuf>, r: &Request<B>) -> Self {
-        let opener = TokioFileReaderOpener::new(path);
-        let mut uri_path = r.uri().path();
-        if uri_path.starts_with('/') {
-            uri_path = &uri_path[1..];
+/// decode the percent-encoded string, return `None` if the `%` is not followed
+/// by two hex digits or the decoded bytes are not valid UTF-8.
+fn decode_percents_strict(string: &str) -> Option<String> {
+    let bytes = string.as_bytes();
+    let is_valid = bytes.iter().enumerate().all(|(i, b)| {
+        *b != b'%'
+            || (i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit())
+    });
+    if !is_valid {
+        return None;
+    }
+    percent_encoding::percent_decode_str(string)
+        .decode_utf8()
+        .ok()
+        .map(|s| s.into_owned())
+}
+
+/// normalize the decoded path to the relative path under the root, the empty and `.`
+/// segments are removed. return `None` if the path has `..`, NUL, backslash or
+/// the segment is not a normal file name, e.g. the drive prefix `C:` on windows.
+pub(crate) fn normalize_path(path: &str) -> Option<String> {
+    if path.contains(['\0', '\\']) {
+        return None;
+    }
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            _ => {
+                let mut components = Path::new(segment).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(_)), None) => segments.push(segment),
+                    _ => return None,
+                }
+            }
         }
-        let opener_future = opener.open(decode_percents(uri_path));
-        let is_method_match = matches!(*r.method(), Method::GET | Method::HEAD);
-        RequestResolve {
-            opener_future,
+    }
+    Some(segments.join("/"))
+}
+
+fn uri_path<B>(r: &Request<B>) -> &str {
+    let uri_path = r.uri().path();
+    uri_path.strip_prefix('/').unwrap_or(uri_path)
+}
+
+/// the location of the request with the path, the query is kept.
+/// the leading `/`s are merged, so the location can't be the other host, e.g. `//evil.com`.
+pub(crate) fn location<B>(r: &Request<B>, path: &str) -> String {
+    let path = path.trim_start_matches('/');
+    match r.uri().query() {
+        Some(query) => format!("/{path}?{query}"),
+        None => format!("/{path}"),
+    }
+}
+
+/// the decoded and normalized request path, `None` if the path is invalid.
+pub(crate) fn normalized_path<B>(r: &Request<B>) -> Option<String> {
+    decode_percents_strict(uri_path(r)).and_then(|p| normalize_path(&p))
+}
+
+/// the decoded request path without the leading `/`, the invalid sequences are replaced,
+/// which is used to match the path patterns.
+pub(crate) fn request_path<B>(r: &Request<B>) -> String {
+    decode_percents(uri_path(r))
+}
+
+impl RequestResolve {
+    /// resolve the request path against the root of the virtual filesystem.
+    pub fn resolve<B>(vfs: Vfs, r: &Request<B>, options: &ResolveOptions) -> Self {
+        let is_method_match = options.methods.contains(r.method());
+        let is_options = r.method() == Method::OPTIONS;
+        let mut resolve = RequestResolve {
+            vfs,
+            meta_future: None,
+            rejected: None,
+            dir_meta: None,
+            index_candidates: VecDeque::new(),
+            try_candidates: VecDeque::new(),
+            is_trying: false,
+            dir_location: None,
+            file_location: None,
             is_method_match,
+            is_autoindex: options.autoindex,
+            is_options,
+        };
+        let path = match normalized_path(r) {
+            Some(p) => p,
+            None => {
+                resolve.rejected = Some(Resolved::BadRequest);
+                return resolve;
+            }
+        };
+        if let Some(rejected) = options.reject(&path) {
+            resolve.rejected = Some(rejected);
+            return resolve;
+        }
+        let signed_url = options.signed_urls.iter().find(|(g, _)| g.is_match(&path));
+        if signed_url.is_some_and(|(_, signer)| !signer.verify(&path, r.uri().query())) {
+            resolve.rejected = Some(Resolved::PermissionDenied);
+            return resolve;
         }
+        // the index files are checked by the options too.
+        resolve.index_candidates = options
+            .index_files
+            .iter()
+            .map(|index| {
+                if path.is_empty() {
+                    index.clone()
+                } else {
+                    format!("{path}/{index}")
+                }
+            })
+            .filter(|p| options.reject(p).is_none())
+            .collect();
+        resolve.try_candidates = options
+            .try_files
+            .iter()
+            .filter_map(|pattern| normalize_path(&pattern.replace("$uri", &path)))
+            .filter(|p| *p != path && options.reject(p).is_none())
+            .collect();
+        if !path.is_empty() {
+            let raw_path = r.uri().path();
+            if !raw_path.ends_with('/') && options.redirect_directory {
+                resolve.dir_location = Some(location(r, &format!("{raw_path}/")));
+            } else if raw_path.ends_with('/') && options.strip_trailing_slash {
+                resolve.file_location = Some(location(r, raw_path.trim_end_matches('/')));
+            }
+        }
+        resolve.meta_future = Some(resolve.vfs.metadata(&path));
+        resolve
     }
 }
 
@@ -46,29 +248,190 @@ impl Future for RequestResolve {
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let Self {
-            ref mut opener_future,
+            ref vfs,
+            ref mut meta_future,
+            ref mut rejected,
+            ref mut dir_meta,
+            ref mut index_candidates,
+            ref mut try_candidates,
+            ref mut is_trying,
+            ref mut dir_location,
+            ref mut file_location,
             is_method_match,
+            is_autoindex,
+            is_options,
         } = *self;
         if !is_method_match {
             return Poll::Ready(Ok(Resolved::MethodNotMatched));
         }
-        let file_with_meta = match Pin::new(opener_future).poll(cx) {
-            Poll::Ready(Ok(r)) => r,
-            Poll::Ready(Err(e)) => {
-                let rs = match e.kind() {
-                    ErrorKind::NotFound => Ok(Resolved::NotFound),
-                    ErrorKind::PermissionDenied => Ok(Resolved::PermissionDenied),
-                    _ => Err(e),
-                };
-
-                return Poll::Ready(rs);
+        loop {
+            let future = match meta_future {
+                Some(f) => f.as_mut(),
+                None => return Poll::Ready(Ok(rejected.take().unwrap_or(Resolved::NotFound))),
+            };
+            let meta = match future.poll(cx) {
+                Poll::Ready(Ok(r)) => Some(r),
+                // the index file is not found, try the next one.
+                Poll::Ready(Err(e)) if dir_meta.is_some() && e.kind() == ErrorKind::NotFound => {
+                    None
+                }
+                // the request path or the candidate is not found, try the next candidate.
+                Poll::Ready(Err(e))
+                    if e.kind() == ErrorKind::NotFound && !try_candidates.is_empty() =>
+                {
+                    *is_trying = true;
+                    *meta_future = try_candidates.pop_front().map(|p| vfs.metadata(&p));
+                    continue;
+                }
+                Poll::Ready(Err(e)) => {
+                    let rs = match e.kind() {
+                        ErrorKind::NotFound => Ok(Resolved::NotFound),
+                        ErrorKind::PermissionDenied => Ok(Resolved::PermissionDenied),
+                        _ => Err(e),
+                    };
+
+                    return Poll::Ready(rs);
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Some(meta) = meta {
+                if is_options {
+                    return Poll::Ready(Ok(Resolved::Options));
+                }
+                // the candidate is served without the redirect, the directory is skipped.
+                if *is_trying {
+                    if !meta.is_dir {
+                        return Poll::Ready(Ok(Resolved::Found(meta)));
+                    }
+                    match try_candidates.pop_front() {
+                        Some(p) => *meta_future = Some(vfs.metadata(&p)),
+                        None => return Poll::Ready(Ok(Resolved::NotFound)),
+                    }
+                    continue;
+                }
+                if !meta.is_dir {
+                    let location = match dir_meta {
+                        Some(_) => dir_location.take(),
+                        None => file_location.take(),
+                    };
+                    return match location {
+                        Some(location) => Poll::Ready(Ok(Resolved::Redirect(location))),
+                        None => Poll::Ready(Ok(Resolved::Found(meta))),
+                    };
+                }
+                // the directory is requested, or the index file is a directory.
+                if dir_meta.is_none() {
+                    *dir_meta = Some(meta);
+                }
             }
-            Poll::Pending => return Poll::Pending,
+            match index_candidates.pop_front() {
+                Some(index) => *meta_future = Some(vfs.metadata(&index)),
+                None => {
+                    let meta = dir_meta.take().unwrap();
+                    // the listing has the relative links too.
+                    return match dir_location.take().filter(|_| is_autoindex) {
+                        Some(location) => Poll::Ready(Ok(Resolved::Redirect(location))),
+                        None => Poll::Ready(Ok(Resolved::IsDirectory(meta))),
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_percents_strict() {
+        assert_eq!(decode_percents_strict("a%20b.txt").unwrap(), "a b.txt");
+        assert_eq!(
+            decode_percents_strict("%E6%96%87%E4%BB%B6.txt").unwrap(),
+            "文件.txt"
+        );
+        assert_eq!(decode_percents_strict("100%25").unwrap(), "100%");
+        assert!(decode_percents_strict("a%2").is_none());
+        assert!(decode_percents_strict("a%zz").is_none());
+        assert!(decode_percents_strict("%FF%FE").is_none());
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("a//b/./c.txt").unwrap(), "a/b/c.txt");
+        assert_eq!(normalize_path("/etc/passwd").unwrap(), "etc/passwd");
+        assert_eq!(normalize_path("").unwrap(), "");
+        assert!(normalize_path("../etc/passwd").is_none());
+        assert!(normalize_path("a/../../b").is_none());
+        assert!(normalize_path("a\\..\\b").is_none());
+        assert!(normalize_path("a.txt\0.png").is_none());
+    }
+
+    #[test]
+    fn test_traversal_payloads() {
+        let resolve = |uri: &str| {
+            let r = Request::get(uri).body(()).unwrap();
+            decode_percents_strict(uri_path(&r)).and_then(|p| normalize_path(&p))
         };
-        if file_with_meta.is_dir {
-            Poll::Ready(Ok(Resolved::IsDirectory))
-        } else {
-            Poll::Ready(Ok(Resolved::Found(file_with_meta)))
+        let payloads = [
+            "/../etc/passwd",
+            "/a/../../etc/passwd",
+            "/%2e%2e/etc/passwd",
+            "/%2E%2E%2F%2E%2E%2Fetc%2Fpasswd",
+            "/.%2e/etc/passwd",
+            "/..%2fetc/passwd",
+            "/..%5cetc%5cpasswd",
+            "/%5c..%5cetc",
+            "/a.txt%00.png",
+            "/%c0%ae%c0%ae/etc/passwd",
+            "/%e0%80%ae%e0%80%ae/etc/passwd",
+            "/%c0%af..%c0%afetc",
+            "/%252e%252e/etc/passwd",
+        ];
+        for payload in payloads {
+            let path = resolve(payload);
+            assert!(
+                path.as_deref().map(|p| !p.contains("..")).unwrap_or(true),
+                "{payload} resolved to {path:?}"
+            );
         }
+        assert_eq!(resolve("//etc/passwd").unwrap(), "etc/passwd");
+    }
+
+    #[test]
+    fn test_location() {
+        let r = Request::get("/a/b?x=1").body(()).unwrap();
+        assert_eq!(location(&r, "/a/b/"), "/a/b/?x=1");
+        let r = Request::get("//evil.com").body(()).unwrap();
+        assert_eq!(location(&r, "//evil.com/"), "/evil.com/");
+    }
+
+    #[test]
+    fn test_reject() {
+        let deny = [
+            (Glob::new("**/*.key"), false),
+            (Glob::new("private/**"), true),
+        ];
+        let allow = [Glob::new("**/*.txt"), Glob::new("**/*.key")];
+        let options = ResolveOptions {
+            methods: &[],
+            hide_hidden: false,
+            deny: &deny,
+            allow: &allow,
+            index_files: &[],
+            redirect_directory: false,
+            strip_trailing_slash: false,
+            autoindex: false,
+            try_files: &[],
+            signed_urls: &[],
+        };
+        let reject = |path| options.reject(path);
+        assert!(matches!(reject("a/server.key"), Some(Resolved::NotFound)));
+        assert!(matches!(
+            reject("private/a.txt"),
+            Some(Resolved::PermissionDenied)
+        ));
+        assert!(matches!(reject("a.html"), Some(Resolved::NotFound)));
+        assert!(reject("public/a.txt").is_none());
     }
 }
@@ -0,0 +1,25 @@
+use std::{future::Future, io::Result, pin::Pin};
+
+use hyper::Request;
+
+use crate::request_resolve::Resolved;
+
+/// The future of the resolver.
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<Resolved>>> + Send + 'a>>;
+
+/// The resolver looks up the request before the root of the service,
+/// e.g. the alias table, the database or the per-tenant roots.
+pub trait Resolver: Clone + Send + Sync + 'static {
+    /// resolve the request, `None` to resolve the request path against the root.
+    fn resolve<'a, B: Sync>(&'a self, request: &'a Request<B>) -> ResolveFuture<'a>;
+}
+
+/// The default resolver, every request is resolved against the root.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RootResolver;
+
+impl Resolver for RootResolver {
+    fn resolve<'a, B: Sync>(&'a self, _request: &'a Request<B>) -> ResolveFuture<'a> {
+        Box::pin(async { Ok(None) })
+    }
+}
@@ -1,17 +1,30 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use hyper::{header, http::Result, HeaderMap, Method, Request, Response, StatusCode};
+use hyper::{
+    header, http::response::Builder, http::Result, HeaderMap, Method, Request, Response, StatusCode,
+};
 
 use crate::{
-    body::{Body, FileBytesStream, MultiRangeBytesStream, RangeBytesStream},
-    file::FileWithMeta,
-    range::HttpRange,
+    body::{Body, CompressedBytesStream, FileBytesStream, MultiRangeBytesStream, RangeBytesStream},
+    compression::{Encoder, Encoding},
+    error::ParseError,
+    etag::{self, EntityTag},
+    file::{FileMeta, FileWithMeta, TokioFileReader, DEFAULT_CHUNK_SIZE},
+    range::{HttpRange, MAX_RANGES},
+    security::{CspNonce, NONCE_PLACEHOLDER},
+    sign,
 };
 
 const VALID_MTIME: Duration = Duration::from_secs(2);
 const BOUNDARY_LEN: u64 = 35;
 const BOUNDARY_CHRS: &[u8] = b"abcdefghghijkmlnopqrstuvwxyz0123456789ABCDEFGHGHIJKMLNOPQRSTUVWXYZ";
 
+fn secs_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct ResponseBuilder {
     //range from request.
@@ -20,6 +33,41 @@ pub struct ResponseBuilder {
     if_modified_since: Option<SystemTime>,
     // `If-Range` request header.
     if_range: Option<String>,
+    // `If-None-Match` request header.
+    if_none_match: Option<String>,
+    // `If-Match` request header.
+    if_match: Option<String>,
+    // `If-Unmodified-Since` request header.
+    if_unmodified_since: Option<SystemTime>,
+    // the entity tag of the file.
+    etag: Option<EntityTag>,
+    // the content type of the file.
+    content_type: Option<String>,
+    // `Content-Disposition` response header.
+    content_disposition: Option<String>,
+    // `Content-Language` response header.
+    content_language: Option<String>,
+    // the content encoding to compress the body.
+    encoding: Option<Encoding>,
+    // the level of the encoder.
+    compression_level: Option<u32>,
+    // the content encoding of the precompressed file.
+    precompressed: Option<Encoding>,
+    // the request headers the response varies by.
+    vary: Vec<String>,
+    // `Cache-Control` response header.
+    cache_control: Option<String>,
+    // the static headers added to the response.
+    headers: HeaderMap,
+    // `Content-Security-Policy` response header with the nonce placeholders.
+    content_security_policy: Option<String>,
+    // the size of the chunk read from the file.
+    chunk_size: Option<usize>,
+    // the chunks read ahead of the chunk sent.
+    read_ahead: usize,
+    // read the file by io_uring.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    io_uring: bool,
     is_head_method: bool,
 }
 
@@ -30,12 +78,11 @@ impl ResponseBuilder {
 
     fn random_boundary() -> String {
         let duration = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
-        let mut rnd = duration.map(|d| d.as_secs()).unwrap();
+        let rnd = duration.map(|d| d.as_secs()).unwrap();
         let mut boundary_buf = vec![0u8; (BOUNDARY_LEN + 10) as _];
-        for iter in boundary_buf.iter_mut().skip(10) {
+        for (rnd, iter) in (rnd..).zip(boundary_buf.iter_mut().skip(10)) {
             let idx = (rnd % BOUNDARY_LEN) as usize;
             *iter = BOUNDARY_CHRS[idx];
-            rnd += 1;
         }
         boundary_buf[0..10].copy_from_slice(b"blockless:");
         String::from_utf8(boundary_buf).unwrap()
@@ -46,6 +93,105 @@ impl ResponseBuilder {
         self
     }
 
+    pub fn etag(&mut self, etag: Option<EntityTag>) -> &mut Self {
+        self.etag = etag;
+        self
+    }
+
+    pub fn content_type(&mut self, content_type: Option<String>) -> &mut Self {
+        self.content_type = content_type;
+        self
+    }
+
+    pub fn content_disposition(&mut self, content_disposition: Option<String>) -> &mut Self {
+        self.content_disposition = content_disposition;
+        self
+    }
+
+    pub fn content_language(&mut self, content_language: Option<String>) -> &mut Self {
+        self.content_language = content_language;
+        self
+    }
+
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// read the chunks ahead of the chunk sent, the default is `0`.
+    pub fn read_ahead(&mut self, depth: usize) -> &mut Self {
+        self.read_ahead = depth;
+        self
+    }
+
+    /// read the file by io_uring, the default is `false`.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub fn io_uring(&mut self, enable: bool) -> &mut Self {
+        self.io_uring = enable;
+        self
+    }
+
+    fn reader(&self, file: FileWithMeta) -> TokioFileReader {
+        let reader = TokioFileReader::new(file, self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE))
+            .read_ahead(self.read_ahead);
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        let reader = reader.io_uring(self.io_uring);
+        reader
+    }
+
+    /// compress the full content with the encoding, the range is not compressed.
+    pub fn encoding(&mut self, encoding: Option<Encoding>) -> &mut Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// the level to compress the content, the encoder default is used if `None`.
+    pub fn compression_level(&mut self, level: Option<u32>) -> &mut Self {
+        self.compression_level = level;
+        self
+    }
+
+    /// the file is precompressed with the encoding, send it as is.
+    pub fn precompressed(&mut self, precompressed: Option<Encoding>) -> &mut Self {
+        self.precompressed = precompressed;
+        self
+    }
+
+    pub fn cache_control(&mut self, cache_control: Option<String>) -> &mut Self {
+        self.cache_control = cache_control;
+        self
+    }
+
+    /// the `Content-Security-Policy`, the `{nonce}` is substituted by the nonce of
+    /// every response.
+    pub fn content_security_policy(&mut self, policy: Option<String>) -> &mut Self {
+        self.content_security_policy = policy;
+        self
+    }
+
+    /// add the static headers to the response, the `Vary` values are merged.
+    pub fn headers(&mut self, headers: HeaderMap) -> &mut Self {
+        for (name, value) in headers.iter() {
+            if name == header::VARY {
+                value.to_str().unwrap_or("").split(',').for_each(|v| {
+                    self.vary(v);
+                });
+            } else {
+                self.headers.append(name.clone(), value.clone());
+            }
+        }
+        self
+    }
+
+    /// the response varies by the request header, the duplicated name is ignored.
+    pub fn vary(&mut self, name: &str) -> &mut Self {
+        let name = name.trim();
+        if !name.is_empty() && !self.vary.iter().any(|v| v.eq_ignore_ascii_case(name)) {
+            self.vary.push(name.to_string());
+        }
+        self
+    }
+
     pub fn request<B>(&mut self, req: &Request<B>) -> &mut Self {
         self.request_headers(req.headers());
         self.is_head_method(req.method());
@@ -56,6 +202,9 @@ impl ResponseBuilder {
         self.range_header(headers.get(header::RANGE));
         self.if_modified_since_header(headers.get(header::IF_MODIFIED_SINCE));
         self.if_range_header(headers.get(header::IF_RANGE));
+        self.if_none_match_header(headers.get(header::IF_NONE_MATCH));
+        self.if_match_header(headers.get(header::IF_MATCH));
+        self.if_unmodified_since_header(headers.get(header::IF_UNMODIFIED_SINCE));
         self
     }
 
@@ -71,70 +220,237 @@ impl ResponseBuilder {
         self
     }
 
+    fn if_none_match_header(&mut self, value: Option<&header::HeaderValue>) -> &mut Self {
+        self.if_none_match = value.and_then(|v| v.to_str().ok()).map(String::from);
+        self
+    }
+
+    fn if_match_header(&mut self, value: Option<&header::HeaderValue>) -> &mut Self {
+        self.if_match = value.and_then(|v| v.to_str().ok()).map(String::from);
+        self
+    }
+
+    fn if_unmodified_since_header(&mut self, value: Option<&header::HeaderValue>) -> &mut Self {
+        self.if_unmodified_since = value
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| httpdate::parse_http_date(v).ok());
+        self
+    }
+
     fn is_head_method(&mut self, method: &Method) -> &mut Self {
         self.is_head_method = method == Method::HEAD;
         self
     }
 
+    /// check the `If-Match` or `If-Unmodified-Since`, the `If-Unmodified-Since` is
+    /// ignored when the `If-Match` is present.
+    fn is_precondition_failed(
+        &self,
+        etag: Option<&EntityTag>,
+        modified: Option<SystemTime>,
+    ) -> bool {
+        if let Some(ref if_match) = self.if_match {
+            return !etag::is_tag_list_matched(if_match, etag, false);
+        }
+        match (modified, self.if_unmodified_since) {
+            (Some(modified), Some(ius)) => secs_since_epoch(modified) > secs_since_epoch(ius),
+            _ => false,
+        }
+    }
+
+    /// check the `If-None-Match` or `If-Modified-Since`, the `If-Modified-Since` is
+    /// ignored when the `If-None-Match` is present.
+    fn is_not_modified(&self, etag: Option<&EntityTag>, modified: Option<SystemTime>) -> bool {
+        if let Some(ref if_none_match) = self.if_none_match {
+            return etag::is_tag_list_matched(if_none_match, etag, true);
+        }
+        match (modified, self.if_modified_since) {
+            (Some(modified), Some(ims)) => secs_since_epoch(modified) <= secs_since_epoch(ims),
+            _ => false,
+        }
+    }
+
+    /// check the `If-Range` validator, the range is only applied when it is matched,
+    /// otherwise the full content should be sent.
+    fn is_if_range_matched(&self, etag: Option<&EntityTag>, modified: Option<SystemTime>) -> bool {
+        let if_range = match self.if_range {
+            Some(ref if_range) => if_range,
+            None => return true,
+        };
+        if let Some(tag) = EntityTag::parse(if_range) {
+            return etag.map(|etag| tag.strong_eq(etag)).unwrap_or(false);
+        }
+        match httpdate::parse_http_date(if_range) {
+            Ok(date) => modified
+                .map(|m| secs_since_epoch(m) == secs_since_epoch(date))
+                .unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
     fn content_range_header(range: &HttpRange, file_size: u64) -> String {
-        format!("bytes {}-{}/{}", range.start, range.length, file_size)
+        format!("bytes {}-{}/{}", range.start, range.end(), file_size)
     }
 
-    pub fn build(&self, file: FileWithMeta) -> Result<Response<Body>> {
-        let file_size = file.size;
-        let mut resp_builder = Response::builder();
-        let modified = file.modified.filter(|m| {
+    /// the mtime of the file, the invalid mtime is ignored.
+    fn modified(meta: &FileMeta) -> Option<SystemTime> {
+        meta.modified.filter(|m| {
             m.duration_since(UNIX_EPOCH)
                 .ok()
                 .filter(|d| d >= &VALID_MTIME)
                 .is_some()
-        });
-        if let Some(modified) = modified {
-            if let Ok(unix_time) = modified.duration_since(UNIX_EPOCH) {
-                let ims_unix_time = self.if_modified_since.map(|t| t.duration_since(UNIX_EPOCH));
-                if let Some(Ok(ims_unix_time)) = ims_unix_time {
-                    if unix_time.as_secs() <= ims_unix_time.as_secs() {
-                        return resp_builder
-                            .status(StatusCode::NOT_MODIFIED)
-                            .body(Body::Empty);
-                    }
-                }
+        })
+    }
+
+    /// the entity tag of the representation, the compressed representation has its own tag.
+    fn representation_etag(&self) -> Option<EntityTag> {
+        let etag = self.etag.as_ref()?;
+        match self.encoding {
+            Some(encoding) => Some(EntityTag::new(
+                etag.weak,
+                format!("{}-{}", etag.tag, encoding.as_str()),
+            )),
+            None => Some(etag.clone()),
+        }
+    }
+
+    /// the value of `Vary` header, `*` means the response varies by others than headers.
+    fn vary_header(&self) -> Option<String> {
+        if self.vary.iter().any(|v| v == "*") {
+            return Some("*".to_string());
+        }
+        (!self.vary.is_empty()).then(|| self.vary.join(", "))
+    }
+
+    /// set the validator headers `ETag`, `Last-Modified` and the `Vary`, `Cache-Control`,
+    /// which are required by the `304 Not Modified` too.
+    fn validator_headers(&self, meta: &FileMeta, mut resp_builder: Builder) -> Builder {
+        if let Some(vary) = self.vary_header() {
+            resp_builder = resp_builder.header(header::VARY, vary);
+        }
+        if let Some(ref cache_control) = self.cache_control {
+            resp_builder = resp_builder.header(header::CACHE_CONTROL, cache_control);
+        }
+        for (name, value) in self.headers.iter() {
+            resp_builder = resp_builder.header(name, value);
+        }
+        if let Some(ref policy) = self.content_security_policy {
+            if policy.contains(NONCE_PLACEHOLDER) {
+                let nonce = sign::random_hex();
+                let policy = policy.replace(NONCE_PLACEHOLDER, &nonce);
+                resp_builder = resp_builder
+                    .header(header::CONTENT_SECURITY_POLICY, policy)
+                    .extension(CspNonce(nonce));
+            } else {
+                resp_builder = resp_builder.header(header::CONTENT_SECURITY_POLICY, policy);
             }
+        }
+        if let Some(etag) = self.representation_etag() {
+            resp_builder = resp_builder.header(header::ETAG, etag.to_string());
+        }
+        if let Some(modified) = Self::modified(meta) {
             let last_modified = httpdate::fmt_http_date(modified);
             resp_builder = resp_builder.header(header::LAST_MODIFIED, last_modified);
-            resp_builder = resp_builder.header(header::ACCEPT_RANGES, "bytes");
-        }
-        let ranges = self.range.as_ref().map(|s| HttpRange::parse(s, file_size));
-        if self.is_head_method {
-            resp_builder = resp_builder.header(header::CONTENT_LENGTH, format!("{}", file_size));
-            return resp_builder.status(StatusCode::OK).body(Body::Empty);
-        }
-        if let Some(ranges) = ranges {
-            let ranges = match ranges {
-                Ok(r) => r,
-                Err(_) => {
-                    return resp_builder
-                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
-                        .body(Body::Empty)
-                }
+        }
+        resp_builder
+    }
+
+    /// set the representation headers `Content-Type`, `Content-Disposition`, `Content-Language` and
+    /// the `Content-Encoding` of the precompressed file.
+    fn representation_headers(&self, mut resp_builder: Builder) -> Builder {
+        if let Some(precompressed) = self.precompressed {
+            resp_builder = resp_builder.header(header::CONTENT_ENCODING, precompressed.as_str());
+        }
+        if let Some(ref content_type) = self.content_type {
+            resp_builder = resp_builder.header(header::CONTENT_TYPE, content_type);
+        }
+        if let Some(ref content_disposition) = self.content_disposition {
+            resp_builder = resp_builder.header(header::CONTENT_DISPOSITION, content_disposition);
+        }
+        if let Some(ref content_language) = self.content_language {
+            resp_builder = resp_builder.header(header::CONTENT_LANGUAGE, content_language);
+        }
+        resp_builder
+    }
+
+    /// build the response which is no need to open the file, e.g. `304 Not Modified` or `HEAD`.
+    /// return `None` if the file content should be sent.
+    /// the preconditions are evaluated in the order of RFC 9110 section 13.2.2.
+    pub fn build_without_file(&self, meta: &FileMeta) -> Option<Result<Response<Body>>> {
+        let etag = self.representation_etag();
+        let etag = etag.as_ref();
+        let modified = Self::modified(meta);
+        let status = if self.is_precondition_failed(etag, modified) {
+            StatusCode::PRECONDITION_FAILED
+        } else if self.is_not_modified(etag, modified) {
+            StatusCode::NOT_MODIFIED
+        } else if self.is_head_method {
+            // the same headers as GET, the range is not defined for HEAD.
+            let resp_builder = self
+                .representation_headers(self.validator_headers(meta, Response::builder()))
+                .header(header::ACCEPT_RANGES, "bytes");
+            let resp_builder = match self.encoding {
+                Some(encoding) => resp_builder.header(header::CONTENT_ENCODING, encoding.as_str()),
+                None => resp_builder.header(header::CONTENT_LENGTH, meta.size),
             };
+            return Some(resp_builder.status(StatusCode::OK).body(Body::Empty));
+        } else {
+            return None;
+        };
+        let resp_builder = self.validator_headers(meta, Response::builder());
+        Some(resp_builder.status(status).body(Body::Empty))
+    }
+
+    pub fn build(&self, file: FileWithMeta) -> Result<Response<Body>> {
+        if let Some(resp) = self.build_without_file(&file.meta) {
+            return resp;
+        }
+        let file_size = file.meta.size;
+        let etag = self.representation_etag();
+        let etag = etag.as_ref();
+        let modified = Self::modified(&file.meta);
+        let mut resp_builder = self.validator_headers(&file.meta, Response::builder());
+        resp_builder = resp_builder.header(header::ACCEPT_RANGES, "bytes");
+        let ranges = self
+            .range
+            .as_ref()
+            .filter(|_| self.is_if_range_matched(etag, modified))
+            .map(|s| HttpRange::parse(s, file_size));
+        // the invalid range is ignored, and the whole file is served.
+        let ranges = match ranges {
+            Some(Err(ParseError::NoOverlap)) => {
+                return resp_builder
+                    .header(header::CONTENT_RANGE, format!("bytes */{file_size}"))
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .body(Body::Empty)
+            }
+            Some(Ok(ranges)) => Some(HttpRange::coalesce(ranges)),
+            _ => None,
+        };
+        // too many ranges are served as the whole file too, which is cheaper to send.
+        if let Some(ranges) = ranges.filter(|r| r.len() <= MAX_RANGES) {
             let ranges_len = ranges.len();
-            
+
             #[allow(clippy::comparison_chain)]
             if ranges_len == 1 {
                 let range = &ranges[0];
-                let content_range_header = Self::content_range_header(range, file.size);
-                resp_builder = resp_builder
+                let content_range_header = Self::content_range_header(range, file_size);
+                resp_builder = self
+                    .representation_headers(resp_builder)
                     .header(header::CONTENT_RANGE, content_range_header)
-                    .header(header::CONTENT_LENGTH, file_size);
-                let stream = RangeBytesStream::new_with_range(file.into(), range);
+                    .header(header::CONTENT_LENGTH, range.length);
+                let stream = RangeBytesStream::new_with_range(self.reader(file), range);
                 return resp_builder
                     .status(StatusCode::PARTIAL_CONTENT)
                     .body(Body::RangeBytesStream(stream));
             } else if ranges_len > 1 {
                 let boundary = Self::random_boundary();
                 let content_type = format!("multipart/byteranges; boundary={}", &boundary);
-                let stream = MultiRangeBytesStream::new(file.into(), ranges, boundary, file_size);
+                let mut stream =
+                    MultiRangeBytesStream::new(self.reader(file), ranges, boundary, file_size);
+                if let Some(ref content_type) = self.content_type {
+                    stream.set_content_type(content_type.clone());
+                }
                 resp_builder = resp_builder
                     .header(header::CONTENT_TYPE, content_type)
                     .header(header::CONTENT_LENGTH, stream.compute_body_len());
@@ -143,8 +459,401 @@ impl ResponseBuilder {
                     .body(Body::MultiRangeBytesStream(stream));
             }
         }
+        resp_builder = self.representation_headers(resp_builder);
+        let stream = FileBytesStream::new_with_limited(self.reader(file), file_size);
+        // fallback to the identity content if the encoder is failed to create.
+        let encoder = self.encoding.and_then(|encoding| {
+            Encoder::new(encoding, self.compression_level)
+                .ok()
+                .map(|e| (encoding, e))
+        });
+        if let Some((encoding, encoder)) = encoder {
+            // the length of compressed body is unknown, send by chunked.
+            let stream = CompressedBytesStream::new(stream, encoder);
+            return resp_builder
+                .header(header::CONTENT_ENCODING, encoding.as_str())
+                .status(StatusCode::OK)
+                .body(Body::Compressed(stream));
+        }
         resp_builder = resp_builder.header(header::CONTENT_LENGTH, file_size);
-        let stream = FileBytesStream::new_with_limited(file.into(), file_size);
         resp_builder.status(StatusCode::OK).body(Body::Full(stream))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::file::{FileReaderOpener, TokioFileReaderOpener};
+
+    const CONTENT: &[u8] = b"0123456789abcdefghij";
+
+    async fn open_test_file(name: &str) -> FileWithMeta {
+        open_test_file_with(name, CONTENT).await
+    }
+
+    async fn open_test_file_with(name: &str, content: &[u8]) -> FileWithMeta {
+        let dir = std::env::temp_dir();
+        std::fs::write(dir.join(name), content).unwrap();
+        let opener = TokioFileReaderOpener::new(dir);
+        let meta = opener.metadata(name).await.unwrap();
+        opener.open(&meta).await.unwrap()
+    }
+
+    fn request(range: &str) -> Request<()> {
+        request_with_headers(&[(header::RANGE, range)])
+    }
+
+    fn request_with_headers(headers: &[(header::HeaderName, &str)]) -> Request<()> {
+        let mut builder = Request::builder();
+        for (name, value) in headers {
+            builder = builder.header(name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_single_range() {
+        let file = open_test_file("blockless-single-range.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request("bytes=2-5"))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 2-5/20");
+        assert_eq!(headers[header::CONTENT_LENGTH], "4");
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"2345");
+    }
+
+    #[tokio::test]
+    async fn test_suffix_range() {
+        let file = open_test_file("blockless-suffix-range.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request("bytes=-3"))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes 17-19/20");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hij");
+    }
+
+    #[tokio::test]
+    async fn test_multi_range() {
+        let file = open_test_file("blockless-multi-range.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request("bytes=0-1, 10-12"))
+            .content_type(Some("text/plain".into()))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = resp.headers()[header::CONTENT_TYPE].to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let content_length: usize = resp.headers()[header::CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body.len(), content_length);
+        let expected = format!(
+            "--{boundary}\r\nContent-Range: bytes 0-1/20\r\nContent-Type: text/plain\r\n\r\n01\r\n\
+            --{boundary}\r\nContent-Range: bytes 10-12/20\r\nContent-Type: text/plain\r\n\r\nabc\r\n\
+            --{boundary}--\r\n"
+        );
+        assert_eq!(&body[..], expected.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_if_range() {
+        let file = open_test_file("blockless-if-range.txt").await;
+        let last_modified = httpdate::fmt_http_date(file.meta.modified.unwrap());
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, &last_modified),
+            ]))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+
+        let file = open_test_file("blockless-if-range.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ]))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "20");
+
+        let file = open_test_file("blockless-if-range.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, "\"stale\""),
+            ]))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_range_not_satisfiable() {
+        for range in ["bytes=20-", "bytes=30-40", "bytes=-0"] {
+            let file = open_test_file("blockless-not-satisfiable.txt").await;
+            let resp = ResponseBuilder::new()
+                .request(&request(range))
+                .build(file)
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE, "{range}");
+            assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes */20");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_ignored() {
+        let many = (0..20)
+            .map(|i| format!("{}-{}", i, i))
+            .collect::<Vec<_>>()
+            .join(",");
+        for range in ["bytes=5-2", "items=0-5", "bytes=a-b"] {
+            let file = open_test_file("blockless-range-ignored.txt").await;
+            let resp = ResponseBuilder::new()
+                .request(&request(range))
+                .build(file)
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK, "{range}");
+            assert_eq!(resp.headers()[header::CONTENT_LENGTH], "20");
+        }
+        // the ranges beyond the limit are not merged.
+        let sparse = (0..=MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 2, i * 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        let file = open_test_file_with("blockless-range-sparse.txt", &[b'a'; 40]).await;
+        let resp = ResponseBuilder::new()
+            .request(&request(&format!("bytes={sparse}")))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "40");
+        // the adjacent ranges are merged into one.
+        let file = open_test_file("blockless-range-ignored.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request(&format!("bytes={many}")))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes 0-19/20");
+    }
+
+    #[tokio::test]
+    async fn test_range_empty_file() {
+        for range in ["bytes=0-", "bytes=-5", "bytes=0-0"] {
+            let file = open_test_file_with("blockless-empty-range.txt", b"").await;
+            let resp = ResponseBuilder::new()
+                .request(&request(range))
+                .build(file)
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE, "{range}");
+            assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes */0");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_suffix_range_larger_than_file() {
+        let file = open_test_file("blockless-large-suffix.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request("bytes=-100"))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes 0-19/20");
+        assert_eq!(resp.headers()[header::CONTENT_LENGTH], "20");
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match() {
+        let file = open_test_file("blockless-if-none-match.txt").await;
+        let etag = EntityTag::from_meta(&file.meta);
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[]))
+            .etag(Some(etag.clone()))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::ETAG], etag.to_string().as_str());
+
+        let file = open_test_file("blockless-if-none-match.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[(
+                header::IF_NONE_MATCH,
+                &etag.to_string(),
+            )]))
+            .etag(Some(etag.clone()))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[header::ETAG], etag.to_string().as_str());
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(body.is_empty());
+
+        // If-None-Match takes precedence over If-Modified-Since.
+        let file = open_test_file("blockless-if-none-match.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[
+                (header::IF_NONE_MATCH, "\"other\""),
+                (header::IF_MODIFIED_SINCE, "Fri, 01 Jan 2100 00:00:00 GMT"),
+            ]))
+            .etag(Some(etag))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_if_range_etag() {
+        let strong = EntityTag::new(false, "strong");
+        let file = open_test_file("blockless-if-range-etag.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, "\"strong\""),
+            ]))
+            .etag(Some(strong))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+
+        // the weak entity tag is never matched with If-Range.
+        let weak = EntityTag::new(true, "weak");
+        let file = open_test_file("blockless-if-range-etag.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[
+                (header::RANGE, "bytes=0-1"),
+                (header::IF_RANGE, "W/\"weak\""),
+            ]))
+            .etag(Some(weak))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_without_file() {
+        let file = open_test_file("blockless-without-file.txt").await;
+        let last_modified = httpdate::fmt_http_date(file.meta.modified.unwrap());
+        let mut builder = ResponseBuilder::new();
+        builder.request(&request_with_headers(&[]));
+        assert!(builder.build_without_file(&file.meta).is_none());
+
+        builder.request(&request_with_headers(&[(
+            header::IF_MODIFIED_SINCE,
+            &last_modified,
+        )]));
+        let resp = builder.build_without_file(&file.meta).unwrap().unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            resp.headers()[header::LAST_MODIFIED],
+            last_modified.as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preconditions() {
+        let file = open_test_file("blockless-preconditions.txt").await;
+        let etag = EntityTag::new(false, "v1");
+        let last_modified = httpdate::fmt_http_date(file.meta.modified.unwrap());
+        let status = |headers: &[(header::HeaderName, &str)]| {
+            let mut builder = ResponseBuilder::new();
+            builder
+                .request(&request_with_headers(headers))
+                .etag(Some(etag.clone()));
+            builder
+                .build_without_file(&file.meta)
+                .map(|r| r.unwrap().status())
+        };
+        assert_eq!(status(&[(header::IF_MATCH, "\"v1\"")]), None);
+        assert_eq!(status(&[(header::IF_MATCH, "*")]), None);
+        assert_eq!(
+            status(&[(header::IF_MATCH, "\"v0\", W/\"v1\"")]),
+            Some(StatusCode::PRECONDITION_FAILED)
+        );
+        assert_eq!(
+            status(&[(header::IF_UNMODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:10 GMT")]),
+            Some(StatusCode::PRECONDITION_FAILED)
+        );
+        assert_eq!(
+            status(&[(header::IF_UNMODIFIED_SINCE, &last_modified)]),
+            None
+        );
+        // If-Unmodified-Since is ignored when If-Match is present.
+        assert_eq!(
+            status(&[
+                (header::IF_MATCH, "\"v1\""),
+                (header::IF_UNMODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:10 GMT"),
+            ]),
+            None
+        );
+        // If-Match is evaluated before If-None-Match.
+        assert_eq!(
+            status(&[
+                (header::IF_MATCH, "\"v0\""),
+                (header::IF_NONE_MATCH, "\"v1\""),
+            ]),
+            Some(StatusCode::PRECONDITION_FAILED)
+        );
+        assert_eq!(
+            status(&[
+                (header::IF_MATCH, "\"v1\""),
+                (header::IF_NONE_MATCH, "\"v1\""),
+            ]),
+            Some(StatusCode::NOT_MODIFIED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gzip() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let file = open_test_file("blockless-gzip.txt").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[]))
+            .etag(Some(EntityTag::new(false, "v1")))
+            .encoding(Some(Encoding::Gzip))
+            .build(file)
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(resp.headers()[header::ETAG], "\"v1-gzip\"");
+        assert!(!resp.headers().contains_key(header::CONTENT_LENGTH));
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let mut decoded = Vec::new();
+        GzDecoder::new(&body[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_content_security_policy() {
+        let file = open_test_file("blockless-csp.html").await;
+        let resp = ResponseBuilder::new()
+            .request(&request_with_headers(&[]))
+            .content_security_policy(Some("script-src 'nonce-{nonce}'".into()))
+            .build(file)
+            .unwrap();
+        let nonce = resp.extensions().get::<CspNonce>().unwrap();
+        let policy = format!("script-src 'nonce-{}'", nonce.0);
+        assert_eq!(
+            resp.headers()[header::CONTENT_SECURITY_POLICY],
+            policy.as_str()
+        );
+    }
+}
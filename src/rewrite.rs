@@ -0,0 +1,121 @@
+use hyper::{StatusCode, Uri};
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Exact(String),
+    Regex(Regex),
+}
+
+/// The rule rewrites the request path before resolving, the path is matched with
+/// the leading `/`. the query of the request is kept unless the replacement has one.
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    matcher: Matcher,
+    replacement: String,
+    // redirect with the status instead of the internal rewrite.
+    redirect: Option<StatusCode>,
+}
+
+/// The result of the rewrite rules.
+#[derive(Debug)]
+pub(crate) enum Rewritten {
+    // rewrite the uri internally.
+    Uri(Uri),
+    // redirect to the location.
+    Redirect(StatusCode, String),
+}
+
+impl RewriteRule {
+    /// rewrite the path equals to `from`, e.g. `/old.html` to `/new.html`.
+    pub fn exact(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            matcher: Matcher::Exact(from.into()),
+            replacement: to.into(),
+            redirect: None,
+        }
+    }
+
+    /// rewrite the path matched the regex, the replacement can refer the captures,
+    /// e.g. `^/blog/(\d+)$` to `/posts/$1.html`.
+    ///
+    /// # Panics
+    ///
+    /// panic if the pattern is invalid regex.
+    pub fn regex(pattern: &str, replacement: impl Into<String>) -> Self {
+        let regex = Regex::new(pattern).expect("invalid rewrite pattern");
+        Self {
+            matcher: Matcher::Regex(regex),
+            replacement: replacement.into(),
+            redirect: None,
+        }
+    }
+
+    /// redirect to the replacement with the status, e.g. `301` or `308`,
+    /// instead of the internal rewrite.
+    pub fn redirect(mut self, status: StatusCode) -> Self {
+        self.redirect = Some(status);
+        self
+    }
+
+    fn replace(&self, path: &str) -> Option<String> {
+        match self.matcher {
+            Matcher::Exact(ref from) => (from == path).then(|| self.replacement.clone()),
+            Matcher::Regex(ref regex) => regex
+                .is_match(path)
+                .then(|| regex.replace(path, self.replacement.as_str()).into_owned()),
+        }
+    }
+
+    /// apply the first matched rule to the uri, `None` if no rule is matched.
+    pub(crate) fn apply(rules: &[RewriteRule], uri: &Uri) -> Option<Rewritten> {
+        let (rule, replaced) = rules
+            .iter()
+            .find_map(|rule| rule.replace(uri.path()).map(|r| (rule, r)))?;
+        let replaced = match uri.query() {
+            Some(query) if !replaced.contains('?') => format!("{replaced}?{query}"),
+            _ => replaced,
+        };
+        match rule.redirect {
+            Some(status) => Some(Rewritten::Redirect(status, replaced)),
+            None => {
+                let mut parts = uri.clone().into_parts();
+                let path_and_query = format!("/{}", replaced.trim_start_matches('/'));
+                parts.path_and_query = Some(path_and_query.parse().ok()?);
+                Uri::from_parts(parts).ok().map(Rewritten::Uri)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let rules = [
+            RewriteRule::exact("/old.html", "/new.html"),
+            RewriteRule::regex(r"^/blog/(\d+)$", "/posts/$1.html"),
+            RewriteRule::regex(r"^/legacy/(.*)$", "/v2/$1?from=legacy")
+                .redirect(StatusCode::PERMANENT_REDIRECT),
+        ];
+        let apply = |uri: &str| RewriteRule::apply(&rules, &uri.parse().unwrap());
+        match apply("/old.html?a=1") {
+            Some(Rewritten::Uri(uri)) => assert_eq!(uri, "/new.html?a=1"),
+            r => panic!("unexpected {r:?}"),
+        }
+        match apply("/blog/42") {
+            Some(Rewritten::Uri(uri)) => assert_eq!(uri, "/posts/42.html"),
+            r => panic!("unexpected {r:?}"),
+        }
+        match apply("/legacy/a/b?x=1") {
+            Some(Rewritten::Redirect(status, location)) => {
+                assert_eq!(status, StatusCode::PERMANENT_REDIRECT);
+                assert_eq!(location, "/v2/a/b?from=legacy");
+            }
+            r => panic!("unexpected {r:?}"),
+        }
+        assert!(apply("/blog/x").is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    future::Future,
+    io::{Error, Result},
+    pin::Pin,
+    result::Result as StdResult,
+    task::{Context, Poll},
+};
+
+use hyper::{service::Service, Request, Response, StatusCode};
+
+use crate::{body::Body, filesvr::FileService};
+
+/// the path is under the prefix by the segments, the prefix `/` matches all.
+fn is_prefix_matched(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix == "/" || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The router maps the url prefixes to the file services, e.g. `/assets` to `./dist`
+/// and `/docs` to `./docs`. the longest matched prefix is picked.
+#[derive(Clone, Default)]
+pub struct FileRouter {
+    // the prefixes are sorted by the length descending.
+    mounts: Vec<(String, FileService)>,
+}
+
+impl FileRouter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// mount the local root at the url prefix.
+    pub fn route(self, prefix: &str, local_root: impl Into<String>) -> Self {
+        self.mount(prefix, FileService::new(local_root))
+    }
+
+    /// mount the configured file service at the url prefix, the prefix of the service
+    /// is replaced.
+    pub fn mount(mut self, prefix: &str, service: FileService) -> Self {
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        let service = service.with_prefix(&prefix);
+        self.mounts.retain(|(p, _)| p != &prefix);
+        self.mounts.push((prefix, service));
+        self.mounts.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        self
+    }
+
+    fn find(&self, path: &str) -> Option<&FileService> {
+        self.mounts
+            .iter()
+            .find(|(prefix, _)| is_prefix_matched(path, prefix))
+            .map(|(_, service)| service)
+    }
+}
+
+impl<B> Service<Request<B>> for FileRouter
+where
+    B: Sync + Send + 'static,
+{
+    type Response = Response<Body>;
+
+    type Error = Error;
+
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response>> + Send>>;
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        match self.find(request.uri().path()) {
+            Some(service) => service.clone().call(request),
+            None => Box::pin(async {
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::Empty)
+                    .map_err(Error::other)
+            }),
+        }
+    }
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[derive(Clone)]
+pub struct FileRouterMaker {
+    router: FileRouter,
+}
+
+/// make the connection services from the configured router.
+impl From<FileRouter> for FileRouterMaker {
+    fn from(router: FileRouter) -> Self {
+        Self { router }
+    }
+}
+
+impl<T> Service<T> for FileRouterMaker {
+    type Response = FileRouter;
+
+    type Error = hyper::Error;
+
+    type Future = Pin<Box<dyn Future<Output = StdResult<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<StdResult<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: T) -> Self::Future {
+        let router = self.router.clone();
+        Box::pin(async move { Ok(router) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_prefix_matched() {
+        assert!(is_prefix_matched("/assets/app.js", "/assets"));
+        assert!(is_prefix_matched("/assets", "/assets"));
+        assert!(!is_prefix_matched("/assetsx/app.js", "/assets"));
+        assert!(is_prefix_matched("/any", "/"));
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let dir = std::env::temp_dir().join("blockless-router");
+        for name in ["root", "assets", "assets-js"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(dir.join(name).join("app.js"), name).unwrap();
+        }
+        let root = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let mut router = FileRouter::new()
+            .route("/", root("root"))
+            .route("/assets", root("assets"))
+            .route("/assets/js/", root("assets-js"));
+        let body = |resp: Response<Body>| async {
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+        for (uri, expected) in [
+            ("/app.js", "root"),
+            ("/assets/app.js", "assets"),
+            ("/assets/js/app.js", "assets-js"),
+        ] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = router.call(req).await.unwrap();
+            assert_eq!(body(resp).await, expected, "{uri}");
+        }
+
+        let mut router = FileRouter::new().route("/assets", root("assets"));
+        let req = Request::get("/app.js").body(()).unwrap();
+        let resp = router.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+/// The executor of the blocking file operations, e.g. `async_std::task::spawn_blocking`
+/// or `blocking::unblock` of smol, the service itself doesn't depend on the runtime.
+pub trait BlockingExecutor: Send + Sync + 'static {
+    /// run the task in background, the result is sent back by the task itself.
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>);
+}
+
+impl<F> BlockingExecutor for F
+where
+    F: Fn(Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+{
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        self(task)
+    }
+}
+
+static EXECUTOR: OnceLock<Box<dyn BlockingExecutor>> = OnceLock::new();
+
+/// set the executor of the blocking file operations of the process, `false` if it
+/// is set already. the blocking pool of the current tokio runtime is used by default,
+/// or a thread for each operation outside the tokio runtime, or inline on wasi.
+///
+/// ```ignore
+/// blockless_hyper_file::set_blocking_executor(|task| {
+///     async_std::task::spawn_blocking(task);
+/// });
+/// ```
+pub fn set_blocking_executor(executor: impl BlockingExecutor) -> bool {
+    EXECUTOR.set(Box::new(executor)).is_ok()
+}
+
+/// run the blocking task by the executor of the process, the task is run inline
+/// on wasi without the executor, there is no thread.
+pub(crate) fn spawn_blocking(task: impl FnOnce() + Send + 'static) {
+    if let Some(executor) = EXECUTOR.get() {
+        return executor.spawn_blocking(Box::new(task));
+    }
+    #[cfg(target_os = "wasi")]
+    return task();
+    #[cfg(all(feature = "tokio", not(target_os = "wasi")))]
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        return drop(handle.spawn_blocking(task));
+    }
+    #[cfg(not(target_os = "wasi"))]
+    drop(std::thread::spawn(task));
+}
+
+/// if the current thread is in the tokio runtime, the tokio file and timer are used.
+/// always `false` without the `tokio` feature.
+pub(crate) fn is_tokio() -> bool {
+    #[cfg(feature = "tokio")]
+    return tokio::runtime::Handle::try_current().is_ok();
+    #[cfg(not(feature = "tokio"))]
+    false
+}
+
+/// the future of the sleep, by the tokio timer or by the blocking executor outside
+/// the tokio runtime.
+pub(crate) type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    #[cfg(feature = "tokio")]
+    if is_tokio() {
+        return Box::pin(tokio::time::sleep(duration));
+    }
+    let (tx, rx) = futures_channel::oneshot::channel();
+    spawn_blocking(move || {
+        std::thread::sleep(duration);
+        let _ = tx.send(());
+    });
+    Box::pin(async move {
+        let _ = rx.await;
+    })
+}
+
+/// the output of the future, `None` if it is not ready before the deadline. the deadline
+/// needs the tokio timer, the future is awaited as it is without the `tokio` feature.
+pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+    #[cfg(feature = "tokio")]
+    return tokio::time::timeout_at(deadline.into(), future).await.ok();
+    #[cfg(not(feature = "tokio"))]
+    {
+        let _ = deadline;
+        Some(future.await)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::Arc,
+        task::{Context, Poll, Wake},
+        thread::Thread,
+    };
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// run the future on the current thread without any runtime.
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_spawn_blocking() {
+        assert!(!super::is_tokio());
+        let (tx, rx) = futures_channel::oneshot::channel();
+        super::spawn_blocking(move || {
+            let _ = tx.send(1);
+        });
+        assert_eq!(block_on(rx).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_sleep() {
+        let start = std::time::Instant::now();
+        block_on(super::sleep(std::time::Duration::from_millis(50)));
+        assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use hyper::{
+    header::{self, HeaderName, HeaderValue},
+    http::uri::Scheme,
+    HeaderMap, Uri,
+};
+
+/// the placeholder of the `Content-Security-Policy` substituted by the nonce of the
+/// response, e.g. `script-src 'nonce-{nonce}'`.
+pub const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// The nonce of the `Content-Security-Policy` in the extensions of the response,
+/// e.g. for the `on_response` hook to rewrite the page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(pub String);
+
+/// The hardened headers added to every response unless the response has them already,
+/// the preset is `X-Content-Type-Options: nosniff`, `X-Frame-Options: SAMEORIGIN`,
+/// `Referrer-Policy: strict-origin-when-cross-origin` and
+/// `Cross-Origin-Resource-Policy: same-origin`.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    headers: HeaderMap,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        let mut headers = HeaderMap::new();
+        let preset = [
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            (header::X_FRAME_OPTIONS, "SAMEORIGIN"),
+            (header::REFERRER_POLICY, "strict-origin-when-cross-origin"),
+            (
+                HeaderName::from_static("cross-origin-resource-policy"),
+                "same-origin",
+            ),
+        ];
+        for (name, value) in preset {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+        Self { headers }
+    }
+}
+
+impl SecurityHeaders {
+    /// the preset headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// override the header of the preset, e.g. `X-Frame-Options: DENY`, or add the other.
+    pub fn set(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// remove the header of the preset, e.g. `Cross-Origin-Resource-Policy` of the
+    /// assets embedded by the other sites.
+    pub fn remove(mut self, name: HeaderName) -> Self {
+        self.headers.remove(name);
+        self
+    }
+
+    /// add the headers missing in the response.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.headers {
+            if !headers.contains_key(name) {
+                headers.insert(name, value.clone());
+            }
+        }
+    }
+}
+
+/// The `Strict-Transport-Security` of the responses sent over TLS, the TLS is detected by
+/// the `https` scheme of the request uri, `X-Forwarded-Proto: https` or `proto=https` of
+/// `Forwarded` of the proxy, unless it is assumed by [`Hsts::assume_tls`].
+#[derive(Debug, Clone)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+    assume_tls: bool,
+}
+
+impl Hsts {
+    /// the duration the host is known as the HTTPS only host by the browser.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+            assume_tls: false,
+        }
+    }
+
+    /// apply the policy to the subdomains of the host.
+    pub fn include_subdomains(mut self, enable: bool) -> Self {
+        self.include_subdomains = enable;
+        self
+    }
+
+    /// consent to the preload list of the browsers, which requires the `includeSubDomains`
+    /// and the max age of a year at least.
+    pub fn preload(mut self, enable: bool) -> Self {
+        self.preload = enable;
+        self
+    }
+
+    /// the service is always behind TLS, e.g. the TLS acceptor, the header is sent without
+    /// the detection.
+    pub fn assume_tls(mut self, enable: bool) -> Self {
+        self.assume_tls = enable;
+        self
+    }
+
+    /// the request is sent over TLS to the service or the proxy of the service.
+    fn is_tls(&self, uri: &Uri, headers: &HeaderMap) -> bool {
+        if self.assume_tls || uri.scheme() == Some(&Scheme::HTTPS) {
+            return true;
+        }
+        // the protocol of the first hop of the client.
+        let first = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+        };
+        if let Some(forwarded) = first(header::FORWARDED) {
+            return forwarded.split(';').any(|pair| {
+                pair.trim().split_once('=').is_some_and(|(name, value)| {
+                    name.eq_ignore_ascii_case("proto")
+                        && value.trim_matches('"').eq_ignore_ascii_case("https")
+                })
+            });
+        }
+        first(HeaderName::from_static("x-forwarded-proto"))
+            .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+    }
+
+    fn value(&self) -> String {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        value
+    }
+
+    /// add the header to the response of the request over TLS, the header sent over
+    /// the plain HTTP is ignored by the browser.
+    pub(crate) fn apply(&self, uri: &Uri, request: &HeaderMap, headers: &mut HeaderMap) {
+        if headers.contains_key(header::STRICT_TRANSPORT_SECURITY) || !self.is_tls(uri, request) {
+            return;
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.value()) {
+            headers.insert(header::STRICT_TRANSPORT_SECURITY, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let security = SecurityHeaders::new()
+            .set(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"))
+            .remove(HeaderName::from_static("cross-origin-resource-policy"));
+        let mut headers = HeaderMap::new();
+        let referrer_policy = HeaderValue::from_static("no-referrer");
+        headers.insert(header::REFERRER_POLICY, referrer_policy);
+        security.apply(&mut headers);
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[header::REFERRER_POLICY], "no-referrer");
+        assert!(!headers.contains_key("cross-origin-resource-policy"));
+    }
+
+    #[test]
+    fn test_hsts() {
+        let hsts = Hsts::new(Duration::from_secs(31536000))
+            .include_subdomains(true)
+            .preload(true);
+        let apply = |hsts: &Hsts, uri: &str, name: &str, value: &str| {
+            let mut request = HeaderMap::new();
+            if !name.is_empty() {
+                let name = HeaderName::from_bytes(name.as_bytes()).unwrap();
+                request.insert(name, HeaderValue::from_str(value).unwrap());
+            }
+            let mut headers = HeaderMap::new();
+            hsts.apply(&uri.parse().unwrap(), &request, &mut headers);
+            headers.remove(header::STRICT_TRANSPORT_SECURITY)
+        };
+        let value = "max-age=31536000; includeSubDomains; preload";
+        assert_eq!(
+            apply(&hsts, "/", "x-forwarded-proto", "https").unwrap(),
+            value
+        );
+        assert!(apply(&hsts, "https://a.example/", "", "").is_some());
+        assert!(apply(&hsts, "/", "forwarded", "for=1.1.1.1;proto=https").is_some());
+        assert!(apply(&hsts, "/", "x-forwarded-proto", "http").is_none());
+        assert!(apply(&hsts, "/", "", "").is_none());
+        let hsts = Hsts::new(Duration::from_secs(60)).assume_tls(true);
+        assert_eq!(apply(&hsts, "/", "", "").unwrap(), "max-age=60");
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    request_resolve,
+    sign::{constant_time_eq, hex, hmac},
+};
+
+/// The key of the HMAC-signed urls expiring at the time, e.g. `/a.zip?exp=1700000000&sig=..`.
+/// the signature is of the normalized path and the expiry, the other query parameters
+/// are not signed.
+#[derive(Clone)]
+pub struct SignedUrl {
+    key: Vec<u8>,
+}
+
+impl SignedUrl {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    /// the hex signature of the normalized path and the expiry in the unix seconds.
+    fn signature(&self, path: &str, exp: u64) -> String {
+        hex(&hmac(&self.key, format!("{path}\n{exp}").as_bytes()))
+    }
+
+    /// sign the path of the url under the root, e.g. `/downloads/a.zip`, the signed url is
+    /// returned with the `exp` and `sig` query, or `None` if the path is invalid. the prefix
+    /// of the service is prepended to the url, which is not signed.
+    pub fn sign(&self, path: &str, expires: SystemTime) -> Option<String> {
+        let decoded = percent_encoding::percent_decode_str(path)
+            .decode_utf8()
+            .ok()?;
+        let normalized = request_resolve::normalize_path(&decoded)?;
+        let exp = expires.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let sig = self.signature(&normalized, exp);
+        Some(format!("{path}?exp={exp}&sig={sig}"))
+    }
+
+    /// verify the query of the normalized path, the expired url is rejected.
+    pub(crate) fn verify(&self, path: &str, query: Option<&str>) -> bool {
+        let (mut exp, mut sig) = (None, None);
+        for pair in query.unwrap_or_default().split('&') {
+            match pair.split_once('=') {
+                Some(("exp", value)) => exp = value.parse::<u64>().ok(),
+                Some(("sig", value)) => sig = Some(value),
+                _ => {}
+            }
+        }
+        let (exp, sig) = match (exp, sig) {
+            (Some(exp), Some(sig)) => (exp, sig),
+            _ => return false,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        if now.map_or(true, |now| now.as_secs() >= exp) {
+            return false;
+        }
+        constant_time_eq(self.signature(path, exp).as_bytes(), sig.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FileService;
+    use hyper::{service::Service, Request, StatusCode};
+    use std::time::Duration;
+
+    #[test]
+    fn test_sign() {
+        let signer = SignedUrl::new("secret");
+        let expires = UNIX_EPOCH + Duration::from_secs(4102444800);
+        let url = signer.sign("/downloads/a%20b.zip", expires).unwrap();
+        let (path, query) = url.split_once('?').unwrap();
+        assert_eq!(path, "/downloads/a%20b.zip");
+        assert!(query.starts_with("exp=4102444800&sig="));
+        assert!(signer.verify("downloads/a b.zip", Some(query)));
+        assert!(!signer.verify("downloads/c.zip", Some(query)));
+        assert!(!SignedUrl::new("other").verify("downloads/a b.zip", Some(query)));
+        assert!(!signer.verify("downloads/a b.zip", None));
+        assert!(signer.sign("/../a.zip", expires).is_none());
+
+        let url = signer.sign("/a.zip", UNIX_EPOCH + Duration::from_secs(1));
+        let (_, query) = url.as_deref().unwrap().split_once('?').unwrap();
+        assert!(!signer.verify("a.zip", Some(query)));
+    }
+
+    #[tokio::test]
+    async fn test_signed_url() {
+        let dir = std::env::temp_dir().join("blockless-signed-url");
+        std::fs::create_dir_all(dir.join("downloads")).unwrap();
+        std::fs::write(dir.join("downloads/a.zip"), b"zip").unwrap();
+        let signer = SignedUrl::new("secret");
+        let mut service =
+            FileService::new(dir.to_str().unwrap()).signed_url("downloads/**", signer.clone());
+        let expires = SystemTime::now() + Duration::from_secs(60);
+        let url = signer.sign("/downloads/a.zip", expires).unwrap();
+        for (uri, status) in [
+            (url.as_str(), StatusCode::OK),
+            ("/downloads/a.zip", StatusCode::FORBIDDEN),
+            (
+                "/downloads/a.zip?exp=4102444800&sig=00",
+                StatusCode::FORBIDDEN,
+            ),
+        ] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = service.call(req).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+
+        // the index file of the directory is signed by the directory path.
+        std::fs::write(dir.join("downloads/index.html"), b"index").unwrap();
+        let url = signer.sign("/downloads/", expires).unwrap();
+        for (uri, status) in [
+            (url.as_str(), StatusCode::OK),
+            ("/downloads/", StatusCode::FORBIDDEN),
+        ] {
+            let req = Request::get(uri).body(()).unwrap();
+            let resp = service.call(req).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use hyper::Response;
+
+use crate::body::{Body, ObservedBytesStream};
+
+/// The hits and the bytes sent of the resolved path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathStats {
+    pub hits: u64,
+    pub bytes: u64,
+}
+
+/// The counters of the resolved paths in memory, the paths beyond the max paths are
+/// not counted.
+#[derive(Debug, Clone)]
+pub(crate) struct Stats {
+    paths: Arc<Mutex<HashMap<PathBuf, PathStats>>>,
+    max_paths: usize,
+}
+
+impl Stats {
+    pub(crate) fn new(max_paths: usize) -> Self {
+        Self {
+            paths: Default::default(),
+            max_paths,
+        }
+    }
+
+    /// count the hit of the path, the bytes are counted when the body is finished.
+    pub(crate) fn observe(&self, path: PathBuf, resp: &mut Response<Body>) {
+        {
+            let mut paths = self.paths.lock().unwrap();
+            if paths.len() >= self.max_paths && !paths.contains_key(&path) {
+                return;
+            }
+            paths.entry(path.clone()).or_default().hits += 1;
+        }
+        let paths = self.paths.clone();
+        let on_finish = move |sent, _| {
+            if let Some(stats) = paths.lock().unwrap().get_mut(&path) {
+                stats.bytes += sent;
+            }
+        };
+        let body = std::mem::replace(resp.body_mut(), Body::Empty);
+        *resp.body_mut() = Body::Observed(ObservedBytesStream::new(body, Box::new(on_finish)));
+    }
+
+    /// the snapshot of the paths, the most hit first.
+    pub(crate) fn snapshot(&self) -> Vec<(PathBuf, PathStats)> {
+        let paths = self.paths.lock().unwrap();
+        let mut snapshot: Vec<_> = paths.iter().map(|(p, s)| (p.clone(), *s)).collect();
+        snapshot.sort_by(|a, b| b.1.hits.cmp(&a.1.hits).then_with(|| a.0.cmp(&b.0)));
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::body::Bytes;
+
+    #[test]
+    fn test_observe() {
+        let stats = Stats::new(2);
+        let observe = |path: &str| {
+            let mut resp = Response::new(Body::Bytes(Some(Bytes::from_static(b"hello"))));
+            stats.observe(PathBuf::from(path), &mut resp);
+        };
+        observe("/b");
+        observe("/a");
+        observe("/a");
+        observe("/c");
+        let hits = |hits, bytes| PathStats { hits, bytes };
+        // the bodies dropped without sent.
+        assert_eq!(
+            stats.snapshot(),
+            [
+                (PathBuf::from("/a"), hits(2, 0)),
+                (PathBuf::from("/b"), hits(1, 0))
+            ]
+        );
+    }
+}
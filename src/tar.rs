@@ -0,0 +1,274 @@
+use std::{
+    fs::File,
+    io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom},
+};
+
+use flate2::read::DeflateDecoder;
+
+use crate::archive::{ArchiveIndex, Member, Method};
+
+const BLOCK_SIZE: u64 = 512;
+
+fn invalid(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid tar archive: {msg}"),
+    )
+}
+
+/// The stream of the tar archive, the data of the member not indexed is skipped.
+trait TarStream: Read {
+    fn skip(&mut self, n: u64) -> Result<()>;
+}
+
+impl TarStream for File {
+    fn skip(&mut self, n: u64) -> Result<()> {
+        self.seek(SeekFrom::Current(n as i64)).map(|_| ())
+    }
+}
+
+impl<R: Read> TarStream for DeflateDecoder<R> {
+    fn skip(&mut self, n: u64) -> Result<()> {
+        let skipped = std::io::copy(&mut self.take(n), &mut std::io::sink())?;
+        if skipped < n {
+            return Err(invalid("truncated"));
+        }
+        Ok(())
+    }
+}
+
+/// the octal number of the header field, or the base-256 number of the GNU extension.
+fn number(field: &[u8]) -> Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        let value = field[1..]
+            .iter()
+            .fold(u64::from(field[0] & 0x7f), |n, b| (n << 8) | u64::from(*b));
+        return Ok(value);
+    }
+    let field = std::str::from_utf8(field).map_err(|_| invalid("bad number"))?;
+    let field = field.trim_matches(|c: char| c == '\0' || c == ' ');
+    if field.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(field, 8).map_err(|_| invalid("bad number"))
+}
+
+/// the field terminated by NUL.
+fn string(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// the `path` of the pax extended header, the records are `<len> <key>=<value>\n`.
+fn pax_path(mut records: &[u8]) -> Option<String> {
+    let mut path = None;
+    while !records.is_empty() {
+        let space = records.iter().position(|b| *b == b' ')?;
+        let len: usize = std::str::from_utf8(&records[..space]).ok()?.parse().ok()?;
+        let record = records.get(space + 1..len)?;
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Some(value) = record.strip_prefix(b"path=") {
+            path = Some(String::from_utf8_lossy(value).into_owned());
+        }
+        records = &records[len..];
+    }
+    path
+}
+
+/// build the index of the tar stream, the offset of the member is the position
+/// in the stream, the links and special files are skipped.
+fn index_stream(
+    stream: &mut impl TarStream,
+    member: impl Fn(u64, u64) -> Member,
+) -> Result<ArchiveIndex> {
+    let mut index = ArchiveIndex::default();
+    let mut position = 0;
+    // the path of the next member by the GNU long name or the pax header.
+    let mut long_name = None;
+    let mut header = [0u8; BLOCK_SIZE as usize];
+    loop {
+        if stream.read_exact(&mut header).is_err() {
+            return Err(invalid("truncated"));
+        }
+        position += BLOCK_SIZE;
+        // the archive ends with the zero blocks.
+        if header.iter().all(|b| *b == 0) {
+            return Ok(index);
+        }
+        let size = number(&header[124..136])?;
+        let padded = size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+        let kind = header[156];
+        if matches!(kind, b'L' | b'x') {
+            let mut data = vec![0u8; size as usize];
+            stream.read_exact(&mut data)?;
+            stream.skip(padded - size)?;
+            position += padded;
+            long_name = match kind {
+                b'L' => Some(string(&data)),
+                _ => pax_path(&data).or(long_name),
+            };
+            continue;
+        }
+        let name = long_name.take().unwrap_or_else(|| {
+            let name = string(&header[..100]);
+            let prefix = string(&header[345..500]);
+            match &header[257..262] {
+                b"ustar" if !prefix.is_empty() => format!("{prefix}/{name}"),
+                _ => name,
+            }
+        });
+        match kind {
+            b'0' | b'\0' | b'7' => index.insert(&name, Some(member(position, size))),
+            b'5' => index.insert(&name, None),
+            _ => {}
+        }
+        stream.skip(padded)?;
+        position += padded;
+    }
+}
+
+/// build the index of the tar archive, the member is read from the archive as is.
+pub(crate) fn index(file: &mut File) -> Result<ArchiveIndex> {
+    index_stream(file, |offset, size| Member {
+        method: Method::Stored,
+        offset,
+        compressed_size: size,
+        size,
+        skip: 0,
+    })
+}
+
+/// the offset of the deflated data after the gzip header.
+fn gzip_data_offset(file: &mut File) -> Result<u64> {
+    let mut reader = BufReader::new(file);
+    let mut header = [0u8; 10];
+    reader.read_exact(&mut header)?;
+    if header[..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid("not gzip"));
+    }
+    let flags = header[3];
+    let mut offset = 10u64;
+    let mut byte = [0u8; 1];
+    if flags & 0x04 != 0 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let len = u16::from_le_bytes(len) as u64;
+        std::io::copy(&mut (&mut reader).take(len), &mut std::io::sink())?;
+        offset += 2 + len;
+    }
+    // the file name and the comment are terminated by NUL.
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            loop {
+                reader.read_exact(&mut byte)?;
+                offset += 1;
+                if byte[0] == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    if flags & 0x02 != 0 {
+        offset += 2;
+    }
+    Ok(offset)
+}
+
+/// build the index of the `.tar.gz` archive, the member is read by inflating the
+/// archive from the beginning and skipping the bytes before it.
+pub(crate) fn index_gzip(file: &mut File) -> Result<ArchiveIndex> {
+    let data_offset = gzip_data_offset(file)?;
+    let compressed_size = file.metadata()?.len().saturating_sub(data_offset);
+    file.seek(SeekFrom::Start(data_offset))?;
+    let mut decoder = DeflateDecoder::new(BufReader::new(file));
+    index_stream(&mut decoder, |offset, size| Member {
+        method: Method::Deflated,
+        offset: data_offset,
+        compressed_size,
+        size,
+        skip: offset,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn header(name: &str, size: usize, kind: u8) -> [u8; 512] {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{size:011o}").as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header
+    }
+
+    fn padded(data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        data.resize(data.len().div_ceil(512) * 512, 0);
+        data
+    }
+
+    /// the tar archive of the files, the long name is written by the GNU extension.
+    pub(crate) fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = Vec::new();
+        for (name, content) in files {
+            if name.len() > 100 {
+                tar.extend_from_slice(&header("././@LongLink", name.len() + 1, b'L'));
+                tar.extend_from_slice(&padded(format!("{name}\0").as_bytes()));
+                tar.extend_from_slice(&header(&name[..100], content.len(), b'0'));
+            } else {
+                tar.extend_from_slice(&header(name, content.len(), b'0'));
+            }
+            tar.extend_from_slice(&padded(content));
+        }
+        tar.extend_from_slice(&[0u8; 1024]);
+        tar
+    }
+
+    pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read(path: &std::path::Path, member: &Member) -> String {
+        let source = crate::archive::Source::Archive {
+            archive: path.to_path_buf(),
+            member: member.clone(),
+        };
+        let mut content = String::new();
+        source
+            .open_std()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_index() {
+        let long_name = format!("{}/file.txt", "d".repeat(100));
+        let tar = tar(&[
+            ("a.txt", b"hello"),
+            (&long_name, b"long"),
+            ("b/c.txt", b"world"),
+        ]);
+        let dir = std::env::temp_dir().join("blockless-tar");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("bundle.tar"), &tar).unwrap();
+        std::fs::write(dir.join("bundle.tar.gz"), gzip(&tar)).unwrap();
+
+        let path = dir.join("bundle.tar");
+        let index = index(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(read(&path, index.member("a.txt").unwrap()), "hello");
+        assert_eq!(read(&path, index.member(&long_name).unwrap()), "long");
+
+        let path = dir.join("bundle.tar.gz");
+        let index = index_gzip(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(read(&path, index.member("b/c.txt").unwrap()), "world");
+        assert_eq!(read(&path, index.member("a.txt").unwrap()), "hello");
+    }
+}
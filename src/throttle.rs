@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+/// The token bucket of the bytes, refilled by the rate per second, and holds
+/// the tokens of one second at most, which is the burst size.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    capacity: u64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64) -> Self {
+        assert!(rate > 0, "the rate must be positive");
+        Self::with_capacity(rate as f64, rate)
+    }
+
+    /// the bucket of the fractional rate holding the tokens of the capacity at most,
+    /// e.g. the requests per minute.
+    pub fn with_capacity(rate: f64, capacity: u64) -> Self {
+        assert!(rate > 0.0 && capacity > 0, "the rate must be positive");
+        Self {
+            rate,
+            capacity,
+            tokens: capacity as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    /// the most tokens can be taken at once.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// the duration to wait until there are `n` tokens, `None` if there are enough.
+    pub fn wait_time(&mut self, n: u64) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity as f64);
+        self.updated = now;
+        let n = n as f64;
+        (self.tokens < n).then(|| Duration::from_secs_f64((n - self.tokens) / self.rate))
+    }
+
+    /// the bucket is refilled, which is the same as the new one.
+    pub fn is_full(&mut self) -> bool {
+        self.wait_time(self.capacity).is_none()
+    }
+
+    /// take `n` tokens, which are checked by `wait_time`.
+    pub fn take(&mut self, n: u64) {
+        self.tokens -= n as f64;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(1000);
+        assert!(bucket.wait_time(600).is_none());
+        bucket.take(600);
+        let wait = bucket.wait_time(600).unwrap();
+        assert!(wait > Duration::from_millis(150) && wait <= Duration::from_millis(200));
+        std::thread::sleep(wait);
+        assert!(bucket.wait_time(600).is_none());
+    }
+}
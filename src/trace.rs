@@ -0,0 +1,136 @@
+use std::{future::Future, io::Result, time::Instant};
+
+use hyper::{header::HeaderName, Request, Response};
+use tracing::{field, Instrument, Span};
+
+use crate::{
+    body::{Body, ObservedBytesStream},
+    file::FileMeta,
+    request_resolve::Resolved,
+};
+
+/// the span of the request, the status is recorded when the response is built.
+pub(crate) fn request_span<B>(request: &Request<B>, request_id: Option<&HeaderName>) -> Span {
+    let span = tracing::info_span!(
+        "file_service",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = field::Empty,
+        status = field::Empty,
+    );
+    let id = request_id.and_then(|name| request.headers().get(name)?.to_str().ok());
+    if let Some(id) = id {
+        span.record("request_id", id);
+    }
+    span
+}
+
+/// serve in the span, the event of the bytes sent and the duration is emitted when the
+/// body is finished.
+pub(crate) async fn instrument<F>(serve: F, span: Span) -> Result<Response<Body>>
+where
+    F: Future<Output = Result<Response<Body>>>,
+{
+    let start = Instant::now();
+    let mut resp = match serve.instrument(span.clone()).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            span.in_scope(|| tracing::error!(error = %e, "the request is failed"));
+            return Err(e);
+        }
+    };
+    let status = resp.status().as_u16();
+    span.record("status", status);
+    let on_finish = move |sent: u64, is_complete: bool| {
+        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        span.in_scope(|| {
+            tracing::info!(status, bytes_sent = sent, is_complete, duration_ms, "sent")
+        });
+    };
+    let body = std::mem::replace(resp.body_mut(), Body::Empty);
+    *resp.body_mut() = Body::Observed(ObservedBytesStream::new(body, Box::new(on_finish)));
+    Ok(resp)
+}
+
+/// the event of the resolved path, the content of the file in memory is not logged.
+pub(crate) fn resolved(resolved: &Resolved) {
+    match resolved {
+        Resolved::Found(meta) => {
+            tracing::debug!(path = %meta.path.display(), size = meta.size, "found")
+        }
+        Resolved::IsDirectory(meta) => {
+            tracing::debug!(path = %meta.path.display(), "directory")
+        }
+        Resolved::Redirect(location) => tracing::debug!(%location, "redirect"),
+        other => tracing::debug!(resolved = ?other, "resolved"),
+    }
+}
+
+/// the event of the file opened to send the content.
+pub(crate) fn opened(meta: &FileMeta, result: &Result<impl Sized>) {
+    match result {
+        Ok(_) => tracing::debug!(path = %meta.path.display(), size = meta.size, "open"),
+        Err(e) => tracing::warn!(path = %meta.path.display(), error = %e, "open failed"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::body::{Bytes, HttpBody};
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    // the subscriber records the fields of the events.
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let field = format!("{}={:?}", field.name(), value);
+            self.0.lock().unwrap().push(field);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut Recorder(self.0.clone()));
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn test_instrument() {
+        let recorder = Recorder::default();
+        let fields = recorder.0.clone();
+        let _guard = tracing::subscriber::set_default(recorder);
+        let request = Request::get("/a.txt").body(()).unwrap();
+        let serve = async {
+            Ok(Response::new(Body::Bytes(Some(Bytes::from_static(
+                b"hello",
+            )))))
+        };
+        let resp = instrument(serve, request_span(&request, None))
+            .await
+            .unwrap();
+        let mut body = resp.into_body();
+        while body.data().await.is_some() {}
+        drop(body);
+        let fields = fields.lock().unwrap();
+        assert!(fields.contains(&"status=200".to_string()));
+        assert!(fields.contains(&"bytes_sent=5".to_string()));
+        assert!(fields.contains(&"is_complete=true".to_string()));
+    }
+}
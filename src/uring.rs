@@ -0,0 +1,422 @@
+use std::{
+    fs::File,
+    io::{Error, ErrorKind, Result},
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    ptr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+use futures_channel::oneshot;
+use hyper::body::Bytes;
+
+use crate::file::BlockingFuture;
+
+// the constants and the layouts of `linux/io_uring.h`.
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x8000000;
+const IORING_OFF_SQES: libc::off_t = 0x10000000;
+const IORING_ENTER_GETEVENTS: u32 = 1;
+const IORING_FEAT_NODROP: u32 = 1 << 1;
+const IORING_FEAT_RW_CUR_POS: u32 = 1 << 3;
+const IORING_OP_READ: u8 = 22;
+
+// the entries of the submission queue, the completion queue is twice the size.
+const ENTRIES: u32 = 256;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqringOffsets,
+    cq_off: CqringOffsets,
+}
+
+#[repr(C)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+/// the read in flight, the buffer and the file are kept until the read is completed,
+/// even if the future is dropped.
+struct Read {
+    buf: Vec<u8>,
+    _file: Arc<File>,
+    sender: oneshot::Sender<Result<Bytes>>,
+}
+
+/// the reads in flight indexed by the user data of the entries.
+#[derive(Default)]
+struct Reads {
+    slots: Vec<Option<Read>>,
+    free: Vec<usize>,
+    len: usize,
+    // the ring is failed to wait for the completions, the reads fall back.
+    closed: bool,
+}
+
+impl Reads {
+    fn insert(&mut self, read: Read) -> usize {
+        self.len += 1;
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = Some(read);
+                index
+            }
+            None => {
+                self.slots.push(Some(read));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Read> {
+        let read = self.slots.get_mut(index)?.take()?;
+        self.free.push(index);
+        self.len -= 1;
+        Some(read)
+    }
+}
+
+/// The ring shared by the process, the reads are submitted by the tasks and completed
+/// by the thread waiting for the completions. the ring is never torn down.
+struct Ring {
+    fd: OwnedFd,
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_entries: u32,
+    sq_array: *mut u32,
+    sqes: *mut Sqe,
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cq_entries: u32,
+    cqes: *const Cqe,
+    // the submission queue is written under the lock of the reads.
+    reads: Mutex<Reads>,
+}
+
+// SAFETY: the submission queue is written under the lock, the completion queue is read
+// by the completion thread only, and the heads and tails are atomic.
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+
+/// map the region of the ring, which is shared with the kernel.
+fn map(fd: RawFd, len: usize, offset: libc::off_t) -> Result<*mut u8> {
+    // SAFETY: the new map of the ring doesn't overlap the existing memory.
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_POPULATE,
+            fd,
+            offset,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(Error::last_os_error());
+    }
+    Ok(ptr as *mut u8)
+}
+
+fn enter(fd: RawFd, to_submit: u32, min_complete: u32, flags: u32) -> Result<u32> {
+    loop {
+        // SAFETY: the ring is set up, the signal mask is not passed.
+        let n = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_enter,
+                fd,
+                to_submit,
+                min_complete,
+                flags,
+                ptr::null::<libc::sigset_t>(),
+                0usize,
+            )
+        };
+        if n >= 0 {
+            return Ok(n as u32);
+        }
+        let e = Error::last_os_error();
+        if e.kind() != ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+impl Ring {
+    fn new() -> Result<Self> {
+        let mut params = Params::default();
+        // SAFETY: the params are the layout of `io_uring_params`.
+        let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, ENTRIES, &mut params) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        // SAFETY: the fd is the new ring owned here.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+        // the reads at the offset and the completions kept on the overflow, since 5.6.
+        let features = IORING_FEAT_NODROP | IORING_FEAT_RW_CUR_POS;
+        if params.features & features != features {
+            return Err(Error::new(ErrorKind::Unsupported, "io_uring is too old"));
+        }
+        let (sq_off, cq_off) = (&params.sq_off, &params.cq_off);
+        let sq_len = sq_off.array as usize + params.sq_entries as usize * mem::size_of::<u32>();
+        let cq_len = cq_off.cqes as usize + params.cq_entries as usize * mem::size_of::<Cqe>();
+        let sqes_len = params.sq_entries as usize * mem::size_of::<Sqe>();
+        let raw = fd.as_raw_fd();
+        let sq = map(raw, sq_len, IORING_OFF_SQ_RING)?;
+        let cq = map(raw, cq_len, IORING_OFF_CQ_RING)?;
+        let sqes = map(raw, sqes_len, IORING_OFF_SQES)?;
+        // SAFETY: the offsets are in the maps of the lengths above.
+        unsafe {
+            Ok(Self {
+                sq_head: sq.add(sq_off.head as usize) as _,
+                sq_tail: sq.add(sq_off.tail as usize) as _,
+                sq_mask: *(sq.add(sq_off.ring_mask as usize) as *const u32),
+                sq_entries: params.sq_entries,
+                sq_array: sq.add(sq_off.array as usize) as _,
+                sqes: sqes as _,
+                cq_head: cq.add(cq_off.head as usize) as _,
+                cq_tail: cq.add(cq_off.tail as usize) as _,
+                cq_mask: *(cq.add(cq_off.ring_mask as usize) as *const u32),
+                cq_entries: params.cq_entries,
+                cqes: cq.add(cq_off.cqes as usize) as _,
+                fd,
+                reads: Default::default(),
+            })
+        }
+    }
+
+    /// submit the read, `None` if the ring is full of the reads in flight.
+    fn read(&self, file: &Arc<File>, offset: u64, len: usize) -> Option<BlockingFuture<Bytes>> {
+        let mut reads = self.reads.lock().unwrap();
+        // the completions of the full ring are kept by the kernel, but never wait for them.
+        if reads.closed || reads.len >= self.cq_entries as usize {
+            return None;
+        }
+        let (sender, future) = BlockingFuture::channel();
+        let mut buf = Vec::with_capacity(len);
+        let addr = buf.as_mut_ptr() as u64;
+        let index = reads.insert(Read {
+            buf,
+            _file: file.clone(),
+            sender,
+        });
+        // SAFETY: the heads and tails are mapped, the tail is written under the lock only.
+        let (head, tail) = unsafe {
+            (
+                (*self.sq_head).load(Ordering::Acquire),
+                (*self.sq_tail).load(Ordering::Relaxed),
+            )
+        };
+        if tail.wrapping_sub(head) >= self.sq_entries {
+            reads.remove(index);
+            return None;
+        }
+        let slot = tail & self.sq_mask;
+        let sqe = Sqe {
+            opcode: IORING_OP_READ,
+            flags: 0,
+            ioprio: 0,
+            fd: file.as_raw_fd(),
+            off: offset,
+            addr,
+            len: len as u32,
+            rw_flags: 0,
+            user_data: index as u64,
+            buf_index: 0,
+            personality: 0,
+            splice_fd_in: 0,
+            addr3: 0,
+            pad: 0,
+        };
+        // SAFETY: the slot is not submitted yet, the buffer of `len` bytes is kept by
+        // the read until it is completed.
+        unsafe {
+            self.sqes.add(slot as usize).write(sqe);
+            self.sq_array.add(slot as usize).write(slot);
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+        if enter(self.fd.as_raw_fd(), 1, 0, 0).is_err() {
+            // the entry is not consumed on the failure, e.g. of the memory.
+            // SAFETY: as above.
+            unsafe { (*self.sq_tail).store(tail, Ordering::Release) };
+            reads.remove(index);
+            return None;
+        }
+        Some(future)
+    }
+
+    /// fail the reads in flight, which are never completed.
+    fn close(&self, e: Error) {
+        let mut reads = self.reads.lock().unwrap();
+        reads.closed = true;
+        for read in reads.slots.drain(..).flatten() {
+            let _ = read.sender.send(Err(Error::new(e.kind(), e.to_string())));
+            // the buffer in flight may still be written by the kernel.
+            mem::forget(read.buf);
+        }
+        reads.free.clear();
+        reads.len = 0;
+    }
+
+    /// wait for the completions and send the results, the sizes are the bytes read.
+    fn complete(&self) {
+        loop {
+            if let Err(e) = enter(self.fd.as_raw_fd(), 0, 1, IORING_ENTER_GETEVENTS) {
+                if e.raw_os_error() != Some(libc::EBUSY) {
+                    return self.close(e);
+                }
+            }
+            // SAFETY: the completion queue is read by this thread only.
+            let (head, tail) = unsafe {
+                (
+                    (*self.cq_head).load(Ordering::Relaxed),
+                    (*self.cq_tail).load(Ordering::Acquire),
+                )
+            };
+            let mut completed = Vec::with_capacity(tail.wrapping_sub(head) as usize);
+            let mut current = head;
+            while current != tail {
+                // SAFETY: the entries between the head and the tail are written by the kernel.
+                let cqe = unsafe { self.cqes.add((current & self.cq_mask) as usize).read() };
+                completed.push((cqe.user_data as usize, cqe.res));
+                current = current.wrapping_add(1);
+            }
+            // SAFETY: as above.
+            unsafe { (*self.cq_head).store(tail, Ordering::Release) };
+            let mut reads = self.reads.lock().unwrap();
+            let completed: Vec<_> = completed
+                .into_iter()
+                .filter_map(|(index, res)| Some((reads.remove(index)?, res)))
+                .collect();
+            drop(reads);
+            for (read, res) in completed {
+                let Read {
+                    mut buf, sender, ..
+                } = read;
+                let result = if res < 0 {
+                    Err(Error::from_raw_os_error(-res))
+                } else {
+                    // SAFETY: the `res` bytes of the capacity are written by the read.
+                    unsafe { buf.set_len(res as usize) };
+                    Ok(Bytes::from(buf))
+                };
+                let _ = sender.send(result);
+            }
+        }
+    }
+}
+
+/// the ring of the process, `None` if io_uring is not supported or denied, e.g. by
+/// the seccomp of the container.
+fn ring() -> Option<&'static Ring> {
+    static RING: OnceLock<Option<&'static Ring>> = OnceLock::new();
+    *RING.get_or_init(|| {
+        let ring: &'static Ring = Box::leak(Box::new(Ring::new().ok()?));
+        std::thread::Builder::new()
+            .name("blockless-io-uring".into())
+            .spawn(move || ring.complete())
+            .ok()?;
+        Some(ring)
+    })
+}
+
+pub(crate) fn is_available() -> bool {
+    ring().is_some()
+}
+
+/// read the `len` bytes of the file at the offset into the new buffer by io_uring,
+/// `None` if the ring is not available or full, the read is issued otherwise.
+pub(crate) fn read(file: &Arc<File>, offset: u64, len: usize) -> Option<BlockingFuture<Bytes>> {
+    ring()?.read(file, offset, len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read() {
+        assert_eq!(mem::size_of::<Sqe>(), 64);
+        assert_eq!(mem::size_of::<Params>(), 120);
+        if !is_available() {
+            return;
+        }
+        let path = std::env::temp_dir().join("blockless-io-uring.txt");
+        std::fs::write(&path, "0123456789".repeat(100)).unwrap();
+        let file = Arc::new(File::open(&path).unwrap());
+        let chunk = read(&file, 3, 4).unwrap().await.unwrap();
+        assert_eq!(&chunk[..], b"3456");
+        let chunk = read(&file, 998, 10).unwrap().await.unwrap();
+        assert_eq!(&chunk[..], b"89");
+
+        // more reads than the completion queue, the reads over are not issued.
+        let reads: Vec<_> = (0..1000u64).map(|n| (n, read(&file, n, 1))).collect();
+        assert!(reads.iter().any(|(_, read)| read.is_some()));
+        for (n, read) in reads {
+            if let Some(read) = read {
+                assert_eq!(read.await.unwrap()[0], b'0' + (n % 10) as u8);
+            }
+        }
+    }
+}
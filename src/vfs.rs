@@ -0,0 +1,307 @@
+use std::{
+    any::Any,
+    cmp::min,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    io::{Error, ErrorKind, Result, SeekFrom},
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::body::Bytes;
+
+use crate::{
+    archive::Source,
+    file::{
+        self, BlockingFuture, FileMeta, FileReaderOpener, SymlinkPolicy, TokioFileReaderOpener,
+    },
+    listing::{self, DirEntry},
+};
+
+/// The future of the virtual filesystem operation.
+pub type VfsFuture<T> = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+/// The virtual filesystem the files are served from, e.g. the object storage.
+/// the path is the normalized path relative to the root, e.g. `css/site.css`,
+/// and the root itself is the empty path.
+pub trait AsyncVfs: Send + Sync + 'static {
+    /// the opened file read by `read_at`.
+    type File: Send + Sync + 'static;
+
+    /// get the meta of the path without opening it, the error of `NotFound` if
+    /// the path doesn't exist.
+    fn metadata(&self, path: &str) -> VfsFuture<FileMeta>;
+
+    /// the entries of the directory, used by the listing and the variants.
+    fn read_dir(&self, path: &str) -> VfsFuture<Vec<DirEntry>>;
+
+    /// open the file resolved by `metadata`.
+    fn open(&self, meta: &FileMeta) -> VfsFuture<Self::File>;
+
+    /// read at most `len` bytes at the offset, the empty bytes is the end of the file.
+    fn read_at(&self, file: Arc<Self::File>, offset: u64, len: usize) -> VfsFuture<Bytes>;
+}
+
+/// The files under the root of the local filesystem, the default of the service.
+pub struct LocalFs {
+    root: PathBuf,
+    symlink_policy: SymlinkPolicy,
+}
+
+impl LocalFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            symlink_policy: Default::default(),
+        }
+    }
+
+    pub fn symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+}
+
+impl AsyncVfs for LocalFs {
+    type File = std::fs::File;
+
+    fn metadata(&self, path: &str) -> VfsFuture<FileMeta> {
+        let opener = TokioFileReaderOpener::new(&self.root).symlink_policy(self.symlink_policy);
+        Box::pin(opener.metadata(path))
+    }
+
+    fn read_dir(&self, path: &str) -> VfsFuture<Vec<DirEntry>> {
+        let mut full_path = self.root.clone();
+        full_path.extend(path.split('/').filter(|s| !s.is_empty()));
+        Box::pin(listing::read_dir(full_path))
+    }
+
+    fn open(&self, meta: &FileMeta) -> VfsFuture<Self::File> {
+        let path = meta.path.clone();
+        Box::pin(BlockingFuture::spawn(move || std::fs::File::open(path)))
+    }
+
+    fn read_at(&self, file: Arc<Self::File>, offset: u64, len: usize) -> VfsFuture<Bytes> {
+        Box::pin(BlockingFuture::spawn(move || {
+            let mut buf = vec![0u8; len];
+            let n = file::pread(&file, &mut buf, offset)?;
+            buf.truncate(n);
+            Ok(Bytes::from(buf))
+        }))
+    }
+}
+
+type DynFile = Arc<dyn Any + Send + Sync>;
+
+/// The object-safe `AsyncVfs` with the file type erased.
+trait DynVfs: Send + Sync {
+    fn metadata(&self, path: &str) -> VfsFuture<FileMeta>;
+
+    fn read_dir(&self, path: &str) -> VfsFuture<Vec<DirEntry>>;
+
+    fn open(&self, meta: &FileMeta) -> VfsFuture<DynFile>;
+
+    fn read_at(&self, file: &DynFile, offset: u64, len: usize) -> VfsFuture<Bytes>;
+}
+
+impl<V: AsyncVfs> DynVfs for V {
+    fn metadata(&self, path: &str) -> VfsFuture<FileMeta> {
+        AsyncVfs::metadata(self, path)
+    }
+
+    fn read_dir(&self, path: &str) -> VfsFuture<Vec<DirEntry>> {
+        AsyncVfs::read_dir(self, path)
+    }
+
+    fn open(&self, meta: &FileMeta) -> VfsFuture<DynFile> {
+        let file = AsyncVfs::open(self, meta);
+        Box::pin(async move { Ok(Arc::new(file.await?) as DynFile) })
+    }
+
+    fn read_at(&self, file: &DynFile, offset: u64, len: usize) -> VfsFuture<Bytes> {
+        let file = file
+            .clone()
+            .downcast::<V::File>()
+            .expect("the file is opened by the vfs");
+        AsyncVfs::read_at(self, file, offset, len)
+    }
+}
+
+/// The virtual filesystem of the service, the meta of the file is tagged by the
+/// source to be read by `read_at`, except the local files under the root.
+#[derive(Clone)]
+pub(crate) struct Vfs {
+    inner: Arc<dyn DynVfs>,
+    // the local file is read by the file reader as usual, e.g. mapped or cached.
+    is_local: bool,
+}
+
+impl Debug for Vfs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vfs")
+            .field("is_local", &self.is_local)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Vfs {
+    pub fn new(vfs: impl AsyncVfs) -> Self {
+        Self {
+            inner: Arc::new(vfs),
+            is_local: false,
+        }
+    }
+
+    /// the local files under the root, the default of the service.
+    pub fn local(root: impl Into<PathBuf>, policy: SymlinkPolicy) -> Self {
+        Self {
+            inner: Arc::new(LocalFs::new(root).symlink_policy(policy)),
+            is_local: true,
+        }
+    }
+
+    pub fn metadata(&self, path: &str) -> VfsFuture<FileMeta> {
+        let meta = self.inner.metadata(path);
+        if self.is_local {
+            return meta;
+        }
+        let source = Source::Vfs {
+            vfs: self.clone(),
+            path: path.to_string(),
+        };
+        Box::pin(async move {
+            let mut meta = meta.await?;
+            meta.source = Some(source);
+            Ok(meta)
+        })
+    }
+
+    pub fn read_dir(&self, path: &str) -> VfsFuture<Vec<DirEntry>> {
+        self.inner.read_dir(path)
+    }
+
+    /// open the reader of the file at the beginning.
+    pub async fn open(&self, meta: &FileMeta) -> Result<VfsReader> {
+        let file = self.inner.open(meta).await?;
+        Ok(VfsReader {
+            vfs: self.inner.clone(),
+            file,
+            size: meta.size,
+            position: 0,
+            reading: None,
+        })
+    }
+
+    /// read the whole content of the file, e.g. to hash or cache it.
+    pub async fn read_all(&self, meta: &FileMeta, chunk_size: usize) -> Result<Bytes> {
+        let file = self.inner.open(meta).await?;
+        let mut content = Vec::with_capacity(meta.size as usize);
+        loop {
+            let chunk = self
+                .inner
+                .read_at(&file, content.len() as u64, chunk_size)
+                .await?;
+            if chunk.is_empty() {
+                return Ok(content.into());
+            }
+            content.extend_from_slice(&chunk);
+        }
+    }
+}
+
+/// The reader of the file opened by the vfs, the chunk is read at the position.
+pub(crate) struct VfsReader {
+    vfs: Arc<dyn DynVfs>,
+    file: DynFile,
+    size: u64,
+    position: u64,
+    // the read in flight, dropped by the seek.
+    reading: Option<VfsFuture<Bytes>>,
+}
+
+impl Debug for VfsReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VfsReader")
+            .field("size", &self.size)
+            .field("position", &self.position)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VfsReader {
+    pub fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        readn: u64,
+        chunk_size: usize,
+    ) -> Poll<Result<Bytes>> {
+        let len = min(readn, chunk_size as u64) as usize;
+        if self.reading.is_none() {
+            self.reading = Some(self.vfs.read_at(&self.file, self.position, len));
+        }
+        let reading = self.reading.as_mut().unwrap();
+        let mut chunk = match reading.as_mut().poll(cx) {
+            Poll::Ready(chunk) => {
+                self.reading = None;
+                chunk?
+            }
+            Poll::Pending => return Poll::Pending,
+        };
+        chunk.truncate(len);
+        self.position += chunk.len() as u64;
+        Poll::Ready(Ok(chunk))
+    }
+
+    pub fn start_seek(&mut self, position: SeekFrom) -> Result<()> {
+        let position = match position {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.size.checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        self.position =
+            position.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek position"))?;
+        self.reading = None;
+        Ok(())
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+/// the relative path of the file of the vfs, `None` if it is the local file.
+pub(crate) fn vfs_path(meta: &FileMeta) -> Option<(&Vfs, &str)> {
+    match meta.source {
+        Some(Source::Vfs { ref vfs, ref path }) => Some((vfs, path)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_fs() {
+        let dir = std::env::temp_dir().join("blockless-vfs");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/a.txt"), b"0123456789").unwrap();
+        let vfs = Vfs::new(LocalFs::new(&dir));
+        let meta = vfs.metadata("sub/a.txt").await.unwrap();
+        assert!(matches!(vfs_path(&meta), Some((_, "sub/a.txt"))));
+        assert_eq!(vfs.read_all(&meta, 3).await.unwrap(), "0123456789");
+
+        let mut reader = vfs.open(&meta).await.unwrap();
+        reader.start_seek(SeekFrom::End(-4)).unwrap();
+        let chunk = std::future::poll_fn(|cx| reader.poll_read(cx, 2, 3)).await;
+        assert_eq!(chunk.unwrap(), "67");
+        assert_eq!(reader.position(), 8);
+
+        let entries = vfs.read_dir("sub").await.unwrap();
+        assert_eq!(entries[0].name, "a.txt");
+        let local = Vfs::local(&dir, SymlinkPolicy::Follow);
+        assert!(local.metadata("sub").await.unwrap().source.is_none());
+    }
+}
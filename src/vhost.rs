@@ -0,0 +1,198 @@
+use std::{
+    future::Future,
+    io::{Error, Result},
+    pin::Pin,
+    result::Result as StdResult,
+    task::{Context, Poll},
+};
+
+use hyper::{header, service::Service, Request, Response, StatusCode};
+
+use crate::{body::Body, filesvr::FileService};
+
+/// the lowercase host of the request without the port, from the `Host` header
+/// or the authority of the uri, e.g. the http/2 request.
+fn request_host<B>(request: &Request<B>) -> Option<String> {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| request.uri().host())?;
+    // the ipv6 address is bracketed, e.g. `[::1]:8080`.
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(rest),
+        None => host.split(':').next().unwrap_or(host),
+    };
+    Some(host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// The service selects the file service by the `Host` header, so one listener
+/// serves many sites. the host pattern is exact, e.g. `example.com`, or wildcard,
+/// e.g. `*.example.com` which matches the subdomains only.
+/// the exact host is preferred, then the longest wildcard, then the default host.
+#[derive(Clone, Default)]
+pub struct VirtualHostService {
+    hosts: Vec<(String, FileService)>,
+    // the suffixes of the wildcard hosts with the leading `.`, sorted by the length descending.
+    wildcards: Vec<(String, FileService)>,
+    default: Option<FileService>,
+}
+
+impl VirtualHostService {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// serve the host pattern by the file service.
+    pub fn host(mut self, pattern: &str, service: FileService) -> Self {
+        let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+        match pattern.strip_prefix('*') {
+            Some(suffix) => {
+                let suffix = suffix.to_string();
+                self.wildcards.retain(|(s, _)| s != &suffix);
+                self.wildcards.push((suffix, service));
+                self.wildcards
+                    .sort_by_key(|(s, _)| std::cmp::Reverse(s.len()));
+            }
+            None => {
+                self.hosts.retain(|(h, _)| h != &pattern);
+                self.hosts.push((pattern, service));
+            }
+        }
+        self
+    }
+
+    /// serve the request of the unknown host or without the host.
+    pub fn default_host(mut self, service: FileService) -> Self {
+        self.default = Some(service);
+        self
+    }
+
+    fn find(&self, host: Option<&str>) -> Option<&FileService> {
+        let service = host.and_then(|host| {
+            self.hosts
+                .iter()
+                .find(|(h, _)| h == host)
+                .or_else(|| {
+                    self.wildcards
+                        .iter()
+                        .find(|(suffix, _)| host.len() > suffix.len() && host.ends_with(suffix))
+                })
+                .map(|(_, service)| service)
+        });
+        service.or(self.default.as_ref())
+    }
+}
+
+impl<B> Service<Request<B>> for VirtualHostService
+where
+    B: Sync + Send + 'static,
+{
+    type Response = Response<Body>;
+
+    type Error = Error;
+
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response>> + Send>>;
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let host = request_host(&request);
+        match self.find(host.as_deref()) {
+            Some(service) => service.clone().call(request),
+            None => Box::pin(async {
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::Empty)
+                    .map_err(Error::other)
+            }),
+        }
+    }
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[derive(Clone)]
+pub struct VirtualHostServiceMaker {
+    service: VirtualHostService,
+}
+
+/// make the connection services from the configured virtual hosts.
+impl From<VirtualHostService> for VirtualHostServiceMaker {
+    fn from(service: VirtualHostService) -> Self {
+        Self { service }
+    }
+}
+
+impl<T> Service<T> for VirtualHostServiceMaker {
+    type Response = VirtualHostService;
+
+    type Error = hyper::Error;
+
+    type Future = Pin<Box<dyn Future<Output = StdResult<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<StdResult<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: T) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move { Ok(service) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request_host() {
+        let host = |h: &str| {
+            let r = Request::get("/").header(header::HOST, h).body(()).unwrap();
+            request_host(&r).unwrap()
+        };
+        assert_eq!(host("Example.COM:8080"), "example.com");
+        assert_eq!(host("example.com."), "example.com");
+        assert_eq!(host("[::1]:8080"), "::1");
+        let r = Request::get("http://a.com/x").body(()).unwrap();
+        assert_eq!(request_host(&r).unwrap(), "a.com");
+    }
+
+    #[tokio::test]
+    async fn test_virtual_host() {
+        let dir = std::env::temp_dir().join("blockless-vhost");
+        for name in ["a", "b", "any", "default"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(dir.join(name).join("index.html"), name).unwrap();
+        }
+        let service = |name: &str| FileService::new(dir.join(name).to_str().unwrap());
+        let mut vhost = VirtualHostService::new()
+            .host("a.example.com", service("a"))
+            .host("*.b.example.com", service("b"))
+            .host("*.example.com", service("any"))
+            .default_host(service("default"));
+        for (host, expected) in [
+            ("a.example.com", "a"),
+            ("x.b.example.com", "b"),
+            ("b.example.com", "any"),
+            ("c.example.com:80", "any"),
+            ("example.com", "default"),
+        ] {
+            let req = Request::get("/")
+                .header(header::HOST, host)
+                .body(())
+                .unwrap();
+            let resp = vhost.call(req).await.unwrap();
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(&body[..], expected.as_bytes(), "{host}");
+        }
+
+        let mut vhost = VirtualHostService::new().host("a.example.com", service("a"));
+        let req = Request::get("/")
+            .header(header::HOST, "x.com")
+            .body(())
+            .unwrap();
+        let resp = vhost.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    io::{Read, Result, Seek, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+/// The file read inline on wasi instead of the tokio file, there is neither the
+/// blocking pool nor the thread to run the file operations. without the `tokio`
+/// feature the file is read by the blocking executor, only the mapped file is kept.
+#[derive(Debug)]
+pub(crate) struct File {
+    file: std::fs::File,
+}
+
+impl File {
+    pub fn from_std(file: std::fs::File) -> Self {
+        Self { file }
+    }
+
+    /// never fails, the operation is never in flight.
+    pub fn try_into_std(self) -> std::result::Result<std::fs::File, Self> {
+        Ok(self.file)
+    }
+}
+
+impl AsyncRead for File {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let n = self.get_mut().file.read(buf.initialize_unfilled())?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for File {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> Result<()> {
+        self.get_mut().file.seek(position)?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<u64>> {
+        Poll::Ready(self.get_mut().file.stream_position())
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    io::{Error, Result},
+    mem::size_of,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+const WATCH_MASK: u32 = libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB
+    | libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO;
+
+const EVENT_BUF_SIZE: usize = 64 * 1024;
+
+/// The inotify instance watching the directories under the root.
+struct Inotify {
+    fd: libc::c_int,
+    // the watched directories by the watch descriptor.
+    dirs: HashMap<libc::c_int, PathBuf>,
+}
+
+/// The eventfd waking the watching thread to exit when the watcher is dropped.
+struct Wake {
+    fd: libc::c_int,
+}
+
+impl Wake {
+    fn new() -> Result<Self> {
+        // SAFETY: the flags are valid.
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Self { fd })
+    }
+
+    fn wake(&self) {
+        let one = 1u64;
+        // SAFETY: the value is valid for the length.
+        unsafe { libc::write(self.fd, &one as *const u64 as *const _, size_of::<u64>()) };
+    }
+}
+
+impl Drop for Wake {
+    fn drop(&mut self) {
+        // SAFETY: the fd is owned by the instance.
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+impl Inotify {
+    fn new() -> Result<Self> {
+        // SAFETY: the flags are valid.
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Self {
+            fd,
+            dirs: HashMap::new(),
+        })
+    }
+
+    /// watch the directory and the sub directories, inotify is not recursive.
+    fn watch_dir(&mut self, dir: &Path) -> Result<()> {
+        let path = CString::new(dir.as_os_str().as_bytes())?;
+        // SAFETY: the path is the valid C string.
+        let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            return Err(Error::last_os_error());
+        }
+        self.dirs.insert(wd, dir.to_path_buf());
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            // the symbolic link is not followed.
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                // the directory removed while watching is ignored.
+                let _ = self.watch_dir(&entry.path());
+            }
+        }
+        Ok(())
+    }
+
+    /// wait for the events, `false` if it is woken to exit.
+    fn wait(&self, wake: &Wake) -> bool {
+        let mut fds = [
+            libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: wake.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        loop {
+            // SAFETY: the fds are valid for the length.
+            let n = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) };
+            if n < 0 && Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return n > 0 && fds[1].revents == 0;
+        }
+    }
+
+    /// read the events and send the changed paths until the watcher is dropped.
+    fn run(mut self, root: PathBuf, sender: Sender<PathBuf>, wake: Arc<Wake>) {
+        let mut buf = vec![0u8; EVENT_BUF_SIZE];
+        loop {
+            if !self.wait(&wake) {
+                return;
+            }
+            // SAFETY: the buffer is valid for the length.
+            let n = unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut _, buf.len()) };
+            if n < 0 {
+                if Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return;
+            }
+            let mut offset = 0;
+            while offset + size_of::<libc::inotify_event>() <= n as usize {
+                // SAFETY: the kernel writes the complete events into the buffer.
+                let event: libc::inotify_event =
+                    unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr() as *const _) };
+                let name_start = offset + size_of::<libc::inotify_event>();
+                let name = &buf[name_start..name_start + event.len as usize];
+                offset = name_start + event.len as usize;
+                let name = OsStr::from_bytes(name.split(|b| *b == 0).next().unwrap_or_default());
+                let changed = match self.dirs.get(&event.wd) {
+                    // the events are dropped, invalidate all.
+                    _ if event.mask & libc::IN_Q_OVERFLOW != 0 => root.clone(),
+                    Some(dir) if name.is_empty() => dir.clone(),
+                    Some(dir) => dir.join(name),
+                    None => continue,
+                };
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.dirs.remove(&event.wd);
+                }
+                let is_new_dir = event.mask & libc::IN_ISDIR != 0
+                    && event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0;
+                if is_new_dir {
+                    let _ = self.watch_dir(&changed);
+                }
+                if sender.send(changed).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        // SAFETY: the fd is owned by the instance.
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// The watcher of the changed paths under the root, the watching thread is woken
+/// and joined when the watcher is dropped.
+pub(crate) struct Watcher {
+    changes: Mutex<Receiver<PathBuf>>,
+    wake: Arc<Wake>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let mut inotify = Inotify::new()?;
+        inotify.watch_dir(&root)?;
+        let wake = Arc::new(Wake::new()?);
+        let (sender, receiver) = mpsc::channel();
+        let thread = {
+            let wake = wake.clone();
+            std::thread::Builder::new()
+                .name("hyper-file-watcher".into())
+                .spawn(move || inotify.run(root, sender, wake))?
+        };
+        Ok(Self {
+            changes: Mutex::new(receiver),
+            wake,
+            thread: Some(thread),
+        })
+    }
+
+    /// the paths changed since the last call, the changed directory means
+    /// all the files under it are changed.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        self.changes.lock().unwrap().try_iter().collect()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.wake.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn wait_changed(watcher: &Watcher, path: &Path) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if watcher.changed().iter().any(|p| p == path) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn test_watcher() {
+        let root = std::env::temp_dir().join("blockless-watch");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        let watcher = Watcher::new(&root).unwrap();
+        std::fs::write(root.join("a/file.txt"), b"hello").unwrap();
+        assert!(wait_changed(&watcher, &root.join("a/file.txt")));
+
+        // the new directory is watched too.
+        std::fs::create_dir_all(root.join("b")).unwrap();
+        assert!(wait_changed(&watcher, &root.join("b")));
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::write(root.join("b/file.txt"), b"hello").unwrap();
+        assert!(wait_changed(&watcher, &root.join("b/file.txt")));
+
+        // the thread exits without the next change.
+        let thread = watcher.thread.as_ref().unwrap().thread().clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            drop(watcher);
+            let _ = sender.send(());
+        });
+        let dropped = receiver.recv_timeout(Duration::from_secs(5));
+        assert!(
+            dropped.is_ok(),
+            "the thread {:?} is not exited",
+            thread.name()
+        );
+    }
+}
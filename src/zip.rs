@@ -0,0 +1,205 @@
+use std::{
+    fs::File,
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+};
+
+use crate::archive::{ArchiveIndex, Member, Method};
+
+const EOCD_SIGNATURE: u32 = 0x06054b50;
+const CENTRAL_SIGNATURE: u32 = 0x02014b50;
+const LOCAL_SIGNATURE: u32 = 0x04034b50;
+const EOCD_SIZE: usize = 22;
+const CENTRAL_SIZE: usize = 46;
+const LOCAL_SIZE: usize = 30;
+// the comment of the archive is 64KiB at most.
+const EOCD_SEARCH_SIZE: u64 = EOCD_SIZE as u64 + 0xffff;
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid zip archive: {msg}"),
+    )
+}
+
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+/// find the end of central directory record, return the offset and the size
+/// of the central directory.
+fn central_directory(file: &mut File) -> Result<(u64, usize, usize)> {
+    let len = file.metadata()?.len();
+    if len < EOCD_SIZE as u64 {
+        return Err(invalid("too short"));
+    }
+    let start = len.saturating_sub(EOCD_SEARCH_SIZE);
+    let mut buf = vec![0u8; (len - start) as usize];
+    read_at(file, start, &mut buf)?;
+    let pos = (0..=buf.len() - EOCD_SIZE)
+        .rev()
+        .find(|i| u32_at(&buf, *i) == EOCD_SIGNATURE)
+        .ok_or_else(|| invalid("no end of central directory"))?;
+    let eocd = &buf[pos..];
+    let entries = u16_at(eocd, 10);
+    let size = u32_at(eocd, 12);
+    let offset = u32_at(eocd, 16);
+    if entries == 0xffff || size == 0xffff_ffff || offset == 0xffff_ffff {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "the zip64 archive is not supported",
+        ));
+    }
+    Ok((offset as u64, size as usize, entries as usize))
+}
+
+/// build the index of the zip archive by the central directory, the encrypted
+/// members and the members compressed by other methods than deflate are skipped.
+pub(crate) fn index(file: &mut File) -> Result<ArchiveIndex> {
+    let (offset, size, entries) = central_directory(file)?;
+    let mut central = vec![0u8; size];
+    read_at(file, offset, &mut central)?;
+    let mut index = ArchiveIndex::default();
+    let mut pos = 0;
+    for _ in 0..entries {
+        let header = central
+            .get(pos..pos + CENTRAL_SIZE)
+            .filter(|h| u32_at(h, 0) == CENTRAL_SIGNATURE)
+            .ok_or_else(|| invalid("bad central directory"))?;
+        let flags = u16_at(header, 8);
+        let method = u16_at(header, 10);
+        let compressed_size = u32_at(header, 20) as u64;
+        let size = u32_at(header, 24) as u64;
+        let name_len = u16_at(header, 28) as usize;
+        let extra_len = u16_at(header, 30) as usize;
+        let comment_len = u16_at(header, 32) as usize;
+        let local_offset = u32_at(header, 42) as u64;
+        let name = central
+            .get(pos + CENTRAL_SIZE..pos + CENTRAL_SIZE + name_len)
+            .ok_or_else(|| invalid("bad central directory"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        pos += CENTRAL_SIZE + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            index.insert(&name, None);
+            continue;
+        }
+        let method = match method {
+            0 => Method::Stored,
+            8 => Method::Deflated,
+            _ => continue,
+        };
+        if flags & 1 != 0 {
+            continue;
+        }
+        // the data follows the local header, which has its own name and extra field.
+        let mut local = [0u8; LOCAL_SIZE];
+        read_at(file, local_offset, &mut local)?;
+        if u32_at(&local, 0) != LOCAL_SIGNATURE {
+            return Err(invalid("bad local header"));
+        }
+        let data_offset = local_offset
+            + LOCAL_SIZE as u64
+            + u16_at(&local, 26) as u64
+            + u16_at(&local, 28) as u64;
+        let member = Member {
+            method,
+            offset: data_offset,
+            compressed_size,
+            size,
+            skip: 0,
+        };
+        index.insert(&name, Some(member));
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use flate2::{write::DeflateEncoder, Compression};
+    use std::io::Write;
+
+    /// write the zip archive of the files, the deflated files are compressed.
+    pub(crate) fn write_zip(path: &std::path::Path, files: &[(&str, &[u8], bool)]) {
+        let mut data = Vec::new();
+        let mut central = Vec::new();
+        for (name, content, deflated) in files {
+            let compressed = if *deflated {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                content.to_vec()
+            };
+            let method: u16 = if *deflated { 8 } else { 0 };
+            let offset = data.len() as u32;
+            let mut header = Vec::new();
+            header.extend_from_slice(&method.to_le_bytes());
+            header.extend_from_slice(&[0; 8]); // time, date and crc.
+            header.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+
+            data.extend_from_slice(&LOCAL_SIGNATURE.to_le_bytes());
+            data.extend_from_slice(&[20, 0, 0, 0]);
+            data.extend_from_slice(&header);
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&compressed);
+
+            central.extend_from_slice(&CENTRAL_SIGNATURE.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&header);
+            central.extend_from_slice(&[0; 12]);
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let offset = data.len() as u32;
+        data.extend_from_slice(&central);
+        data.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_index() {
+        let path = std::env::temp_dir().join("blockless-zip/bundle.zip");
+        write_zip(
+            &path,
+            &[
+                ("app.js", b"console.log(1)", false),
+                ("css/site.css", b"body {}", true),
+                ("../evil.txt", b"evil", false),
+            ],
+        );
+        let index = index(&mut File::open(&path).unwrap()).unwrap();
+        let mut content = String::new();
+        let source = crate::archive::Source::Archive {
+            archive: path.clone(),
+            member: index.member("css/site.css").unwrap().clone(),
+        };
+        source
+            .open_std()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "body {}");
+        assert_eq!(index.member("app.js").unwrap().method, Method::Stored);
+        assert!(index.member("../evil.txt").is_none());
+        assert!(index.member("evil.txt").is_none());
+    }
+}